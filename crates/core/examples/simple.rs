@@ -4,7 +4,7 @@ use jobfire_core::{
     domain::job::{
         Job,
         context::{Context, ContextData},
-        error::{JobError, JobResult},
+        error::{CustomError, JobError, JobResult},
         r#impl::{JobImpl, JobImplName},
         report::Report,
     },
@@ -48,16 +48,16 @@ impl JobImpl<SimpleContextData> for SimpleJobImpl {
 
     async fn run(&self, _context: Context<SimpleContextData>) -> JobResult<Report> {
         log::info!("job number started");
-        Err(JobError::Custom {
-            message: "xd".to_owned(),
-        })
+        Err(JobError::Custom(CustomError::new("xd")))
     }
 
     async fn on_success(&self, _context: Context<SimpleContextData>) {
         log::info!("on_sucess ran: {}", self.id);
     }
-    async fn on_fail(&self, _context: Context<SimpleContextData>) {
-        log::info!("on_fail ran: {}", self.id);
+    async fn on_fail(&self, context: Context<SimpleContextData>) {
+        let context = context.data();
+        context.increment();
+        log::info!("on_fail ran: {}, {} jobs failed", self.id, context.read());
     }
 }
 
@@ -1,6 +1,11 @@
 use std::sync::Arc;
 
-use crate::services::Services;
+use chrono::{DateTime, Utc};
+use tokio_util::sync::CancellationToken;
+
+use crate::{domain::run::id::RunId, services::Services};
+
+use super::id::JobId;
 
 /// Marker trait for context data accessible from jobs.
 /// Types implementing this must be `Send` + `Sync` + `'static`.
@@ -11,6 +16,7 @@ pub trait ContextData: Send + Sync + 'static {}
 pub struct Context<TData: ContextData> {
     data: Arc<TData>,
     services: Services,
+    run_info: Option<RunInfo>,
 }
 
 impl<TData: ContextData> Clone for Context<TData> {
@@ -18,6 +24,7 @@ impl<TData: ContextData> Clone for Context<TData> {
         Self {
             data: self.data.clone(),
             services: self.services.clone(),
+            run_info: self.run_info.clone(),
         }
     }
 }
@@ -27,6 +34,39 @@ impl<TData: ContextData> Context<TData> {
         Self {
             data: Arc::new(data.into()),
             services,
+            run_info: None,
+        }
+    }
+
+    /// Creates a copy of this context bound to a specific job run.
+    pub fn with_run_info(&self, run_info: RunInfo) -> Self {
+        Self {
+            data: self.data.clone(),
+            services: self.services.clone(),
+            run_info: Some(run_info),
+        }
+    }
+
+    /// Information about the run this context was created for.
+    /// `None` outside of job execution.
+    pub fn run_info(&self) -> Option<&RunInfo> {
+        self.run_info.as_ref()
+    }
+
+    /// Whether cancellation of the current run has been requested.
+    /// Always `false` outside of job execution.
+    pub fn is_cancelled(&self) -> bool {
+        self.run_info
+            .as_ref()
+            .is_some_and(|run_info| run_info.cancellation_token().is_cancelled())
+    }
+
+    /// Completes once cancellation of the current run has been requested.
+    /// Never completes outside of job execution.
+    pub async fn cancelled(&self) {
+        match &self.run_info {
+            Some(run_info) => run_info.cancellation_token().cancelled().await,
+            None => std::future::pending().await,
         }
     }
 
@@ -47,6 +87,60 @@ impl<TData: ContextData> Context<TData> {
     }
 }
 
+/// Identifies the job run a `Context` has been handed to.
+#[derive(Clone, Debug)]
+pub struct RunInfo {
+    job_id: JobId,
+    run_id: RunId,
+    scheduled_at: DateTime<Utc>,
+    started_at: DateTime<Utc>,
+    cancellation_token: CancellationToken,
+}
+
+impl RunInfo {
+    pub fn new(
+        job_id: JobId,
+        run_id: RunId,
+        scheduled_at: DateTime<Utc>,
+        started_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            job_id,
+            run_id,
+            scheduled_at,
+            started_at,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    pub fn job_id(&self) -> JobId {
+        self.job_id
+    }
+
+    pub fn run_id(&self) -> RunId {
+        self.run_id
+    }
+
+    /// Time the run was scheduled at, which may be earlier than the time it started.
+    pub fn scheduled_at(&self) -> DateTime<Utc> {
+        self.scheduled_at
+    }
+
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    /// Token cancelled when cancellation of the run is requested, e.g. through `JobManager::cancel`.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+}
+
 pub struct EmptyContextData;
 
 impl ContextData for EmptyContextData {}
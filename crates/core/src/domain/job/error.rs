@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use thiserror::Error;
 
 /// Possible errors returned from job run.
@@ -10,14 +11,198 @@ pub enum JobError {
     /// Job has been cancelled
     #[error("job has been cancelled")]
     JobCancelled,
-    /// Policy stopped
+    /// Policy stopped, e.g. the run has timed out
     #[error("job has been stopped by policy")]
     PolicyShortCircuit,
     #[error("policy not found")]
     PolicyNotFound,
-    /// custom message reserved for user defined errors
-    #[error("job failed: {message}")]
-    Custom { message: String },
+    /// Run has been left behind by a worker that is gone, e.g. after a crash
+    #[error("job run has been orphaned")]
+    Orphaned,
+    /// Run has been put off by a policy without executing the job, e.g. when a rate limit
+    /// is exceeded. The policy schedules the job again and no failed run is recorded
+    #[error("job run has been deferred")]
+    Deferred,
+    /// reserved for user defined errors
+    #[error("job failed: {0}")]
+    Custom(CustomError),
+}
+
+impl JobError {
+    /// Whether running the job again may succeed.
+    ///
+    /// Retry policies only retry errors for which this is `true`.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            JobError::PolicyShortCircuit => true,
+            JobError::Custom(error) => error.is_retryable(),
+            JobError::JobImplBuildFailed
+            | JobError::JobCancelled
+            | JobError::PolicyNotFound
+            | JobError::Orphaned
+            | JobError::Deferred => false,
+        }
+    }
+}
+
+impl From<CustomError> for JobError {
+    fn from(value: CustomError) -> Self {
+        JobError::Custom(value)
+    }
+}
+
+/// User defined error of a job run, stored with its `FailedRun`.
+///
+/// Errors are retryable unless marked with `CustomError::permanent`.
+#[derive(Error, Clone, Serialize, Deserialize, Debug)]
+#[error("{message}")]
+pub struct CustomError {
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    #[serde(default = "default_retryable")]
+    retryable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<String>,
+}
+
+fn default_retryable() -> bool {
+    true
+}
+
+impl CustomError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            kind: None,
+            retryable: true,
+            details: None,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Creates an error with the message of `error` and the messages of its sources.
+    pub fn from_error(error: &(dyn std::error::Error + 'static)) -> Self {
+        Self::new(error.to_string()).with_sources_of(error)
+    }
+
+    /// Sets a kind or code used to classify the error, e.g. `"network"`.
+    pub fn with_kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = Some(kind.into());
+        self
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// Marks the error as one that won't go away by running the job again.
+    pub fn permanent(self) -> Self {
+        self.with_retryable(false)
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Appends `source` and its own sources to the source chain.
+    pub fn with_source(mut self, source: &(dyn std::error::Error + 'static)) -> Self {
+        self.sources.push(source.to_string());
+        self.with_sources_of(source)
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    pub fn details(&self) -> Option<&Value> {
+        self.details.as_ref()
+    }
+
+    /// Messages of the source chain, starting with the direct cause.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    fn with_sources_of(mut self, error: &(dyn std::error::Error + 'static)) -> Self {
+        let mut source = error.source();
+        while let Some(error) = source {
+            self.sources.push(error.to_string());
+            source = error.source();
+        }
+        self
+    }
 }
 
 pub type JobResult<T> = std::result::Result<T, JobError>;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Error, Debug)]
+    #[error("request failed")]
+    struct RequestError {
+        #[source]
+        source: std::io::Error,
+    }
+
+    #[test]
+    fn test_source_chain() {
+        let error = RequestError {
+            source: std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"),
+        };
+
+        let custom = CustomError::from_error(&error).with_kind("network");
+
+        assert_eq!(custom.message(), "request failed");
+        assert_eq!(custom.kind(), Some("network"));
+        assert_eq!(custom.sources(), ["timed out"]);
+        assert!(JobError::from(custom).is_retryable());
+    }
+
+    #[test]
+    fn test_serialization_round_trip() {
+        let error: JobError = CustomError::new("invalid input")
+            .with_kind("validation")
+            .with_details(json!({ "field": "email" }))
+            .permanent()
+            .into();
+
+        let serialized = serde_json::to_string(&error).unwrap();
+        let deserialized: JobError = serde_json::from_str(&serialized).unwrap();
+
+        let JobError::Custom(custom) = &deserialized else {
+            panic!("expected custom error");
+        };
+        assert_eq!(custom.kind(), Some("validation"));
+        assert_eq!(custom.details(), Some(&json!({ "field": "email" })));
+        assert!(!deserialized.is_retryable());
+    }
+
+    #[test]
+    fn test_deserialize_message_only() {
+        let error: JobError = serde_json::from_str(r#"{"Custom":{"message":"failed"}}"#).unwrap();
+
+        let JobError::Custom(custom) = &error else {
+            panic!("expected custom error");
+        };
+        assert_eq!(custom.message(), "failed");
+        assert!(custom.sources().is_empty());
+        assert!(error.is_retryable());
+    }
+}
@@ -4,6 +4,9 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
+/// Namespace of ids derived from job keys with `JobId::from_key`.
+const KEY_NAMESPACE: Uuid = uuid::uuid!("0d3c5e4a-8f2b-4b6e-9a1d-7c5f3e2b1a90");
+
 /// Unique identifier for a job.
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
 pub struct JobId(Uuid);
@@ -13,6 +16,11 @@ impl JobId {
         Self(uuid)
     }
 
+    /// Creates an id derived from `key`, so the same key always maps to the same job.
+    pub fn from_key(key: &str) -> Self {
+        Self::new(Uuid::new_v5(&KEY_NAMESPACE, key.as_bytes()))
+    }
+
     pub fn value(&self) -> &Uuid {
         &self.0
     }
@@ -65,4 +73,10 @@ mod tests {
 
         assert_eq!(uuid, job_id.0);
     }
+
+    #[test]
+    fn test_from_key() {
+        assert_eq!(JobId::from_key("report"), JobId::from_key("report"));
+        assert_ne!(JobId::from_key("report"), JobId::from_key("cleanup"));
+    }
 }
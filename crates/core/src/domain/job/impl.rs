@@ -6,7 +6,7 @@ use super::{
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::Value;
-use std::sync::Arc;
+use std::{fmt::Display, sync::Arc};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -28,6 +28,12 @@ impl JobImplName {
     }
 }
 
+impl Display for JobImplName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[async_trait]
 pub trait JobImpl<TData: ContextData>:
     Serialize + DeserializeOwned + Sized + Send + Sync + 'static
@@ -36,6 +42,12 @@ pub trait JobImpl<TData: ContextData>:
     async fn run(&self, context: Context<TData>) -> JobResult<Report>;
     async fn on_fail(&self, context: Context<TData>);
     async fn on_success(&self, context: Context<TData>);
+
+    /// Whether finished jobs of this impl are kept by the finished job retention,
+    /// e.g. because they are rescheduled later on.
+    fn retain_when_finished() -> bool {
+        false
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
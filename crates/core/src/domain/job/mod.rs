@@ -1,8 +1,9 @@
 use chrono::{DateTime, Utc};
 use context::ContextData;
 use id::JobId;
-use policy::{Policies, Policy, PolicyData};
 use r#impl::{JobImpl, SerializedJobImpl};
+use policy::{Policies, Policy, PolicyData};
+use recovery::RecoveryPolicy;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -12,8 +13,10 @@ pub mod id;
 pub mod r#impl;
 pub mod pending;
 pub mod policy;
+pub mod recovery;
 pub mod report;
 pub mod running;
+pub mod status;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -48,6 +51,10 @@ pub struct Job {
 
     /// Policies and policy data invoked for this job
     policies: Policies,
+
+    /// What happens with a run of this job that is left behind by a crashed worker.
+    #[serde(default)]
+    recovery_policy: RecoveryPolicy,
 }
 
 impl Job {
@@ -62,9 +69,15 @@ impl Job {
             created_at,
             r#impl,
             policies,
+            recovery_policy: RecoveryPolicy::default(),
         }
     }
 
+    pub fn with_recovery_policy(mut self, recovery_policy: RecoveryPolicy) -> Self {
+        self.recovery_policy = recovery_policy;
+        self
+    }
+
     pub fn id(&self) -> JobId {
         self.id
     }
@@ -81,18 +94,32 @@ impl Job {
         &self.r#impl
     }
 
+    pub fn recovery_policy(&self) -> RecoveryPolicy {
+        self.recovery_policy
+    }
+
     /// Function to create a job from custom job implementation
     pub fn from_impl<TData: ContextData>(
         job_impl: impl JobImpl<TData>,
         now: DateTime<Utc>,
         policies: Vec<Box<dyn Policy<TData>>>,
+    ) -> Result<Self> {
+        Self::from_impl_with_id(JobId::default(), job_impl, now, policies)
+    }
+
+    /// Function to create a job with a given id from custom job implementation
+    pub fn from_impl_with_id<TData: ContextData>(
+        id: JobId,
+        job_impl: impl JobImpl<TData>,
+        now: DateTime<Utc>,
+        policies: Vec<Box<dyn Policy<TData>>>,
     ) -> Result<Self> {
         let data = PolicyData::default();
         for policy in policies.iter() {
             policy.init(data.clone());
         }
         Ok(Self::new(
-            JobId::default(),
+            id,
             now,
             SerializedJobImpl::from_job_impl(r#job_impl).map_err(|_| Error::BuildingJobFailed)?,
             Policies::new(policies.iter().map(|p| p.name()).collect::<Vec<_>>(), data),
@@ -102,4 +129,8 @@ impl Job {
     pub fn update_policies(&mut self, policies: Policies) {
         self.policies = policies;
     }
+
+    pub fn update_impl(&mut self, r#impl: SerializedJobImpl) {
+        self.r#impl = r#impl;
+    }
 }
@@ -1,5 +1,6 @@
-use super::context::ContextData;
+use super::{context::ContextData, error::JobError};
 use crate::domain::run::job_actions::{OnFailFn, OnSuccessFn, RunFn};
+use chrono::{DateTime, Utc};
 use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{from_value, to_value, Value};
 use std::{
@@ -56,6 +57,18 @@ pub trait Policy<TData: ContextData>: Send + Sync + 'static {
     fn wrap_on_success(&self, f: OnSuccessFn<TData>, _data: PolicyData) -> OnSuccessFn<TData> {
         f
     }
+    /// Time at which a failed run should be retried, `None` lets the job fail.
+    ///
+    /// The retry is scheduled in the transaction recording the failed run, together with
+    /// changes made to `data`, and the `on_fail` callback is invoked only once no retry is due.
+    fn retry_at(
+        &self,
+        _error: &JobError,
+        _data: PolicyData,
+        _now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        None
+    }
 }
 
 #[derive(Default, Debug)]
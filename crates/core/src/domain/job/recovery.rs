@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Decides what happens with a run that was left behind by a worker that is gone,
+/// e.g. because the process crashed mid-run.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryPolicy {
+    /// Records the run as a `FailedRun` with `JobError::Orphaned`.
+    #[default]
+    Fail,
+    /// Schedules the job to run again as soon as possible.
+    Requeue,
+}
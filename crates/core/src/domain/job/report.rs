@@ -1,16 +1,153 @@
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct Report {}
+use chrono::Duration;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use thiserror::Error;
 
-impl Default for Report {
-    fn default() -> Self {
-        Self::new()
-    }
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("serialization failed")]
+    SerializationFailed,
+    #[error("deserialization failed")]
+    DeserializationFailed,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Outcome of a successful run, stored with its `SuccessfulRun`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Report {
+    #[serde(default)]
+    output: Value,
+    #[serde(default)]
+    metrics: Metrics,
 }
 
 impl Report {
     pub fn new() -> Self {
-        Report {}
+        Self::default()
+    }
+
+    /// Sets the output of the run, read back with `Report::output`.
+    pub fn with_output<T: Serialize>(self, output: &T) -> Result<Self> {
+        let output = serde_json::to_value(output).map_err(|_| Error::SerializationFailed)?;
+        Ok(self.with_raw_output(output))
+    }
+
+    pub fn with_raw_output(mut self, output: Value) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Sets the number of items processed by the run.
+    pub fn with_item_count(mut self, item_count: u64) -> Self {
+        self.metrics.item_count = Some(item_count);
+        self
+    }
+
+    /// Sets the duration of the run, or of the part of it the job wants to report.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.metrics.duration_millis = Some(duration.num_milliseconds());
+        self
+    }
+
+    /// Sets a custom metric, replacing a previous value with the same key.
+    pub fn with_metric(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.metrics.custom.insert(key.into(), value.into());
+        self
+    }
+
+    /// Deserializes the output set with `Report::with_output`.
+    pub fn output<T: DeserializeOwned>(&self) -> Result<T> {
+        T::deserialize(&self.output).map_err(|_| Error::DeserializationFailed)
+    }
+
+    /// Output as stored, `Value::Null` if none has been set.
+    pub fn raw_output(&self) -> &Value {
+        &self.output
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+}
+
+/// Optional measurements reported by a successful run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Metrics {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    item_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration_millis: Option<i64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    custom: BTreeMap<String, Value>,
+}
+
+impl Metrics {
+    pub fn item_count(&self) -> Option<u64> {
+        self.item_count
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration_millis.map(Duration::milliseconds)
+    }
+
+    pub fn custom(&self) -> &BTreeMap<String, Value> {
+        &self.custom
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.custom.get(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Output {
+        processed: Vec<String>,
+    }
+
+    #[test]
+    fn test_typed_output_round_trip() {
+        let output = Output {
+            processed: vec!["a".to_owned(), "b".to_owned()],
+        };
+        let report = Report::new()
+            .with_output(&output)
+            .unwrap()
+            .with_item_count(2)
+            .with_duration(Duration::milliseconds(1500))
+            .with_metric("source", "import");
+
+        let serialized = serde_json::to_string(&report).unwrap();
+        let report: Report = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(report.output::<Output>().unwrap(), output);
+        assert_eq!(report.metrics().item_count(), Some(2));
+        assert_eq!(
+            report.metrics().duration(),
+            Some(Duration::milliseconds(1500))
+        );
+        assert_eq!(report.metrics().get("source"), Some(&Value::from("import")));
+    }
+
+    #[test]
+    fn test_deserialize_empty_report() {
+        let report: Report = serde_json::from_str("{}").unwrap();
+
+        assert!(report.raw_output().is_null());
+        assert!(report.output::<Output>().is_err());
+        assert!(report.metrics().custom().is_empty());
     }
 }
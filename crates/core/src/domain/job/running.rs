@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::domain::run::id::RunId;
+use crate::domain::{run::id::RunId, worker::id::WorkerId};
 
 use super::id::JobId;
 
@@ -27,14 +27,31 @@ pub struct RunningJob {
     ///
     /// Used for tracking execution time and potentially for timeout management.
     started_at: DateTime<Utc>,
+
+    /// Worker that holds the lease on this run.
+    worker_id: WorkerId,
+
+    /// Timestamp of the last heartbeat of the owning worker.
+    ///
+    /// The lease on the run expires when the worker stops renewing it,
+    /// after which other workers may reclaim the run.
+    heartbeat_at: DateTime<Utc>,
 }
 
 impl RunningJob {
-    pub fn new(job_id: JobId, run_id: RunId, started_at: DateTime<Utc>) -> Self {
+    pub fn new(
+        job_id: JobId,
+        run_id: RunId,
+        started_at: DateTime<Utc>,
+        worker_id: WorkerId,
+        heartbeat_at: DateTime<Utc>,
+    ) -> Self {
         Self {
             run_id,
             job_id,
             started_at,
+            worker_id,
+            heartbeat_at,
         }
     }
 
@@ -49,4 +66,16 @@ impl RunningJob {
     pub fn started_at(&self) -> DateTime<Utc> {
         self.started_at
     }
+
+    pub fn worker_id(&self) -> WorkerId {
+        self.worker_id
+    }
+
+    pub fn heartbeat_at(&self) -> DateTime<Utc> {
+        self.heartbeat_at
+    }
+
+    pub fn update_heartbeat_at(&mut self, heartbeat_at: DateTime<Utc>) {
+        self.heartbeat_at = heartbeat_at;
+    }
 }
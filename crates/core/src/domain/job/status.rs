@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::run::id::RunId;
+
+use super::error::JobError;
+
+/// Lifecycle state of a job, derived from storage.
+#[derive(Clone, Debug)]
+pub enum JobStatus {
+    /// Waiting to run at `at`.
+    Pending { at: DateTime<Utc> },
+    /// Running since `since`.
+    Running { since: DateTime<Utc>, run_id: RunId },
+    /// The last run of the job succeeded.
+    Succeeded {
+        run_id: RunId,
+        finished_at: DateTime<Utc>,
+    },
+    /// The last run of the job failed.
+    Failed {
+        run_id: RunId,
+        finished_at: DateTime<Utc>,
+        error: JobError,
+    },
+    /// The job was cancelled, either while pending or while running.
+    Cancelled {
+        run_id: RunId,
+        finished_at: DateTime<Utc>,
+    },
+    /// The job doesn't exist, has never run or its records have been purged.
+    Unknown,
+}
+
+impl JobStatus {
+    /// Whether the job won't change its state unless it's scheduled again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Succeeded { .. } | JobStatus::Failed { .. } | JobStatus::Cancelled { .. }
+        )
+    }
+}
@@ -1,2 +1,3 @@
 pub mod job;
 pub mod run;
+pub mod worker;
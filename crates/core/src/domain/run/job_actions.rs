@@ -30,6 +30,7 @@ pub struct JobActions<TData: ContextData> {
     run_fn: RunFn<TData>,
     on_success_fn: OnSuccessFn<TData>,
     on_fail_fn: OnFailFn<TData>,
+    retain_when_finished: bool,
 }
 
 impl<TData: ContextData> Clone for JobActions<TData> {
@@ -38,6 +39,7 @@ impl<TData: ContextData> Clone for JobActions<TData> {
             run_fn: self.run_fn.clone(),
             on_success_fn: self.on_success_fn.clone(),
             on_fail_fn: self.on_fail_fn.clone(),
+            retain_when_finished: self.retain_when_finished,
         }
     }
 }
@@ -52,6 +54,7 @@ impl<TData: ContextData> JobActions<TData> {
             run_fn: run,
             on_success_fn: on_success,
             on_fail_fn: on_fail,
+            retain_when_finished: false,
         }
     }
     pub fn from_job_impl<TJobImpl: JobImpl<TData>>() -> Self {
@@ -104,7 +107,10 @@ impl<TData: ContextData> JobActions<TData> {
             },
         );
 
-        Self::new(run, on_success, on_fail)
+        Self {
+            retain_when_finished: TJobImpl::retain_when_finished(),
+            ..Self::new(run, on_success, on_fail)
+        }
     }
 
     pub fn get_run_fn(&self) -> RunFn<TData> {
@@ -118,4 +124,8 @@ impl<TData: ContextData> JobActions<TData> {
     pub fn get_on_fail_fn(&self) -> OnFailFn<TData> {
         self.on_fail_fn.clone()
     }
+
+    pub fn retain_when_finished(&self) -> bool {
+        self.retain_when_finished
+    }
 }
@@ -1,4 +1,77 @@
+use chrono::{DateTime, Utc};
+use failed::FailedRun;
+use id::RunId;
+use serde::{Deserialize, Serialize};
+use successful::SuccessfulRun;
+
+use super::job::{error::JobError, id::JobId};
+
 pub mod failed;
 pub mod id;
 pub mod job_actions;
 pub mod successful;
+
+/// Finished run of a job, either successful or failed.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Run {
+    Successful(SuccessfulRun),
+    Failed(FailedRun),
+}
+
+impl Run {
+    pub fn run_id(&self) -> RunId {
+        match self {
+            Run::Successful(run) => run.run_id(),
+            Run::Failed(run) => run.run_id(),
+        }
+    }
+
+    pub fn job_id(&self) -> JobId {
+        match self {
+            Run::Successful(run) => run.job_id(),
+            Run::Failed(run) => run.job_id(),
+        }
+    }
+
+    pub fn scheduled_at(&self) -> DateTime<Utc> {
+        match self {
+            Run::Successful(run) => run.scheduled_at(),
+            Run::Failed(run) => run.scheduled_at(),
+        }
+    }
+
+    /// When the run started, None if it finished without starting, e.g. cancelled while pending.
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Run::Successful(run) => run.started_at(),
+            Run::Failed(run) => run.started_at(),
+        }
+    }
+
+    pub fn finished_at(&self) -> DateTime<Utc> {
+        match self {
+            Run::Successful(run) => run.finished_at(),
+            Run::Failed(run) => run.finished_at(),
+        }
+    }
+
+    /// Error of a failed run, None for a successful one.
+    pub fn error(&self) -> Option<&JobError> {
+        match self {
+            Run::Successful(_) => None,
+            Run::Failed(run) => Some(run.error()),
+        }
+    }
+}
+
+impl From<SuccessfulRun> for Run {
+    fn from(value: SuccessfulRun) -> Self {
+        Run::Successful(value)
+    }
+}
+
+impl From<FailedRun> for Run {
+    fn from(value: FailedRun) -> Self {
+        Run::Failed(value)
+    }
+}
@@ -11,6 +11,7 @@ pub struct SuccessfulRun {
     run_id: RunId,
     job_id: JobId,
     scheduled_at: DateTime<Utc>,
+    started_at: Option<DateTime<Utc>>,
     finished_at: DateTime<Utc>,
     report: Report,
 }
@@ -27,11 +28,18 @@ impl SuccessfulRun {
             run_id,
             job_id,
             scheduled_at,
+            started_at: None,
             finished_at,
             report,
         }
     }
 
+    /// Records when the run started, runs finished without starting have none.
+    pub fn with_started_at(mut self, started_at: DateTime<Utc>) -> Self {
+        self.started_at = Some(started_at);
+        self
+    }
+
     pub fn run_id(&self) -> RunId {
         self.run_id
     }
@@ -44,6 +52,10 @@ impl SuccessfulRun {
         self.scheduled_at
     }
 
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.started_at
+    }
+
     pub fn finished_at(&self) -> DateTime<Utc> {
         self.finished_at
     }
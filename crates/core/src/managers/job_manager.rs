@@ -1,24 +1,41 @@
 use super::job_scheduler::{self, JobScheduler};
 use crate::{
-    domain::job::{
-        Job,
-        context::{Context, ContextData},
-        id::JobId,
+    domain::{
+        job::{
+            Job,
+            context::{Context, ContextData},
+            error::JobError,
+            id::JobId,
+            pending::PendingJob,
+            status::JobStatus,
+        },
+        run::{Run, failed::FailedRun, successful::SuccessfulRun},
+        worker::id::WorkerId,
+    },
+    runners::{
+        cancellation::RunCancellations,
+        completion::{self, JobCompletion, RunCompletions},
+        job::JobRunner,
+        on_fail::OnFailRunner,
+        on_success::OnSuccessRunner,
+        recovery::RecoveryRunner,
+        retention::{RetentionRunner, RetentionSettings},
     },
-    runners::{job::JobRunner, on_fail::OnFailRunner, on_success::OnSuccessRunner},
     services::{
         Services,
+        notify::PendingJobNotifier,
+        rate_limit::RateLimiter,
         time::{AnyClock, SystemClock},
         verify::ServiceMissing,
     },
-    storage::{self, Storage},
+    storage::{self, Storage, query::Filter},
     util::r#async::poll_predicate,
     verify_services,
-    workers::job::{JobWorker, JobWorkerHandle, JobWorkerSettings, State},
+    workers::job::{JobWorker, JobWorkerHandle, JobWorkerSettings, State, StopSummary},
 };
 use chrono::{DateTime, Duration, Utc};
 use thiserror::Error;
-use tokio::time::interval;
+use tokio::{task, time};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -28,6 +45,8 @@ pub enum Error {
     Storage(#[from] storage::error::Error),
     #[error("scheduler error: {0}")]
     Scheduler(#[from] job_scheduler::Error),
+    #[error("completion error: {0}")]
+    Completion(#[from] completion::Error),
     #[error("failed to build a job")]
     JobBuildFailed,
     #[error("service missing: {0}")]
@@ -42,6 +61,7 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub struct JobManager<TData: ContextData> {
     context: Context<TData>,
     job_worker_handle: JobWorkerHandle,
+    retention_handle: Option<task::JoinHandle<()>>,
 }
 
 impl<TData: ContextData> JobManager<TData> {
@@ -76,8 +96,11 @@ impl<TData: ContextData> JobManager<TData> {
         verify_services!(
             services,
             JobWorkerSettings,
+            PendingJobNotifier,
             Storage,
             JobRunner<TData>,
+            RecoveryRunner<TData>,
+            RetentionRunner<TData>,
             JobScheduler
         );
 
@@ -89,17 +112,47 @@ impl<TData: ContextData> JobManager<TData> {
         let job_runner = context.get_required_service::<JobRunner<TData>>();
         let job_worker = JobWorker::new(job_worker_settings, context.clone(), job_runner.clone());
         let job_worker_handle = job_worker.start();
+        let retention_handle = Self::start_retention(&context);
 
         log::info!("JobfireManager started");
         Self {
             context,
             job_worker_handle,
+            retention_handle,
         }
     }
 
-    pub async fn stop(self) -> Result<()> {
+    /// Spawns periodic enforcement of `RetentionSettings`, unless nothing is ever deleted.
+    fn start_retention(context: &Context<TData>) -> Option<task::JoinHandle<()>> {
+        let settings = context.get_required_service::<RetentionSettings>();
+        if !settings.is_enabled() {
+            return None;
+        }
+
+        let sweep_interval = settings.sweep_interval().to_std().unwrap();
+        let retention_runner = context.get_required_service::<RetentionRunner<TData>>();
+
+        Some(tokio::spawn(async move {
+            let mut interval = time::interval(sweep_interval);
+            interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                retention_runner.run().await;
+            }
+        }))
+    }
+
+    /// Stops popping pending jobs and waits for in-flight runs to finish.
+    ///
+    /// Runs still in flight after `JobWorkerSettings` shutdown timeout are cancelled
+    /// and reported as abandoned.
+    pub async fn stop(self) -> Result<StopSummary> {
         log::info!("JobfireManager stopping");
-        self.job_worker_handle
+        if let Some(retention_handle) = &self.retention_handle {
+            retention_handle.abort();
+        }
+        let summary = self
+            .job_worker_handle
             .stop()
             .await
             .map_err(|_| Error::StopFailed)?;
@@ -108,12 +161,12 @@ impl<TData: ContextData> JobManager<TData> {
 
         poll_predicate(
             async move || job_worker_handle.get_state().await == State::Stopped,
-            interval(Duration::milliseconds(100).to_std().unwrap()),
+            time::interval(Duration::milliseconds(100).to_std().unwrap()),
         )
         .await;
 
         log::info!("JobfireManager stopped");
-        Ok(())
+        Ok(summary)
     }
 
     pub async fn schedule(&self, job: Job, at: DateTime<Utc>) -> Result<JobId> {
@@ -127,6 +180,40 @@ impl<TData: ContextData> JobManager<TData> {
         Ok(job_id)
     }
 
+    /// Like `schedule`, but returns a handle resolving to the final run of the job.
+    pub async fn schedule_with_completion(
+        &self,
+        job: Job,
+        at: DateTime<Utc>,
+    ) -> Result<JobCompletion> {
+        // subscribed before scheduling, so the run can't finish unnoticed
+        let completion = self.completion(&job.id()).await?;
+        self.schedule(job, at).await?;
+        Ok(completion)
+    }
+
+    /// Creates a handle resolving to the final run of an already scheduled job.
+    ///
+    /// Runs that finished before the handle was created are ignored.
+    pub async fn completion(&self, job_id: &JobId) -> Result<JobCompletion> {
+        let poll_rate = self
+            .context
+            .get_required_service::<JobWorkerSettings>()
+            .poll_rate();
+        let completion = JobCompletion::new(
+            *job_id,
+            self.storage(),
+            self.context.get_service::<RunCompletions>().as_ref(),
+            poll_rate,
+        )
+        .await?;
+        Ok(completion)
+    }
+
+    /// Cancels a pending job, or requests cancellation of a running one.
+    ///
+    /// A running job observes the request through `Context::cancelled` and is recorded
+    /// as failed with `JobError::JobCancelled`.
     pub async fn cancel(&self, job_id: &JobId) -> Result<()> {
         self.context
             .get_required_service::<JobScheduler>()
@@ -135,6 +222,19 @@ impl<TData: ContextData> JobManager<TData> {
         Ok(())
     }
 
+    /// Like `cancel`, but a running job that doesn't finish within `grace_period` is aborted.
+    pub async fn cancel_with_grace_period(
+        &self,
+        job_id: &JobId,
+        grace_period: Duration,
+    ) -> Result<()> {
+        self.context
+            .get_required_service::<JobScheduler>()
+            .cancel_with_grace_period(job_id, grace_period)
+            .await?;
+        Ok(())
+    }
+
     pub async fn reschedule(
         &self,
         job_id: &JobId,
@@ -147,6 +247,88 @@ impl<TData: ContextData> JobManager<TData> {
         Ok(())
     }
 
+    /// Lists jobs matching a filter, ordered by creation time.
+    pub async fn list_jobs(&self, filter: &Filter) -> Result<Vec<Job>> {
+        let jobs = self.storage().job_repo().list(filter).await?;
+        Ok(jobs)
+    }
+
+    /// Lists pending jobs matching a filter, ordered by scheduled time.
+    pub async fn list_pending_jobs(&self, filter: &Filter) -> Result<Vec<PendingJob>> {
+        let jobs = self.storage().pending_job_repo().list(filter).await?;
+        Ok(jobs)
+    }
+
+    /// Lists successful runs matching a filter, most recently finished first.
+    pub async fn list_successful_runs(&self, filter: &Filter) -> Result<Vec<SuccessfulRun>> {
+        let runs = self.storage().successful_run_repo().list(filter).await?;
+        Ok(runs)
+    }
+
+    /// Lists failed runs matching a filter, most recently finished first.
+    pub async fn list_failed_runs(&self, filter: &Filter) -> Result<Vec<FailedRun>> {
+        let runs = self.storage().failed_run_repo().list(filter).await?;
+        Ok(runs)
+    }
+
+    /// Retrieves the attempt history of a job, successful and failed runs ordered by
+    /// the time they finished.
+    pub async fn runs_of(&self, job_id: &JobId) -> Result<Vec<Run>> {
+        let runs = self.storage().runs_of(job_id).await?;
+        Ok(runs)
+    }
+
+    /// Retrieves the lifecycle state of a job.
+    pub async fn status(&self, job_id: &JobId) -> Result<JobStatus> {
+        let storage = self.storage();
+
+        // pending is read first, a claim moves a job from pending to running, so a job
+        // claimed in between is still seen as running
+        if let Some(pending_job) = storage.pending_job_repo().get(job_id).await? {
+            return Ok(JobStatus::Pending {
+                at: pending_job.scheduled_at(),
+            });
+        }
+        if let Some(running_job) = storage.running_job_repo().get(job_id).await? {
+            return Ok(JobStatus::Running {
+                since: running_job.started_at(),
+                run_id: running_job.run_id(),
+            });
+        }
+
+        let status = match self.runs_of(job_id).await?.pop() {
+            Some(Run::Successful(run)) => JobStatus::Succeeded {
+                run_id: run.run_id(),
+                finished_at: run.finished_at(),
+            },
+            Some(Run::Failed(run)) => match run.error() {
+                JobError::JobCancelled => JobStatus::Cancelled {
+                    run_id: run.run_id(),
+                    finished_at: run.finished_at(),
+                },
+                error => JobStatus::Failed {
+                    run_id: run.run_id(),
+                    finished_at: run.finished_at(),
+                    error: error.clone(),
+                },
+            },
+            None => JobStatus::Unknown,
+        };
+
+        Ok(status)
+    }
+
+    fn storage(&self) -> Storage {
+        self.context.get_required_service::<Storage>()
+    }
+
+    /// Identity of the worker of this manager, recorded on every run it starts.
+    pub fn worker_id(&self) -> WorkerId {
+        self.context
+            .get_required_service::<JobWorkerSettings>()
+            .worker_id()
+    }
+
     pub fn context(&self) -> &Context<TData> {
         &self.context
     }
@@ -156,8 +338,122 @@ fn add_default_services<TData: ContextData>(context: &Context<TData>) {
     let services = context.services();
     services.add_service(AnyClock::new(SystemClock));
     services.add_service(JobWorkerSettings::default());
+    services.add_service(PendingJobNotifier::default());
+    services.add_service(RunCancellations::default());
+    services.add_service(RunCompletions::default());
+    services.add_service(RateLimiter::default());
     services.add_service(JobRunner::new(context.clone()));
     services.add_service(OnSuccessRunner::new(context.clone()));
     services.add_service(OnFailRunner::new(context.clone()));
+    services.add_service(RecoveryRunner::new(context.clone()));
+    services.add_service(RetentionSettings::default());
+    services.add_service(RetentionRunner::new(context.clone()));
     services.add_service(JobScheduler::new(services.clone()));
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        domain::job::{
+            error::JobResult,
+            r#impl::{JobImpl, JobImplName},
+            report::Report,
+        },
+        registries::{job_actions::JobActionsRegistryBuilder, policies::PolicyRegistryBuilder},
+        storage::memory::AddMemoryStorageService,
+    };
+
+    #[derive(Default)]
+    struct TestContextData {
+        running: AtomicUsize,
+    }
+
+    impl ContextData for TestContextData {}
+
+    #[derive(Serialize, Deserialize)]
+    struct SleepJobImpl {
+        millis: u64,
+    }
+
+    #[async_trait]
+    impl JobImpl<TestContextData> for SleepJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("sleep")
+        }
+
+        async fn run(&self, context: Context<TestContextData>) -> JobResult<Report> {
+            context.data().running.fetch_add(1, Ordering::SeqCst);
+            time::sleep(std::time::Duration::from_millis(self.millis)).await;
+            Ok(Report::new())
+        }
+
+        async fn on_success(&self, _context: Context<TestContextData>) {}
+
+        async fn on_fail(&self, _context: Context<TestContextData>) {}
+    }
+
+    fn test_manager() -> JobManager<TestContextData> {
+        JobManager::new_default(TestContextData::default(), |builder| {
+            builder.add_service(JobWorkerSettings::new(Duration::milliseconds(1), 32).unwrap());
+            let mut job_actions_registry = JobActionsRegistryBuilder::default();
+            job_actions_registry.register::<SleepJobImpl>();
+            builder.add_service(job_actions_registry.build());
+            builder.add_service(PolicyRegistryBuilder::<TestContextData>::default().build());
+            builder.add_memory_storage();
+        })
+        .unwrap()
+    }
+
+    async fn schedule_sleep(
+        manager: &JobManager<TestContextData>,
+        millis: u64,
+        at: DateTime<Utc>,
+    ) -> JobId {
+        let job = Job::from_impl(SleepJobImpl { millis }, Utc::now(), Vec::new()).unwrap();
+        manager.schedule(job, at).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_status() {
+        let manager = test_manager();
+        let scheduled_at = Utc::now() + Duration::days(1);
+        let pending_job_id = schedule_sleep(&manager, 0, scheduled_at).await;
+        let job_id = schedule_sleep(&manager, 100, Utc::now() - Duration::seconds(1)).await;
+
+        let status = manager.status(&pending_job_id).await.unwrap();
+        assert!(matches!(status, JobStatus::Pending { at } if at == scheduled_at));
+        manager.cancel(&pending_job_id).await.unwrap();
+        let status = manager.status(&pending_job_id).await.unwrap();
+        assert!(matches!(status, JobStatus::Cancelled { .. }));
+
+        let data = manager.context().data();
+        time::timeout(std::time::Duration::from_secs(5), async {
+            while data.running.load(Ordering::SeqCst) == 0 {
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let status = manager.status(&job_id).await.unwrap();
+        assert!(matches!(status, JobStatus::Running { .. }));
+        time::timeout(std::time::Duration::from_secs(5), async {
+            while !manager.status(&job_id).await.unwrap().is_terminal() {
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let status = manager.status(&job_id).await.unwrap();
+        assert!(matches!(status, JobStatus::Succeeded { .. }));
+
+        let status = manager.status(&JobId::default()).await.unwrap();
+        assert!(matches!(status, JobStatus::Unknown));
+        manager.stop().await.unwrap();
+    }
+}
@@ -1,13 +1,19 @@
 use crate::{
-    domain::job::{self, id::JobId, pending::PendingJob},
+    domain::{
+        job::{self, error::JobError, id::JobId, pending::PendingJob},
+        run::{Run, failed::FailedRun, id::RunId},
+    },
+    runners::{cancellation::RunCancellations, completion::RunCompletions},
     services::{
         Services,
+        notify::PendingJobNotifier,
+        time::{AnyClock, Clock},
         verify::{ServiceMissing, VerifyService},
     },
-    storage::{self, Storage},
+    storage::{self, Storage, transaction::Transaction},
     verify_services,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -38,11 +44,17 @@ impl JobScheduler {
     pub fn new(services: Services) -> Self {
         Self { services }
     }
+
+    fn notify(&self) {
+        if let Some(notifier) = self.services.get_service::<PendingJobNotifier>() {
+            notifier.notify();
+        }
+    }
 }
 
 impl VerifyService for JobScheduler {
     fn verify(&self, services: &Services) -> std::result::Result<(), ServiceMissing> {
-        verify_services!(services, Storage);
+        verify_services!(services, Storage, AnyClock);
         Ok(())
     }
 }
@@ -57,22 +69,100 @@ impl JobScheduler {
             return Err(Error::AlreadyScheduled);
         }
 
-        storage.job_repo().add(job).await?;
-        storage.pending_job_repo().add(pending_job).await?;
+        let transaction = Transaction::default()
+            .add_job(job)
+            .add_pending_job(pending_job);
+        match storage.commit(transaction).await {
+            Ok(_) => {}
+            Err(storage::error::Error::AlreadyExists) => return Err(Error::AlreadyScheduled),
+            Err(error) => return Err(Error::Storage(error)),
+        }
+        self.notify();
 
         Ok(())
     }
 
+    /// Schedules another run of a job that is already stored, e.g. the next
+    /// occurrence of a recurring job.
+    pub async fn schedule_existing(
+        &self,
+        job_id: &JobId,
+        scheduled_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let storage = self.services.get_required_service::<Storage>();
+
+        if storage.job_repo().get(job_id).await?.is_none() {
+            return Err(Error::JobNotFound);
+        }
+
+        match storage
+            .pending_job_repo()
+            .add(PendingJob::new(*job_id, scheduled_at))
+            .await
+        {
+            Ok(_) => {
+                self.notify();
+                Ok(())
+            }
+            Err(storage::error::Error::AlreadyExists) => Err(Error::AlreadyScheduled),
+            Err(error) => Err(Error::Storage(error)),
+        }
+    }
+
+    /// Cancels a pending job, or requests cancellation of a job that is currently running.
+    ///
+    /// Either way the job ends up with a failed run with `JobError::JobCancelled`.
     pub async fn cancel(&self, job_id: &JobId) -> Result<()> {
+        self.cancel_internal(job_id, None).await
+    }
+
+    /// Like `cancel`, but a running job is aborted if it doesn't finish within `grace_period`.
+    pub async fn cancel_with_grace_period(
+        &self,
+        job_id: &JobId,
+        grace_period: Duration,
+    ) -> Result<()> {
+        self.cancel_internal(job_id, Some(grace_period)).await
+    }
+
+    async fn cancel_internal(&self, job_id: &JobId, grace_period: Option<Duration>) -> Result<()> {
         let storage = self.services.get_required_service::<Storage>();
 
-        // TODO add cancel queue
-        match storage.pending_job_repo().delete(job_id).await {
-            Ok(_) => Ok(()),
-            Err(error) => match error {
-                storage::error::Error::NotFound => Err(Error::JobNotFound),
-                _ => Err(Error::Storage(error)),
-            },
+        if let Some(pending_job) = storage.pending_job_repo().get(job_id).await? {
+            let now = self.services.get_required_service::<AnyClock>().utc_now();
+            // the failed run persists the cancellation as the terminal state of the job
+            let failed_run = FailedRun::new(
+                RunId::default(),
+                *job_id,
+                pending_job.scheduled_at(),
+                now,
+                JobError::JobCancelled,
+            );
+            let transaction = Transaction::default()
+                .delete_pending_job(*job_id)
+                .add_failed_run(failed_run.clone());
+
+            match storage.commit(transaction).await {
+                Ok(_) => {
+                    if let Some(run_completions) = self.services.get_service::<RunCompletions>() {
+                        run_completions.complete(Run::Failed(failed_run));
+                    }
+                    return Ok(());
+                }
+                // popped in the meantime, so it may be running now
+                Err(storage::error::Error::NotFound) => {}
+                Err(error) => return Err(Error::Storage(error)),
+            }
+        }
+
+        let cancelled = self
+            .services
+            .get_service::<RunCancellations>()
+            .is_some_and(|run_cancellations| run_cancellations.cancel(job_id, grace_period));
+
+        match cancelled {
+            true => Ok(()),
+            false => Err(Error::JobNotFound),
         }
     }
 
@@ -85,8 +175,20 @@ impl JobScheduler {
         }
         let mut scheduled_job = scheduled_job.unwrap();
         scheduled_job.reschedule(new_scheduled_at);
-        storage.pending_job_repo().delete(job_id).await?;
-        storage.pending_job_repo().add(scheduled_job).await?;
+
+        // replaced in a single transaction, so the job is never missing from storage
+        let transaction = Transaction::default()
+            .delete_pending_job(*job_id)
+            .add_pending_job(scheduled_job);
+        storage
+            .commit(transaction)
+            .await
+            .map_err(|error| match error {
+                // popped in the meantime
+                storage::error::Error::NotFound => Error::JobNotFound,
+                error => Error::Storage(error),
+            })?;
+        self.notify();
         Ok(())
     }
 }
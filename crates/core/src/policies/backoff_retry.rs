@@ -0,0 +1,464 @@
+use std::{fmt::Display, marker::PhantomData, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+use crate::domain::{
+    job::{
+        context::{Context, ContextData},
+        error::JobError,
+        r#impl::SerializedJobImpl,
+        policy::{Policy, PolicyData, PolicyName},
+    },
+    run::job_actions::RunFn,
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid settings: {0}")]
+    InvalidSettings(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// How the delay before a retry grows with the number of failed attempts.
+#[derive(Clone, Copy, Debug)]
+pub enum Backoff {
+    /// `delay * attempt`
+    Linear(Duration),
+    /// `delay * 2^(attempt - 1)`
+    Exponential(Duration),
+}
+
+impl Backoff {
+    fn base_delay(&self) -> Duration {
+        match self {
+            Backoff::Linear(delay) | Backoff::Exponential(delay) => *delay,
+        }
+    }
+
+    /// Delay before retrying after `attempt` failed runs, starting at 1, saturating
+    /// at `Duration::MAX`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match self {
+            Backoff::Linear(delay) => i32::try_from(attempt)
+                .ok()
+                .and_then(|attempt| delay.checked_mul(attempt))
+                .unwrap_or(Duration::MAX),
+            Backoff::Exponential(delay) => {
+                let factor = 2_i32.checked_pow(attempt.saturating_sub(1));
+                factor
+                    .and_then(|factor| delay.checked_mul(factor))
+                    .unwrap_or(Duration::MAX)
+            }
+        }
+    }
+}
+
+impl Display for Backoff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backoff::Linear(delay) => write!(f, "linear::{delay}"),
+            Backoff::Exponential(delay) => write!(f, "exponential::{delay}"),
+        }
+    }
+}
+
+/// Retries a job failed with a retryable error by scheduling it again after a backoff,
+/// up to `max_tries` runs in total.
+///
+/// Every failed attempt is recorded as a `FailedRun` and no worker is occupied while
+/// waiting. The number of attempts is kept in `PolicyData`, so it survives restarts,
+/// and is reset once the job succeeds or fails for good.
+#[derive(Clone)]
+pub struct BackoffRetryPolicy<TData: ContextData> {
+    max_tries: u32,
+    backoff: Backoff,
+    max_delay: Duration,
+    jitter: f64,
+    phantom_data: PhantomData<TData>,
+}
+
+impl<TData: ContextData> Default for BackoffRetryPolicy<TData> {
+    fn default() -> Self {
+        Self::new(5, Backoff::Exponential(Duration::seconds(1))).unwrap()
+    }
+}
+
+impl<TData: ContextData> BackoffRetryPolicy<TData> {
+    pub fn new(max_tries: u32, backoff: Backoff) -> Result<Self> {
+        if backoff.base_delay() < Duration::zero() {
+            return Err(Error::InvalidSettings(
+                "backoff delay can't be negative".to_owned(),
+            ));
+        }
+        Ok(Self {
+            max_tries,
+            backoff,
+            max_delay: Duration::hours(1),
+            jitter: 0.0,
+            phantom_data: Default::default(),
+        })
+    }
+
+    /// Caps the delay before a retry.
+    ///
+    /// Defaults to 1 hour.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Result<Self> {
+        if max_delay < Duration::zero() {
+            return Err(Error::InvalidSettings(
+                "max_delay can't be negative".to_owned(),
+            ));
+        }
+        self.max_delay = max_delay;
+        Ok(self)
+    }
+
+    /// Shortens every delay by a random fraction of up to `jitter`, between 0 and 1,
+    /// so jobs failed at the same time don't retry at the same time.
+    ///
+    /// Defaults to 0.
+    pub fn with_jitter(mut self, jitter: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(Error::InvalidSettings(
+                "jitter has to be between 0 and 1".to_owned(),
+            ));
+        }
+        self.jitter = jitter;
+        Ok(self)
+    }
+
+    /// Delay before retrying after `attempt` failed runs, without jitter.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff.delay(attempt).min(self.max_delay)
+    }
+
+    fn attempt_key(&self) -> String {
+        format!("{}::ATTEMPT", self.name())
+    }
+
+    fn jittered(&self, delay: Duration) -> Duration {
+        let millis = delay.num_milliseconds() as f64;
+        let jitter = millis * self.jitter * fastrand::f64();
+        Duration::milliseconds((millis - jitter) as i64)
+    }
+}
+
+impl<TData: ContextData> Policy<TData> for BackoffRetryPolicy<TData> {
+    fn name(&self) -> PolicyName {
+        PolicyName::new(&format!(
+            "jobfire::backoff_retry::{}::{}::{}::{}",
+            self.max_tries, self.backoff, self.max_delay, self.jitter
+        ))
+    }
+
+    fn init(&self, data: PolicyData) {
+        data.set(&self.attempt_key(), 0_u32).unwrap();
+    }
+
+    fn wrap_run(&self, f: RunFn<TData>, data: PolicyData) -> RunFn<TData> {
+        let attempt_key = self.attempt_key();
+        let run: RunFn<TData> = Arc::new(
+            move |serialized_job_impl: SerializedJobImpl, job_context: Context<TData>| {
+                let f = f.clone();
+                let data = data.clone();
+                let attempt_key = attempt_key.clone();
+
+                Box::pin(async move {
+                    let result = f(serialized_job_impl, job_context).await;
+                    if result.is_ok() {
+                        data.set(&attempt_key, 0_u32).unwrap();
+                    }
+                    result
+                })
+            },
+        );
+
+        run
+    }
+
+    fn retry_at(
+        &self,
+        error: &JobError,
+        data: PolicyData,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        // deferred runs never executed the job, so they neither count as attempts nor reset them
+        if matches!(error, JobError::Deferred) {
+            return None;
+        }
+
+        let attempt_key = self.attempt_key();
+        let attempt = data.get::<u32>(&attempt_key).ok().flatten().unwrap_or(0) + 1;
+
+        if !error.is_retryable() || attempt >= self.max_tries {
+            data.set(&attempt_key, 0_u32).unwrap();
+            return None;
+        }
+
+        data.set(&attempt_key, attempt).unwrap();
+        let retry_at = now
+            .checked_add_signed(self.jittered(self.delay(attempt)))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        Some(retry_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+    use tokio::time;
+
+    use crate::{
+        domain::{
+            job::{
+                Job,
+                context::EmptyContextData,
+                error::{CustomError, JobResult},
+                id::JobId,
+                r#impl::{JobImpl, JobImplName},
+                report::Report,
+            },
+            run::Run,
+        },
+        managers::job_manager::JobManager,
+        registries::{job_actions::JobActionsRegistryBuilder, policies::PolicyRegistryBuilder},
+        storage::{Storage, memory::AddMemoryStorageService},
+        workers::job::JobWorkerSettings,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestContextData {
+        attempts: AtomicUsize,
+        failed_callbacks: AtomicUsize,
+    }
+
+    impl ContextData for TestContextData {}
+
+    #[derive(Serialize, Deserialize)]
+    struct FlakyJobImpl {
+        failures: usize,
+    }
+
+    #[async_trait]
+    impl JobImpl<TestContextData> for FlakyJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("flaky")
+        }
+
+        async fn run(&self, context: Context<TestContextData>) -> JobResult<Report> {
+            match context.data().attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                true => Err(CustomError::new("unavailable").into()),
+                false => Ok(Report::new()),
+            }
+        }
+
+        async fn on_success(&self, _context: Context<TestContextData>) {}
+
+        async fn on_fail(&self, context: Context<TestContextData>) {
+            context
+                .data()
+                .failed_callbacks
+                .fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn linear_policy() -> BackoffRetryPolicy<TestContextData> {
+        BackoffRetryPolicy::new(3, Backoff::Linear(Duration::milliseconds(10))).unwrap()
+    }
+
+    async fn run_flaky(failures: usize) -> (JobManager<TestContextData>, JobId, Run) {
+        let manager = JobManager::new_default(TestContextData::default(), |builder| {
+            builder.add_service(JobWorkerSettings::new(Duration::milliseconds(1), 32).unwrap());
+            let mut job_actions_registry = JobActionsRegistryBuilder::default();
+            job_actions_registry.register::<FlakyJobImpl>();
+            builder.add_service(job_actions_registry.build());
+            let mut policy_registry = PolicyRegistryBuilder::<TestContextData>::default();
+            policy_registry.register(linear_policy());
+            builder.add_service(policy_registry.build());
+            builder.add_memory_storage();
+        })
+        .unwrap();
+        let job = Job::from_impl(
+            FlakyJobImpl { failures },
+            Utc::now(),
+            vec![Box::new(linear_policy())],
+        )
+        .unwrap();
+        let job_id = job.id();
+
+        let completion = manager
+            .schedule_with_completion(job, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        let run = time::timeout(std::time::Duration::from_secs(5), completion.wait())
+            .await
+            .unwrap()
+            .unwrap();
+
+        (manager, job_id, run)
+    }
+
+    fn policy(max_tries: u32) -> BackoffRetryPolicy<EmptyContextData> {
+        BackoffRetryPolicy::new(max_tries, Backoff::Exponential(Duration::seconds(1)))
+            .unwrap()
+            .with_max_delay(Duration::seconds(5))
+            .unwrap()
+    }
+
+    fn retryable() -> JobError {
+        CustomError::new("network").into()
+    }
+
+    #[test]
+    fn test_delay() {
+        let linear = Backoff::Linear(Duration::seconds(2));
+        assert_eq!(linear.delay(3), Duration::seconds(6));
+
+        let policy = policy(10);
+        let delays = (1..=5)
+            .map(|attempt| policy.delay(attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [1, 2, 4, 5, 5].map(Duration::seconds).to_vec());
+        assert_eq!(policy.delay(100), Duration::seconds(5));
+    }
+
+    #[test]
+    fn test_delay_saturates() {
+        let linear = Backoff::Linear(Duration::days(365 * 1000));
+        assert_eq!(linear.delay(u32::MAX), Duration::MAX);
+        let exponential = Backoff::Exponential(Duration::seconds(1));
+        assert_eq!(exponential.delay(64), Duration::MAX);
+
+        let policy =
+            BackoffRetryPolicy::<EmptyContextData>::new(10, Backoff::Linear(Duration::MAX))
+                .unwrap()
+                .with_max_delay(Duration::MAX)
+                .unwrap();
+        let data = PolicyData::default();
+        policy.init(data.clone());
+        let retry_at = policy.retry_at(&retryable(), data, Utc::now());
+        assert_eq!(retry_at, Some(DateTime::<Utc>::MAX_UTC));
+    }
+
+    #[test]
+    fn test_negative_delay() {
+        let result =
+            BackoffRetryPolicy::<EmptyContextData>::new(3, Backoff::Linear(Duration::seconds(-1)));
+
+        assert!(matches!(result, Err(Error::InvalidSettings(_))));
+    }
+
+    #[test]
+    fn test_retry_at() {
+        let policy = policy(3);
+        let data = PolicyData::default();
+        policy.init(data.clone());
+        let now = Utc::now();
+
+        assert_eq!(
+            policy.retry_at(&retryable(), data.clone(), now),
+            Some(now + Duration::seconds(1))
+        );
+        assert_eq!(
+            policy.retry_at(&retryable(), data.clone(), now),
+            Some(now + Duration::seconds(2))
+        );
+        assert_eq!(policy.retry_at(&retryable(), data.clone(), now), None);
+
+        // the attempts start over once the job has failed for good
+        assert!(policy.retry_at(&retryable(), data.clone(), now).is_some());
+    }
+
+    #[test]
+    fn test_permanent_error_is_not_retried() {
+        let policy = policy(3);
+        let data = PolicyData::default();
+        policy.init(data.clone());
+
+        let error = CustomError::new("invalid input").permanent().into();
+        assert_eq!(policy.retry_at(&error, data, Utc::now()), None);
+    }
+
+    #[test]
+    fn test_deferred_run_keeps_attempts() {
+        let policy = policy(3);
+        let data = PolicyData::default();
+        policy.init(data.clone());
+        let now = Utc::now();
+
+        policy.retry_at(&retryable(), data.clone(), now);
+        assert_eq!(
+            policy.retry_at(&JobError::Deferred, data.clone(), now),
+            None
+        );
+
+        assert_eq!(
+            policy.retry_at(&retryable(), data, now),
+            Some(now + Duration::seconds(2))
+        );
+    }
+
+    #[test]
+    fn test_jitter() {
+        let policy = policy(3).with_jitter(0.5).unwrap();
+        let data = PolicyData::default();
+        let now = Utc::now();
+
+        let retry_at = policy.retry_at(&retryable(), data, now).unwrap();
+        assert!(retry_at >= now + Duration::milliseconds(500));
+        assert!(retry_at <= now + Duration::seconds(1));
+
+        assert!(
+            BackoffRetryPolicy::<EmptyContextData>::default()
+                .with_jitter(1.5)
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backoff_retry() {
+        let (manager, job_id, run) = run_flaky(2).await;
+
+        assert!(matches!(run, Run::Successful(_)));
+        let runs = manager.runs_of(&job_id).await.unwrap();
+        assert_eq!(runs.iter().filter(|run| run.error().is_some()).count(), 2);
+        assert!(runs[1].scheduled_at() - runs[0].finished_at() >= Duration::milliseconds(10));
+        let data = manager.context().data();
+        assert_eq!(data.failed_callbacks.load(Ordering::SeqCst), 0);
+
+        // the attempts start over for the next run of the job
+        let storage = manager.context().get_required_service::<Storage>();
+        let job = storage.job_repo().get(&job_id).await.unwrap().unwrap();
+        assert_eq!(
+            job.policies()
+                .data()
+                .get::<u32>(&linear_policy().attempt_key())
+                .unwrap(),
+            Some(0)
+        );
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backoff_retry_gives_up() {
+        let (manager, job_id, run) = run_flaky(5).await;
+
+        assert!(run.error().is_some());
+        assert_eq!(manager.runs_of(&job_id).await.unwrap().len(), 3);
+        let data = manager.context().data();
+        time::timeout(std::time::Duration::from_secs(5), async {
+            while data.failed_callbacks.load(Ordering::SeqCst) != 1 {
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        manager.stop().await.unwrap();
+    }
+}
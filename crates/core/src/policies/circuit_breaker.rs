@@ -0,0 +1,475 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+use crate::{
+    domain::{
+        job::{
+            context::{Context, ContextData},
+            error::{JobError, JobResult},
+            r#impl::SerializedJobImpl,
+            policy::{Policy, PolicyData, PolicyName},
+            report::Report,
+        },
+        run::job_actions::RunFn,
+    },
+    services::time::{AnyClock, Clock},
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid settings: {0}")]
+    InvalidSettings(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What happens to a run of a job while its circuit is open.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenBehavior {
+    /// Fails the run with `JobError::PolicyShortCircuit`, which retry policies may retry.
+    FastFail,
+    /// Puts the run off with `JobError::Deferred` and schedules the job again once the
+    /// circuit half-opens. Deferred runs aren't recorded as failed runs nor retried by
+    /// retry policies.
+    Defer,
+}
+
+/// State of a circuit as seen by `CircuitBreakerPolicy::state`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Runs go through.
+    Closed,
+    /// Runs are rejected until the cooldown ends.
+    Open { until: DateTime<Utc> },
+    /// A single run goes through to probe whether the circuit can close.
+    HalfOpen,
+}
+
+#[derive(Default)]
+struct Circuit {
+    failures: VecDeque<DateTime<Utc>>,
+    open_until: Option<DateTime<Utc>>,
+    probing: bool,
+}
+
+/// Lets another run probe a half-open circuit if the probing run is dropped before it
+/// finishes, e.g. because it got aborted.
+struct ProbeGuard {
+    circuits: Arc<Mutex<HashMap<String, Circuit>>>,
+    key: String,
+    finished: bool,
+}
+
+impl ProbeGuard {
+    fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for ProbeGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if let Ok(mut circuits) = self.circuits.lock()
+            && let Some(circuit) = circuits.get_mut(&self.key)
+        {
+            circuit.probing = false;
+        }
+    }
+}
+
+/// Stops running jobs of a failing impl, e.g. while a downstream service is down.
+///
+/// The circuit opens once `failure_threshold` runs failed with a retryable error within
+/// `window`, and rejects runs according to `OpenBehavior` for `cooldown`. Then it
+/// half-opens and lets a single run through, which closes the circuit when it succeeds
+/// and opens it again when it fails.
+///
+/// Circuits are kept in memory of the registered policy, one per impl name of the run
+/// jobs, or a single one shared by all jobs using the policy if it has a key.
+pub struct CircuitBreakerPolicy<TData: ContextData> {
+    key: Option<String>,
+    failure_threshold: usize,
+    window: Duration,
+    cooldown: Duration,
+    open_behavior: OpenBehavior,
+    circuits: Arc<Mutex<HashMap<String, Circuit>>>,
+    phantom_data: PhantomData<TData>,
+}
+
+impl<TData: ContextData> Clone for CircuitBreakerPolicy<TData> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            failure_threshold: self.failure_threshold,
+            window: self.window,
+            cooldown: self.cooldown,
+            open_behavior: self.open_behavior,
+            circuits: self.circuits.clone(),
+            phantom_data: Default::default(),
+        }
+    }
+}
+
+impl<TData: ContextData> CircuitBreakerPolicy<TData> {
+    pub fn new(failure_threshold: usize, window: Duration, cooldown: Duration) -> Result<Self> {
+        if failure_threshold == 0 {
+            return Err(Error::InvalidSettings(
+                "failure_threshold has to be positive".to_owned(),
+            ));
+        }
+        if window <= Duration::zero() {
+            return Err(Error::InvalidSettings(
+                "window has to be positive".to_owned(),
+            ));
+        }
+        if cooldown < Duration::zero() {
+            return Err(Error::InvalidSettings(
+                "cooldown can't be negative".to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            key: None,
+            failure_threshold,
+            window,
+            cooldown,
+            open_behavior: OpenBehavior::FastFail,
+            circuits: Default::default(),
+            phantom_data: Default::default(),
+        })
+    }
+
+    /// Shares a single circuit between all jobs using the policy, instead of one per impl name.
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Defaults to `OpenBehavior::FastFail`.
+    pub fn with_open_behavior(mut self, open_behavior: OpenBehavior) -> Self {
+        self.open_behavior = open_behavior;
+        self
+    }
+
+    /// State of the circuit of `key`, which is the impl name unless the policy has a key.
+    pub fn state(&self, key: &str, now: DateTime<Utc>) -> CircuitState {
+        let circuits = self.circuits.lock().unwrap();
+        match circuits.get(key).and_then(|circuit| circuit.open_until) {
+            Some(until) if now < until => CircuitState::Open { until },
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+
+    fn deferred_until_key(&self) -> String {
+        format!("{}::DEFERRED_UNTIL", self.name())
+    }
+
+    /// Returns whether a run let through probes a half-open circuit, or when to retry a
+    /// rejected run.
+    fn acquire(&self, key: &str, now: DateTime<Utc>) -> std::result::Result<bool, DateTime<Utc>> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(key.to_owned()).or_default();
+
+        match circuit.open_until {
+            None => Ok(false),
+            Some(until) if now < until => Err(until),
+            Some(_) if circuit.probing => Err(now + self.cooldown),
+            Some(_) => {
+                circuit.probing = true;
+                Ok(true)
+            }
+        }
+    }
+
+    fn record(&self, key: &str, now: DateTime<Utc>, probe: bool, result: &JobResult<Report>) {
+        let failed = matches!(result, Err(error) if error.is_retryable());
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(key.to_owned()).or_default();
+
+        // deferred runs never reached the job, so they tell nothing about its health
+        if matches!(result, Err(JobError::Deferred)) {
+            if probe {
+                circuit.probing = false;
+            }
+            return;
+        }
+        if probe {
+            circuit.probing = false;
+            circuit.open_until = failed.then_some(now + self.cooldown);
+            return;
+        }
+        // runs started before the circuit opened don't affect it
+        if !failed || circuit.open_until.is_some() {
+            return;
+        }
+
+        circuit.failures.push_back(now);
+        while circuit
+            .failures
+            .front()
+            .is_some_and(|failed_at| *failed_at <= now - self.window)
+        {
+            circuit.failures.pop_front();
+        }
+        if circuit.failures.len() >= self.failure_threshold {
+            log::warn!("circuit {key} opened");
+            circuit.failures.clear();
+            circuit.open_until = Some(now + self.cooldown);
+        }
+    }
+}
+
+impl<TData: ContextData> Policy<TData> for CircuitBreakerPolicy<TData> {
+    fn name(&self) -> PolicyName {
+        PolicyName::new(&format!(
+            "jobfire::circuit_breaker::{}::{}::{}::{}::{:?}",
+            self.key.as_deref().unwrap_or("impl"),
+            self.failure_threshold,
+            self.window,
+            self.cooldown,
+            self.open_behavior
+        ))
+    }
+
+    fn wrap_run(&self, f: RunFn<TData>, data: PolicyData) -> RunFn<TData> {
+        let policy = self.clone();
+        let run: RunFn<TData> = Arc::new(
+            move |serialized_job_impl: SerializedJobImpl, job_context: Context<TData>| {
+                let f = f.clone();
+                let data = data.clone();
+                let policy = policy.clone();
+
+                Box::pin(async move {
+                    let key = match &policy.key {
+                        Some(key) => key.clone(),
+                        None => serialized_job_impl.name().to_string(),
+                    };
+                    let clock = job_context.get_required_service::<AnyClock>();
+
+                    let probe = match policy.acquire(&key, clock.utc_now()) {
+                        Ok(probe) => probe,
+                        Err(until) => match policy.open_behavior {
+                            OpenBehavior::FastFail => return Err(JobError::PolicyShortCircuit),
+                            OpenBehavior::Defer => {
+                                data.set(&policy.deferred_until_key(), Some(until)).unwrap();
+                                return Err(JobError::Deferred);
+                            }
+                        },
+                    };
+
+                    let probe_guard = probe.then(|| ProbeGuard {
+                        circuits: policy.circuits.clone(),
+                        key: key.clone(),
+                        finished: false,
+                    });
+                    let result = f(serialized_job_impl, job_context).await;
+                    policy.record(&key, clock.utc_now(), probe, &result);
+                    if let Some(probe_guard) = probe_guard {
+                        probe_guard.finish();
+                    }
+                    result
+                })
+            },
+        );
+
+        run
+    }
+
+    fn retry_at(
+        &self,
+        _error: &JobError,
+        data: PolicyData,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let deferred_until_key = self.deferred_until_key();
+        let deferred_until = data
+            .get::<Option<DateTime<Utc>>>(&deferred_until_key)
+            .ok()
+            .flatten()
+            .flatten()?;
+
+        data.set(&deferred_until_key, None::<DateTime<Utc>>)
+            .unwrap();
+        Some(deferred_until.max(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::time;
+
+    use crate::{
+        domain::job::{context::EmptyContextData, error::CustomError, r#impl::JobImplName},
+        services::{Services, time::FixedClock},
+    };
+
+    use super::*;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    fn policy() -> CircuitBreakerPolicy<EmptyContextData> {
+        CircuitBreakerPolicy::new(2, Duration::seconds(10), Duration::seconds(30)).unwrap()
+    }
+
+    fn failed() -> JobResult<Report> {
+        Err(CustomError::new("unavailable").into())
+    }
+
+    async fn run(
+        policy: &CircuitBreakerPolicy<EmptyContextData>,
+        data: PolicyData,
+        name: &str,
+        now: DateTime<Utc>,
+        result: JobResult<Report>,
+    ) -> JobResult<Report> {
+        let run: RunFn<EmptyContextData> = Arc::new(move |_, _| {
+            let result = result.clone();
+            Box::pin(async move { result })
+        });
+        let services = Services::default();
+        services.add_service(AnyClock::new(FixedClock(now)));
+
+        policy.wrap_run(run, data)(
+            SerializedJobImpl::new(JobImplName::new(name), json!(null)),
+            Context::new(EmptyContextData, services),
+        )
+        .await
+    }
+
+    #[test]
+    fn test_opens_after_failures_in_window() {
+        let policy = policy();
+
+        policy.record("test", at(0), false, &failed());
+        policy.record("test", at(10), false, &failed());
+        assert_eq!(policy.state("test", at(10)), CircuitState::Closed);
+
+        policy.record("test", at(15), false, &failed());
+        assert_eq!(
+            policy.state("test", at(15)),
+            CircuitState::Open { until: at(45) }
+        );
+        assert_eq!(policy.acquire("test", at(20)), Err(at(45)));
+
+        // permanent errors don't count
+        let permanent: JobResult<Report> = Err(CustomError::new("invalid").permanent().into());
+        policy.record("other", at(0), false, &permanent);
+        policy.record("other", at(1), false, &permanent);
+        assert_eq!(policy.state("other", at(1)), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open() {
+        let policy = policy();
+        policy.record("test", at(0), false, &failed());
+        policy.record("test", at(1), false, &failed());
+
+        assert_eq!(policy.state("test", at(31)), CircuitState::HalfOpen);
+        assert_eq!(policy.acquire("test", at(31)), Ok(true));
+        assert_eq!(policy.acquire("test", at(32)), Err(at(62)));
+
+        policy.record("test", at(33), true, &failed());
+        assert_eq!(
+            policy.state("test", at(33)),
+            CircuitState::Open { until: at(63) }
+        );
+
+        assert_eq!(policy.acquire("test", at(63)), Ok(true));
+        policy.record("test", at(64), true, &Ok(Report::new()));
+        assert_eq!(policy.state("test", at(64)), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_deferred_probe() {
+        let policy = policy();
+        policy.record("test", at(0), false, &failed());
+        policy.record("test", at(1), false, &failed());
+
+        assert_eq!(policy.acquire("test", at(31)), Ok(true));
+        policy.record("test", at(31), true, &Err(JobError::Deferred));
+
+        assert_eq!(policy.state("test", at(32)), CircuitState::HalfOpen);
+        assert_eq!(policy.acquire("test", at(32)), Ok(true));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_probe() {
+        let policy = policy();
+        policy.record("test", at(0), false, &failed());
+        policy.record("test", at(1), false, &failed());
+
+        let pending: RunFn<EmptyContextData> = Arc::new(|_, _| Box::pin(std::future::pending()));
+        let services = Services::default();
+        services.add_service(AnyClock::new(FixedClock(at(31))));
+        let probe = policy.wrap_run(pending, PolicyData::default())(
+            SerializedJobImpl::new(JobImplName::new("test"), json!(null)),
+            Context::new(EmptyContextData, services),
+        );
+        // dropped while probing, e.g. when its worker aborts it
+        assert!(
+            time::timeout(std::time::Duration::from_millis(10), probe)
+                .await
+                .is_err()
+        );
+
+        assert_eq!(policy.state("test", at(32)), CircuitState::HalfOpen);
+        assert_eq!(policy.acquire("test", at(32)), Ok(true));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_per_impl_name() {
+        let policy = policy();
+        let data = PolicyData::default();
+
+        for now in [at(0), at(1)] {
+            assert!(
+                run(&policy, data.clone(), "test", now, failed())
+                    .await
+                    .is_err()
+            );
+        }
+
+        let result = run(&policy, data.clone(), "test", at(2), Ok(Report::new())).await;
+        assert!(matches!(result, Err(JobError::PolicyShortCircuit)));
+        let result = run(&policy, data.clone(), "other", at(2), Ok(Report::new())).await;
+        assert!(result.is_ok());
+        // fast fail doesn't defer
+        assert_eq!(
+            policy.retry_at(&JobError::PolicyShortCircuit, data, at(2)),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_defer_with_key() {
+        let policy = policy()
+            .with_key("downstream")
+            .with_open_behavior(OpenBehavior::Defer);
+        let data = PolicyData::default();
+
+        run(&policy, data.clone(), "first", at(0), failed())
+            .await
+            .ok();
+        run(&policy, data.clone(), "second", at(1), failed())
+            .await
+            .ok();
+        let result = run(&policy, data.clone(), "third", at(2), Ok(Report::new())).await;
+
+        assert!(matches!(result, Err(JobError::Deferred)));
+        let error = JobError::Deferred;
+        assert_eq!(policy.retry_at(&error, data.clone(), at(3)), Some(at(31)));
+        assert_eq!(policy.retry_at(&error, data, at(3)), None);
+    }
+}
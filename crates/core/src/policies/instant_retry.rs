@@ -10,6 +10,8 @@ use crate::domain::{
 use log::error;
 use std::{marker::PhantomData, sync::Arc};
 
+/// Runs the job again right away when it fails with a retryable error,
+/// up to `max_tries` runs in total.
 #[derive(Clone)]
 pub struct InstantRetryPolicy<TData: ContextData> {
     name: PolicyName,
@@ -61,8 +63,9 @@ impl<TData: ContextData> Policy<TData> for InstantRetryPolicy<TData> {
                     while current_try < max_tries {
                         current_try += 1;
                         result = f(serialized_job_impl.clone(), job_context.clone()).await;
-                        match result {
+                        match &result {
                             Ok(_) => break,
+                            Err(error) if !error.is_retryable() => break,
                             Err(_) => error!("Retry #{current_try}"),
                         }
                     }
@@ -74,3 +77,57 @@ impl<TData: ContextData> Policy<TData> for InstantRetryPolicy<TData> {
         run
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use serde_json::json;
+
+    use crate::{
+        domain::job::{
+            context::EmptyContextData, error::CustomError, r#impl::JobImplName, report::Report,
+        },
+        services::Services,
+    };
+
+    use super::*;
+
+    async fn run_with_error(error: JobError) -> u32 {
+        let policy = InstantRetryPolicy::<EmptyContextData>::new(3);
+        let data = PolicyData::default();
+        policy.init(data.clone());
+        let tries = Arc::new(AtomicU32::new(0));
+        let run: RunFn<EmptyContextData> = {
+            let tries = tries.clone();
+            Arc::new(move |_, _| {
+                tries.fetch_add(1, Ordering::SeqCst);
+                let error = error.clone();
+                Box::pin(async move { Err::<Report, _>(error) })
+            })
+        };
+
+        let result = policy.wrap_run(run, data)(
+            SerializedJobImpl::new(JobImplName::new("test"), json!(null)),
+            Context::new(EmptyContextData, Services::default()),
+        )
+        .await;
+
+        assert!(result.is_err());
+        tries.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_retries_retryable_error() {
+        assert_eq!(run_with_error(CustomError::new("network").into()).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_permanent_error() {
+        assert_eq!(
+            run_with_error(CustomError::new("invalid input").permanent().into()).await,
+            1
+        );
+        assert_eq!(run_with_error(JobError::JobImplBuildFailed).await, 1);
+    }
+}
@@ -1,2 +1,5 @@
+pub mod backoff_retry;
+pub mod circuit_breaker;
 pub mod instant_retry;
+pub mod rate_limit;
 pub mod timeout;
@@ -0,0 +1,373 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use tokio::time;
+
+use crate::{
+    domain::{
+        job::{
+            context::{Context, ContextData},
+            error::{CustomError, JobError, JobResult},
+            r#impl::SerializedJobImpl,
+            policy::{Policy, PolicyData, PolicyName},
+        },
+        run::job_actions::RunFn,
+    },
+    services::{
+        rate_limit::{Permit, Rate, RateLimiter},
+        time::{AnyClock, Clock},
+    },
+};
+
+/// What happens to a run exceeding the budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExceededBehavior {
+    /// Waits within the run until a token is available, occupying the worker meanwhile.
+    Delay,
+    /// Puts the run off with `JobError::Deferred` and schedules the job again once a token
+    /// is available. Deferred runs aren't recorded as failed runs, retried by retry
+    /// policies nor counted by circuit breakers.
+    Reschedule,
+}
+
+/// Limits how often jobs start running, using a token bucket of the `RateLimiter` service.
+///
+/// Buckets are shared by all jobs with the same impl name, or by all jobs using the
+/// policy if it has a key, across workers of a process, or across processes if the
+/// `RateLimiter` keeps them in storage.
+pub struct RateLimitPolicy<TData: ContextData> {
+    key: Option<String>,
+    rate: Rate,
+    exceeded_behavior: ExceededBehavior,
+    phantom_data: PhantomData<TData>,
+}
+
+impl<TData: ContextData> Clone for RateLimitPolicy<TData> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            rate: self.rate,
+            exceeded_behavior: self.exceeded_behavior,
+            phantom_data: Default::default(),
+        }
+    }
+}
+
+impl<TData: ContextData> RateLimitPolicy<TData> {
+    pub fn new(rate: Rate) -> Self {
+        Self {
+            key: None,
+            rate,
+            exceeded_behavior: ExceededBehavior::Delay,
+            phantom_data: Default::default(),
+        }
+    }
+
+    /// Shares a single bucket between all jobs using the policy, instead of one per impl name.
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Defaults to `ExceededBehavior::Delay`.
+    pub fn with_exceeded_behavior(mut self, exceeded_behavior: ExceededBehavior) -> Self {
+        self.exceeded_behavior = exceeded_behavior;
+        self
+    }
+
+    fn deferred_until_key(&self) -> String {
+        format!("{}::DEFERRED_UNTIL", self.name())
+    }
+
+    /// Takes a token, waiting for one unless the run gets rescheduled.
+    ///
+    /// Returns when to reschedule the run if the budget is exceeded.
+    async fn acquire(
+        &self,
+        key: &str,
+        context: &Context<TData>,
+    ) -> JobResult<Option<DateTime<Utc>>> {
+        let rate_limiter = context.get_required_service::<RateLimiter>();
+        let clock = context.get_required_service::<AnyClock>();
+
+        loop {
+            let now = clock.utc_now();
+            let permit = rate_limiter
+                .try_acquire(key, self.rate, now)
+                .await
+                .map_err(|error| {
+                    CustomError::new(format!("failed to acquire rate limit: {error}"))
+                        .with_kind("rate_limit")
+                })?;
+
+            let retry_at = match permit {
+                Permit::Granted => return Ok(None),
+                Permit::Denied { retry_at } => retry_at,
+            };
+            if self.exceeded_behavior == ExceededBehavior::Reschedule {
+                return Ok(Some(retry_at));
+            }
+
+            let delay = (retry_at - now).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = time::sleep(delay) => {}
+                _ = context.cancelled() => return Err(JobError::JobCancelled),
+            }
+        }
+    }
+}
+
+impl<TData: ContextData> Policy<TData> for RateLimitPolicy<TData> {
+    fn name(&self) -> PolicyName {
+        PolicyName::new(&format!(
+            "jobfire::rate_limit::{}::{}::{}::{:?}",
+            self.key.as_deref().unwrap_or("impl"),
+            self.rate.amount(),
+            self.rate.per(),
+            self.exceeded_behavior
+        ))
+    }
+
+    fn wrap_run(&self, f: RunFn<TData>, data: PolicyData) -> RunFn<TData> {
+        let policy = self.clone();
+        let run: RunFn<TData> = Arc::new(
+            move |serialized_job_impl: SerializedJobImpl, job_context: Context<TData>| {
+                let f = f.clone();
+                let data = data.clone();
+                let policy = policy.clone();
+
+                Box::pin(async move {
+                    let key = match &policy.key {
+                        Some(key) => key.clone(),
+                        None => serialized_job_impl.name().to_string(),
+                    };
+
+                    if let Some(retry_at) = policy.acquire(&key, &job_context).await? {
+                        data.set(&policy.deferred_until_key(), Some(retry_at))
+                            .unwrap();
+                        return Err(JobError::Deferred);
+                    }
+
+                    f(serialized_job_impl, job_context).await
+                })
+            },
+        );
+
+        run
+    }
+
+    fn retry_at(
+        &self,
+        _error: &JobError,
+        data: PolicyData,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let deferred_until_key = self.deferred_until_key();
+        let deferred_until = data
+            .get::<Option<DateTime<Utc>>>(&deferred_until_key)
+            .ok()
+            .flatten()
+            .flatten()?;
+
+        data.set(&deferred_until_key, None::<DateTime<Utc>>)
+            .unwrap();
+        Some(deferred_until.max(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use chrono::Duration;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use crate::{
+        domain::job::{
+            Job,
+            context::EmptyContextData,
+            r#impl::{JobImpl, JobImplName},
+            report::Report,
+        },
+        managers::job_manager::JobManager,
+        policies::circuit_breaker::{CircuitBreakerPolicy, CircuitState},
+        registries::{job_actions::JobActionsRegistryBuilder, policies::PolicyRegistryBuilder},
+        services::{
+            Services,
+            time::{FixedClock, SystemClock},
+        },
+        storage::{Storage, memory::AddMemoryStorageService},
+        workers::job::JobWorkerSettings,
+    };
+
+    use super::*;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    fn services(now: DateTime<Utc>) -> Services {
+        let services = Services::default();
+        services.add_service(AnyClock::new(FixedClock(now)));
+        services.add_service(RateLimiter::default());
+        services
+    }
+
+    async fn run(
+        policy: &RateLimitPolicy<EmptyContextData>,
+        data: PolicyData,
+        services: Services,
+    ) -> JobResult<Report> {
+        let run: RunFn<EmptyContextData> = Arc::new(|_, _| Box::pin(async { Ok(Report::new()) }));
+
+        policy.wrap_run(run, data)(
+            SerializedJobImpl::new(JobImplName::new("test"), json!(null)),
+            Context::new(EmptyContextData, services),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_reschedule() {
+        let policy = RateLimitPolicy::new(Rate::per_second(1).unwrap())
+            .with_exceeded_behavior(ExceededBehavior::Reschedule);
+        let data = PolicyData::default();
+        let services = services(at(0));
+
+        assert!(run(&policy, data.clone(), services.clone()).await.is_ok());
+        let result = run(&policy, data.clone(), services).await;
+
+        assert!(matches!(result, Err(JobError::Deferred)));
+        let error = JobError::Deferred;
+        assert_eq!(policy.retry_at(&error, data.clone(), at(0)), Some(at(1000)));
+        assert_eq!(policy.retry_at(&error, data, at(0)), None);
+    }
+
+    #[tokio::test]
+    async fn test_reschedule_with_circuit_breaker() {
+        let policy = RateLimitPolicy::new(Rate::per_second(1).unwrap())
+            .with_exceeded_behavior(ExceededBehavior::Reschedule);
+        let circuit_breaker =
+            CircuitBreakerPolicy::new(1, Duration::seconds(10), Duration::seconds(30)).unwrap();
+        let data = PolicyData::default();
+        let services = services(at(0));
+        let run: RunFn<EmptyContextData> = Arc::new(|_, _| Box::pin(async { Ok(Report::new()) }));
+        // the circuit breaker sees the outcome of the rate limit
+        let run = circuit_breaker.wrap_run(policy.wrap_run(run, data.clone()), data.clone());
+
+        for _ in 0..3 {
+            run(
+                SerializedJobImpl::new(JobImplName::new("test"), json!(null)),
+                Context::new(EmptyContextData, services.clone()),
+            )
+            .await
+            .ok();
+        }
+
+        assert_eq!(circuit_breaker.state("test", at(0)), CircuitState::Closed);
+        assert_eq!(
+            circuit_breaker.retry_at(&JobError::Deferred, data.clone(), at(0)),
+            None
+        );
+        assert_eq!(
+            policy.retry_at(&JobError::Deferred, data, at(0)),
+            Some(at(1000))
+        );
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct NoopJobImpl;
+
+    #[async_trait]
+    impl JobImpl<EmptyContextData> for NoopJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("noop")
+        }
+
+        async fn run(&self, _context: Context<EmptyContextData>) -> JobResult<Report> {
+            Ok(Report::new())
+        }
+
+        async fn on_success(&self, _context: Context<EmptyContextData>) {}
+
+        async fn on_fail(&self, _context: Context<EmptyContextData>) {}
+    }
+
+    #[tokio::test]
+    async fn test_reschedule_records_no_failed_run() {
+        let policy = || {
+            RateLimitPolicy::new(Rate::new(1, Duration::minutes(1)).unwrap())
+                .with_exceeded_behavior(ExceededBehavior::Reschedule)
+        };
+        let manager = JobManager::new_default(EmptyContextData, |builder| {
+            builder.add_service(JobWorkerSettings::new(Duration::milliseconds(1), 32).unwrap());
+            let mut job_actions_registry = JobActionsRegistryBuilder::default();
+            job_actions_registry.register::<NoopJobImpl>();
+            builder.add_service(job_actions_registry.build());
+            let mut policy_registry = PolicyRegistryBuilder::<EmptyContextData>::default();
+            policy_registry.register(policy());
+            builder.add_service(policy_registry.build());
+            builder.add_memory_storage();
+        })
+        .unwrap();
+        let storage = manager.context().get_required_service::<Storage>();
+
+        let scheduled_at = Utc::now() - Duration::seconds(1);
+        let mut job_ids = Vec::new();
+        for _ in 0..2 {
+            let job = Job::from_impl(NoopJobImpl, Utc::now(), vec![Box::new(policy())]).unwrap();
+            job_ids.push(manager.schedule(job, scheduled_at).await.unwrap());
+        }
+
+        // one job runs, the other one is put off until the bucket refills
+        let mut deferred = None;
+        time::timeout(std::time::Duration::from_secs(5), async {
+            while deferred.is_none() {
+                for job_id in job_ids.iter() {
+                    let pending_job = storage.pending_job_repo().get(job_id).await.unwrap();
+                    if pending_job
+                        .is_some_and(|pending_job| pending_job.scheduled_at() > Utc::now())
+                    {
+                        deferred = Some(*job_id);
+                    }
+                }
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let deferred = deferred.unwrap();
+        assert!(storage.runs_of(&deferred).await.unwrap().is_empty());
+        assert!(
+            storage
+                .running_job_repo()
+                .get(&deferred)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delay() {
+        let policy =
+            RateLimitPolicy::new(Rate::new(1, Duration::milliseconds(50)).unwrap()).with_key("api");
+        let data = PolicyData::default();
+        let services = Services::default();
+        services.add_service(AnyClock::new(SystemClock));
+        services.add_service(RateLimiter::default());
+
+        let started_at = Utc::now();
+        for _ in 0..3 {
+            assert!(run(&policy, data.clone(), services.clone()).await.is_ok());
+        }
+
+        assert!(Utc::now() - started_at >= Duration::milliseconds(100));
+        assert_eq!(
+            policy.retry_at(&JobError::PolicyShortCircuit, data, Utc::now()),
+            None
+        );
+    }
+}
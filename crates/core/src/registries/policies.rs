@@ -2,15 +2,17 @@ use crate::{
     domain::{
         job::{
             context::ContextData,
+            error::JobError,
             policy::{Policy, PolicyData, PolicyName},
         },
-        run::job_actions::RunFn,
+        run::job_actions::{OnFailFn, OnSuccessFn, RunFn},
     },
     services::{
         verify::{ServiceMissing, VerifyService},
         Services,
     },
 };
+use chrono::{DateTime, Utc};
 use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
 
@@ -35,6 +37,15 @@ impl<TData: ContextData> Clone for PolicyRegistry<TData> {
 }
 
 impl<TData: ContextData> PolicyRegistry<TData> {
+    /// Initialises the data of a policy for a new job, see `Policy::init`.
+    pub fn init(&self, name: PolicyName, data: PolicyData) -> Result<()> {
+        self.policies
+            .get(&name)
+            .ok_or(Error::PolicyNotFound)?
+            .init(data);
+        Ok(())
+    }
+
     pub fn wrap_run(
         &self,
         name: PolicyName,
@@ -47,6 +58,46 @@ impl<TData: ContextData> PolicyRegistry<TData> {
             .ok_or(Error::PolicyNotFound)?
             .wrap_run(f, data))
     }
+
+    pub fn wrap_on_success(
+        &self,
+        name: PolicyName,
+        f: OnSuccessFn<TData>,
+        data: PolicyData,
+    ) -> Result<OnSuccessFn<TData>> {
+        Ok(self
+            .policies
+            .get(&name)
+            .ok_or(Error::PolicyNotFound)?
+            .wrap_on_success(f, data))
+    }
+
+    pub fn wrap_on_fail(
+        &self,
+        name: PolicyName,
+        f: OnFailFn<TData>,
+        data: PolicyData,
+    ) -> Result<OnFailFn<TData>> {
+        Ok(self
+            .policies
+            .get(&name)
+            .ok_or(Error::PolicyNotFound)?
+            .wrap_on_fail(f, data))
+    }
+
+    pub fn retry_at(
+        &self,
+        name: PolicyName,
+        error: &JobError,
+        data: PolicyData,
+        now: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>> {
+        Ok(self
+            .policies
+            .get(&name)
+            .ok_or(Error::PolicyNotFound)?
+            .retry_at(error, data, now))
+    }
 }
 
 impl<TData: ContextData> VerifyService for PolicyRegistry<TData> {
@@ -0,0 +1,151 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    domain::job::id::JobId,
+    services::{
+        Services,
+        verify::{ServiceMissing, VerifyService},
+    },
+};
+
+/// Cancellation handles of a single run.
+#[derive(Clone, Default)]
+pub struct RunCancellation {
+    token: CancellationToken,
+    abort_token: CancellationToken,
+}
+
+impl RunCancellation {
+    /// Token exposed to the job through `Context`, cancelled when cancellation is requested.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Token observed by the runner, cancelled when the run has to be aborted.
+    pub fn abort_token(&self) -> &CancellationToken {
+        &self.abort_token
+    }
+
+    /// Requests cancellation, aborting the run once `grace_period` elapses if given.
+    fn cancel(&self, grace_period: Option<Duration>) {
+        self.token.cancel();
+        if let Some(grace_period) = grace_period {
+            let grace_period = grace_period.to_std().unwrap_or_default();
+            let abort_token = self.abort_token.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(grace_period).await;
+                abort_token.cancel();
+            });
+        }
+    }
+}
+
+/// Keeps cancellation handles of runs in progress, so they can be cancelled by job id.
+#[derive(Clone, Default)]
+pub struct RunCancellations {
+    runs: Arc<Mutex<HashMap<JobId, RunCancellation>>>,
+}
+
+impl VerifyService for RunCancellations {
+    fn verify(&self, _services: &Services) -> Result<(), ServiceMissing> {
+        Ok(())
+    }
+}
+
+impl RunCancellations {
+    pub fn register(&self, job_id: JobId) -> RunCancellation {
+        let run_cancellation = RunCancellation::default();
+        self.runs
+            .lock()
+            .unwrap()
+            .insert(job_id, run_cancellation.clone());
+        run_cancellation
+    }
+
+    pub fn unregister(&self, job_id: &JobId) {
+        self.runs.lock().unwrap().remove(job_id);
+    }
+
+    /// Whether a run of the job is in progress in this process.
+    pub fn is_running(&self, job_id: &JobId) -> bool {
+        self.runs.lock().unwrap().contains_key(job_id)
+    }
+
+    /// Requests cancellation of the run of a job.
+    ///
+    /// With `grace_period`, the run is aborted if it hasn't finished by the time it elapses.
+    /// Returns `false` if the job isn't running.
+    pub fn cancel(&self, job_id: &JobId, grace_period: Option<Duration>) -> bool {
+        let run_cancellation = match self.runs.lock().unwrap().get(job_id) {
+            Some(run_cancellation) => run_cancellation.clone(),
+            None => return false,
+        };
+
+        run_cancellation.cancel(grace_period);
+        true
+    }
+
+    /// Requests cancellation of all runs in progress, see `RunCancellations::cancel`.
+    pub fn cancel_all(&self, grace_period: Option<Duration>) {
+        for run_cancellation in self.runs.lock().unwrap().values() {
+            run_cancellation.cancel(grace_period);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let run_cancellations = RunCancellations::default();
+        let job_id = JobId::default();
+        let run_cancellation = run_cancellations.register(job_id);
+
+        assert!(run_cancellations.cancel(&job_id, None));
+        assert!(run_cancellation.token().is_cancelled());
+        assert!(!run_cancellation.abort_token().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_with_grace_period() {
+        let run_cancellations = RunCancellations::default();
+        let job_id = JobId::default();
+        let run_cancellation = run_cancellations.register(job_id);
+
+        assert!(run_cancellations.cancel(&job_id, Some(Duration::milliseconds(10))));
+        run_cancellation.abort_token().cancelled().await;
+        assert!(run_cancellation.token().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all() {
+        let run_cancellations = RunCancellations::default();
+        let first = run_cancellations.register(JobId::default());
+        let second = run_cancellations.register(JobId::default());
+
+        run_cancellations.cancel_all(Some(Duration::zero()));
+
+        for run_cancellation in [first, second] {
+            assert!(run_cancellation.token().is_cancelled());
+            run_cancellation.abort_token().cancelled().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_not_running() {
+        let run_cancellations = RunCancellations::default();
+        let job_id = JobId::default();
+        run_cancellations.register(job_id);
+        run_cancellations.unregister(&job_id);
+
+        assert!(!run_cancellations.cancel(&job_id, None));
+    }
+}
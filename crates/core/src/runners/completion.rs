@@ -0,0 +1,353 @@
+use std::collections::HashSet;
+
+use chrono::Duration;
+use thiserror::Error;
+use tokio::{sync::broadcast, time};
+
+use crate::{
+    domain::{
+        job::id::JobId,
+        run::{Run, id::RunId},
+    },
+    services::{
+        Services,
+        verify::{ServiceMissing, VerifyService},
+    },
+    storage::{self, Storage},
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("storage error: {0}")]
+    Storage(#[from] storage::error::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Capacity of the channel, subscribers lagging behind fall back to polling storage.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Broadcasts runs finished in this process to `JobCompletion` handles.
+#[derive(Clone)]
+pub struct RunCompletions {
+    tx: broadcast::Sender<Run>,
+}
+
+impl VerifyService for RunCompletions {
+    fn verify(&self, _services: &Services) -> std::result::Result<(), ServiceMissing> {
+        Ok(())
+    }
+}
+
+impl Default for RunCompletions {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { tx }
+    }
+}
+
+impl RunCompletions {
+    pub fn subscribe(&self) -> broadcast::Receiver<Run> {
+        self.tx.subscribe()
+    }
+
+    /// Notifies subscribers about a run, after it has been recorded and its callback invoked.
+    pub fn complete(&self, run: Run) {
+        // no subscribers is not an error
+        let _ = self.tx.send(run);
+    }
+}
+
+/// Handle resolving to the final run of a job, once it's neither pending nor running.
+///
+/// Runs finished in this process are received right away, runs finished by other
+/// processes sharing the storage are found by polling it every `poll_interval`.
+pub struct JobCompletion {
+    job_id: JobId,
+    storage: Storage,
+    rx: Option<broadcast::Receiver<Run>>,
+    poll_interval: Duration,
+    previous_run_ids: HashSet<RunId>,
+}
+
+impl JobCompletion {
+    /// Creates a handle of a job, runs finished before are ignored.
+    ///
+    /// Has to be created before the job can finish, so the final run isn't missed.
+    pub async fn new(
+        job_id: JobId,
+        storage: Storage,
+        run_completions: Option<&RunCompletions>,
+        poll_interval: Duration,
+    ) -> Result<Self> {
+        let rx = run_completions.map(RunCompletions::subscribe);
+        let previous_run_ids = storage
+            .runs_of(&job_id)
+            .await?
+            .iter()
+            .map(Run::run_id)
+            .collect();
+
+        Ok(Self {
+            job_id,
+            storage,
+            rx,
+            poll_interval,
+            previous_run_ids,
+        })
+    }
+
+    pub fn job_id(&self) -> JobId {
+        self.job_id
+    }
+
+    /// Waits for the final run of the job, combine with `tokio::time::timeout` to bound it.
+    pub async fn wait(mut self) -> Result<Run> {
+        let poll_interval = self.poll_interval.to_std().unwrap();
+        loop {
+            if let Some(run) = self.final_run().await? {
+                return Ok(run);
+            }
+            if let Some(run) = self.receive(poll_interval).await? {
+                return Ok(run);
+            }
+        }
+    }
+
+    /// Waits up to `poll_interval` for the final run to be received, returns None once
+    /// storage has to be checked again.
+    async fn receive(&mut self, poll_interval: std::time::Duration) -> Result<Option<Run>> {
+        let deadline = time::sleep(poll_interval);
+        tokio::pin!(deadline);
+
+        let Some(mut rx) = self.rx.take() else {
+            deadline.await;
+            return Ok(None);
+        };
+        loop {
+            let received = tokio::select! {
+                received = rx.recv() => received,
+                _ = &mut deadline => break,
+            };
+
+            match received {
+                // e.g. the next occurrence of a recurring job has been scheduled in the callback
+                Ok(run) if run.job_id() == self.job_id && !self.is_active().await? => {
+                    return Ok(Some(run));
+                }
+                Ok(_) => {}
+                // runs were missed, so the final one may be in storage already
+                Err(broadcast::error::RecvError::Lagged(_)) => break,
+                // nothing is received anymore, so only polling is left
+                Err(broadcast::error::RecvError::Closed) => {
+                    deadline.await;
+                    return Ok(None);
+                }
+            }
+        }
+
+        self.rx = Some(rx);
+        Ok(None)
+    }
+
+    async fn final_run(&self) -> Result<Option<Run>> {
+        if self.is_active().await? {
+            return Ok(None);
+        }
+
+        let run = self
+            .storage
+            .runs_of(&self.job_id)
+            .await?
+            .pop()
+            .filter(|run| !self.previous_run_ids.contains(&run.run_id()));
+        Ok(run)
+    }
+
+    async fn is_active(&self) -> Result<bool> {
+        let pending = self.storage.pending_job_repo().get(&self.job_id).await?;
+        let running = self.storage.running_job_repo().get(&self.job_id).await?;
+        Ok(pending.is_some() || running.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        domain::{
+            job::{
+                Job,
+                context::{Context, EmptyContextData},
+                error::{JobError, JobResult},
+                r#impl::{JobImpl, JobImplName},
+                pending::PendingJob,
+                report::Report,
+            },
+            run::failed::FailedRun,
+        },
+        managers::job_manager::JobManager,
+        registries::{job_actions::JobActionsRegistryBuilder, policies::PolicyRegistryBuilder},
+        storage::{
+            memory::{AddMemoryStorageService, MemoryStorage},
+            transaction::Transaction,
+        },
+        workers::job::JobWorkerSettings,
+    };
+
+    #[derive(Serialize, Deserialize)]
+    struct SleepJobImpl {
+        millis: u64,
+    }
+
+    #[async_trait]
+    impl JobImpl<EmptyContextData> for SleepJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("sleep")
+        }
+
+        async fn run(&self, _context: Context<EmptyContextData>) -> JobResult<Report> {
+            time::sleep(std::time::Duration::from_millis(self.millis)).await;
+            Ok(Report::new())
+        }
+
+        async fn on_success(&self, _context: Context<EmptyContextData>) {}
+
+        async fn on_fail(&self, _context: Context<EmptyContextData>) {}
+    }
+
+    fn test_manager() -> JobManager<EmptyContextData> {
+        JobManager::new_default(EmptyContextData, |builder| {
+            builder.add_service(JobWorkerSettings::default());
+            let mut job_actions_registry = JobActionsRegistryBuilder::default();
+            job_actions_registry.register::<SleepJobImpl>();
+            builder.add_service(job_actions_registry.build());
+            builder.add_service(PolicyRegistryBuilder::<EmptyContextData>::default().build());
+            builder.add_memory_storage();
+        })
+        .unwrap()
+    }
+
+    async fn schedule_sleep(
+        manager: &JobManager<EmptyContextData>,
+        millis: u64,
+        at: DateTime<Utc>,
+    ) -> JobId {
+        let job = Job::from_impl(SleepJobImpl { millis }, Utc::now(), Vec::new()).unwrap();
+        manager.schedule(job, at).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_schedule_with_completion() {
+        let manager = test_manager();
+        let job = Job::from_impl(SleepJobImpl { millis: 10 }, Utc::now(), Vec::new()).unwrap();
+        let job_id = job.id();
+
+        let completion = manager
+            .schedule_with_completion(job, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        let run = time::timeout(std::time::Duration::from_secs(5), completion.wait())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(run, Run::Successful(_)));
+        assert_eq!(run.job_id(), job_id);
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion_of_cancelled_job() {
+        let manager = test_manager();
+        let job_id = schedule_sleep(&manager, 0, Utc::now() + Duration::days(1)).await;
+        let completion = manager.completion(&job_id).await.unwrap();
+
+        manager.cancel(&job_id).await.unwrap();
+        let run = time::timeout(std::time::Duration::from_secs(5), completion.wait())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(run.error(), Some(JobError::JobCancelled)));
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion_polls_storage() {
+        let manager = test_manager();
+        let job_id = schedule_sleep(&manager, 10, Utc::now() + Duration::days(1)).await;
+        // as if the job ran on another node, without in-process notifications
+        let completion = JobCompletion::new(
+            job_id,
+            manager.context().get_required_service::<Storage>(),
+            None,
+            Duration::milliseconds(10),
+        )
+        .await
+        .unwrap();
+
+        manager
+            .reschedule(&job_id, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        let run = time::timeout(std::time::Duration::from_secs(5), completion.wait())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(run, Run::Successful(_)));
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion_after_channel_closed() {
+        let storage = Storage::from(MemoryStorage::default());
+        let job_id = JobId::default();
+        storage
+            .pending_job_repo()
+            .add(PendingJob::new(job_id, Utc::now()))
+            .await
+            .unwrap();
+        let run_completions = RunCompletions::default();
+        let completion = JobCompletion::new(
+            job_id,
+            storage.clone(),
+            Some(&run_completions),
+            Duration::milliseconds(10),
+        )
+        .await
+        .unwrap();
+        drop(run_completions);
+
+        let finished = tokio::spawn(async move {
+            time::sleep(std::time::Duration::from_millis(50)).await;
+            let run = FailedRun::new(
+                RunId::default(),
+                job_id,
+                Utc::now(),
+                Utc::now(),
+                JobError::JobCancelled,
+            );
+            storage
+                .commit(
+                    Transaction::default()
+                        .delete_pending_job(job_id)
+                        .add_failed_run(run),
+                )
+                .await
+                .unwrap();
+        });
+        let run = time::timeout(std::time::Duration::from_secs(5), completion.wait())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(run.error(), Some(JobError::JobCancelled)));
+        finished.await.unwrap();
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     domain::{
         job::{
             Job,
-            context::{Context, ContextData},
+            context::{Context, ContextData, RunInfo},
             error::{JobError, JobResult},
             id::JobId,
             pending::PendingJob,
@@ -14,17 +14,21 @@ use crate::{
             running::RunningJob,
         },
         run::{
-            id::RunId,
+            Run,
+            failed::FailedRun,
             job_actions::{JobActions, RunFn},
+            successful::SuccessfulRun,
         },
     },
     registries::{job_actions::JobActionsRegistry, policies::PolicyRegistry},
+    runners::cancellation::{RunCancellation, RunCancellations},
     services::{
         time::{AnyClock, Clock},
         verify::{ServiceMissing, VerifyService},
     },
-    storage::{self, Storage},
+    storage::{self, Storage, transaction::Transaction},
     verify_services,
+    workers::job::JobWorkerSettings,
 };
 use chrono::{DateTime, Utc};
 use thiserror::Error;
@@ -37,10 +41,22 @@ enum Error {
     CorrespondingJobNotFound,
     #[error("job actions not found")]
     JobActionsNotFound,
+    #[error("lease on the running job lost")]
+    LeaseLost,
 }
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// What became of a run once its outcome has been recorded.
+enum RunOutcome {
+    Finished(Run),
+    /// The run failed and the job is scheduled to run again at the given time.
+    Retried(DateTime<Utc>),
+    /// A policy put the run off without executing the job, nothing is recorded but the
+    /// job being scheduled again at the given time.
+    Deferred(DateTime<Utc>),
+}
+
 pub struct JobRunner<TData: ContextData> {
     context: Context<TData>,
 }
@@ -53,7 +69,9 @@ impl<TData: ContextData> VerifyService for JobRunner<TData> {
         verify_services!(
             services,
             JobActionsRegistry<TData>,
+            JobWorkerSettings,
             Storage,
+            RunCancellations,
             OnSuccessRunner<TData>,
             OnFailRunner<TData>
         );
@@ -76,62 +94,90 @@ impl<TData: ContextData> JobRunner<TData> {
         }
     }
 
-    pub async fn run(&self, pending_job: PendingJob) {
-        if let Err(error) = self.run_internal(pending_job).await {
+    /// Runs a job claimed from storage, `running_job` is the lease recorded by the claim.
+    pub async fn run(&self, pending_job: PendingJob, running_job: RunningJob) {
+        let job_id = pending_job.job_id();
+        if let Err(error) = self.run_internal(pending_job, running_job).await {
             log::error!("error during job run: {error}");
         }
-    }
 
-    async fn run_internal(&self, pending_job: PendingJob) -> Result<()> {
-        let job = self.get_job(&pending_job.job_id()).await?;
-        let now = self.context.get_required_service::<AnyClock>().utc_now();
-        self.save_running_job(&job, now).await?;
+        // unregistered only once the running job is gone, so recovery never sees it as orphaned
+        self.context
+            .get_required_service::<RunCancellations>()
+            .unregister(&job_id);
+    }
 
-        let job_actions = self
+    async fn run_internal(&self, pending_job: PendingJob, running_job: RunningJob) -> Result<()> {
+        let (job, job_actions) = match self.get_job_with_actions(&pending_job.job_id()).await {
+            Ok(job_with_actions) => job_with_actions,
+            Err(error) => {
+                self.release_running_job(&running_job).await;
+                return Err(error);
+            }
+        };
+        let run_cancellation = self
             .context
-            .get_required_service::<JobActionsRegistry<TData>>()
-            .get(job.r#impl().name())
-            .ok_or(Error::JobActionsNotFound)?;
+            .get_required_service::<RunCancellations>()
+            .register(job.id());
+        let context = self.context.with_run_info(
+            RunInfo::new(
+                job.id(),
+                running_job.run_id(),
+                pending_job.scheduled_at(),
+                running_job.started_at(),
+            )
+            .with_cancellation_token(run_cancellation.token().clone()),
+        );
 
         let policy_registry = self.context.get_required_service::<PolicyRegistry<TData>>();
 
-        let run_result = self
-            .run_job_with_policies(job_actions, policy_registry, &job)
-            .await;
+        let run_result = Self::run_cancellable(
+            &run_cancellation,
+            self.run_job_with_policies(job_actions, policy_registry, &job, context),
+        )
+        .await;
 
-        let running_job = self
-            .context
-            .get_required_service::<Storage>()
-            .running_job_repo()
-            .delete(&job.id())
-            .await?;
-
-        self.context
-            .get_required_service::<Storage>()
-            .job_repo()
-            .update_policies(&job.id(), job.policies().clone())
-            .await?;
+        let outcome = match self
+            .finish_run(&job, &pending_job, &running_job, run_result)
+            .await
+        {
+            Err(Error::LeaseLost) => {
+                log::warn!(
+                    "run {} of job {} lost its running job, discarding its outcome",
+                    running_job.run_id(),
+                    job.id()
+                );
+                return Ok(());
+            }
+            result => result?,
+        };
 
-        match run_result {
-            Ok(report) => {
+        match outcome {
+            RunOutcome::Retried(retry_at) => {
+                log::info!("run of job {} failed, retrying at {retry_at}", job.id());
+            }
+            RunOutcome::Deferred(retry_at) => {
+                log::info!("run of job {} deferred until {retry_at}", job.id());
+            }
+            RunOutcome::Finished(Run::Successful(run)) => {
                 self.context
                     .get_required_service::<OnSuccessRunner<TData>>()
                     .run(&OnSuccessRunnerInput::new(
                         job.clone(),
                         pending_job.clone(),
                         running_job,
-                        report,
+                        run,
                     ))
                     .await;
             }
-            Err(error) => {
+            RunOutcome::Finished(Run::Failed(run)) => {
                 self.context
                     .get_required_service::<OnFailRunner<TData>>()
                     .run(&OnFailRunnerInput::new(
                         job.clone(),
                         pending_job.clone(),
                         running_job,
-                        error,
+                        run,
                     ))
                     .await;
             }
@@ -139,11 +185,121 @@ impl<TData: ContextData> JobRunner<TData> {
         Ok(())
     }
 
+    /// Records the outcome of a run and removes the running job in a single transaction.
+    ///
+    /// A failed run is retried if a policy of the job says so, the retry is scheduled in
+    /// the same transaction. A run deferred by a policy is only scheduled again, without
+    /// recording a failed run. Fails with `Error::LeaseLost` if the running job was
+    /// reclaimed meanwhile, e.g. by recovery after missed heartbeats, in which case
+    /// nothing is recorded.
+    async fn finish_run(
+        &self,
+        job: &Job,
+        pending_job: &PendingJob,
+        running_job: &RunningJob,
+        run_result: JobResult<Report>,
+    ) -> Result<RunOutcome> {
+        let now = self.context.get_required_service::<AnyClock>().utc_now();
+        let deferred = matches!(run_result, Err(JobError::Deferred));
+
+        let run = match run_result {
+            Ok(report) => Run::Successful(
+                SuccessfulRun::new(
+                    running_job.run_id(),
+                    job.id(),
+                    pending_job.scheduled_at(),
+                    now,
+                    report,
+                )
+                .with_started_at(running_job.started_at()),
+            ),
+            Err(error) => Run::Failed(
+                FailedRun::new(
+                    running_job.run_id(),
+                    job.id(),
+                    pending_job.scheduled_at(),
+                    now,
+                    error,
+                )
+                .with_started_at(running_job.started_at()),
+            ),
+        };
+        // policies may update their data when deciding, so it's done before it gets stored
+        let retry_at = match &run {
+            Run::Successful(_) => None,
+            Run::Failed(run) => self.retry_at(job, run.error(), now),
+        };
+
+        let outcome = match retry_at {
+            Some(retry_at) if deferred => RunOutcome::Deferred(retry_at),
+            Some(retry_at) => RunOutcome::Retried(retry_at),
+            None => RunOutcome::Finished(run.clone()),
+        };
+
+        let transaction = Transaction::default()
+            .delete_running_job(job.id(), running_job.run_id())
+            .update_policies(job.id(), job.policies().clone());
+        let transaction = match (&outcome, &run) {
+            (RunOutcome::Deferred(_), _) => transaction,
+            (_, Run::Successful(run)) => transaction.add_successful_run(run.clone()),
+            (_, Run::Failed(run)) => transaction.add_failed_run(run.clone()),
+        };
+        let transaction = match retry_at {
+            Some(retry_at) => transaction.add_pending_job(PendingJob::new(job.id(), retry_at)),
+            None => transaction,
+        };
+
+        let storage = self.context.get_required_service::<Storage>();
+        match storage.commit(transaction).await {
+            Ok(()) => Ok(outcome),
+            Err(storage::error::Error::NotFound) => {
+                let running = storage.running_job_repo().get(&job.id()).await?;
+                match running {
+                    Some(running) if running.run_id() == running_job.run_id() => {
+                        Err(storage::error::Error::NotFound.into())
+                    }
+                    _ => Err(Error::LeaseLost),
+                }
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Asks the policies of a job in order whether its failed run should be retried.
+    fn retry_at(&self, job: &Job, error: &JobError, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let policy_registry = self.context.get_required_service::<PolicyRegistry<TData>>();
+
+        job.policies().names().iter().find_map(|name| {
+            policy_registry
+                .retry_at(name.clone(), error, job.policies().data(), now)
+                .ok()
+                .flatten()
+        })
+    }
+
+    /// Aborts the run once the abort token is cancelled, and reports a failed run
+    /// of a cancelled job as `JobError::JobCancelled`.
+    async fn run_cancellable(
+        run_cancellation: &RunCancellation,
+        run: impl Future<Output = JobResult<Report>>,
+    ) -> JobResult<Report> {
+        let run_result = tokio::select! {
+            run_result = run => run_result,
+            _ = run_cancellation.abort_token().cancelled() => Err(JobError::JobCancelled),
+        };
+
+        match run_result {
+            Err(_) if run_cancellation.token().is_cancelled() => Err(JobError::JobCancelled),
+            run_result => run_result,
+        }
+    }
+
     async fn run_job_with_policies(
         &self,
         job_actions: JobActions<TData>,
         policy_registry: PolicyRegistry<TData>,
         job: &Job,
+        context: Context<TData>,
     ) -> JobResult<Report> {
         let mut run_fn: RunFn<TData> = job_actions.get_run_fn();
 
@@ -153,18 +309,35 @@ impl<TData: ContextData> JobRunner<TData> {
                 .map_err(|_| JobError::PolicyNotFound)?;
         }
 
-        run_fn(job.r#impl().clone(), self.context.clone()).await
+        run_fn(job.r#impl().clone(), context).await
     }
 
-    async fn save_running_job(&self, job: &Job, now: DateTime<Utc>) -> Result<()> {
-        let running_job = RunningJob::new(job.id(), RunId::default(), now);
-        self.context
-            .get_required_service::<Storage>()
-            .running_job_repo()
-            .add(running_job)
-            .await?;
+    async fn get_job_with_actions(&self, job_id: &JobId) -> Result<(Job, JobActions<TData>)> {
+        let job = self.get_job(job_id).await?;
+        let job_actions = self
+            .context
+            .get_required_service::<JobActionsRegistry<TData>>()
+            .get(job.r#impl().name())
+            .ok_or(Error::JobActionsNotFound)?;
 
-        Ok(())
+        Ok((job, job_actions))
+    }
+
+    /// Removes the running job of a run that never started, so it isn't left to recovery.
+    async fn release_running_job(&self, running_job: &RunningJob) {
+        let transaction =
+            Transaction::default().delete_running_job(running_job.job_id(), running_job.run_id());
+        let result = self
+            .context
+            .get_required_service::<Storage>()
+            .commit(transaction)
+            .await;
+        if let Err(error) = result {
+            log::error!(
+                "failed to release running job {}: {error}",
+                running_job.job_id()
+            );
+        }
     }
 
     async fn get_job(&self, job_id: &JobId) -> Result<Job> {
@@ -181,3 +354,108 @@ impl<TData: ContextData> JobRunner<TData> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        domain::{
+            job::r#impl::{JobImpl, JobImplName},
+            run::id::RunId,
+            worker::id::WorkerId,
+        },
+        managers::job_manager::JobManager,
+        registries::{job_actions::JobActionsRegistryBuilder, policies::PolicyRegistryBuilder},
+        storage::memory::AddMemoryStorageService,
+    };
+
+    #[derive(Default)]
+    struct TestContextData {
+        callbacks: AtomicUsize,
+    }
+
+    impl ContextData for TestContextData {}
+
+    /// Loses its running job to another run while running, as if recovery reclaimed it.
+    #[derive(Serialize, Deserialize)]
+    struct ReclaimedJobImpl;
+
+    #[async_trait]
+    impl JobImpl<TestContextData> for ReclaimedJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("reclaimed")
+        }
+
+        async fn run(&self, context: Context<TestContextData>) -> JobResult<Report> {
+            let job_id = context.run_info().unwrap().job_id();
+            let storage = context.get_required_service::<Storage>();
+            let running_job_repo = storage.running_job_repo();
+            running_job_repo.delete(&job_id).await.unwrap();
+            running_job_repo
+                .add(RunningJob::new(
+                    job_id,
+                    RunId::default(),
+                    Utc::now(),
+                    WorkerId::default(),
+                    Utc::now(),
+                ))
+                .await
+                .unwrap();
+
+            Ok(Report::new())
+        }
+
+        async fn on_success(&self, context: Context<TestContextData>) {
+            context.data().callbacks.fetch_add(1, Ordering::SeqCst);
+        }
+
+        async fn on_fail(&self, context: Context<TestContextData>) {
+            context.data().callbacks.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lease_lost() {
+        let manager = JobManager::new_default(TestContextData::default(), |builder| {
+            let mut job_actions_registry = JobActionsRegistryBuilder::default();
+            job_actions_registry.register::<ReclaimedJobImpl>();
+            builder.add_service(job_actions_registry.build());
+            builder.add_service(PolicyRegistryBuilder::<TestContextData>::default().build());
+            builder.add_memory_storage();
+        })
+        .unwrap();
+        let storage = manager.context().get_required_service::<Storage>();
+        let job = Job::from_impl(ReclaimedJobImpl, Utc::now(), Vec::new()).unwrap();
+        storage.job_repo().add(job.clone()).await.unwrap();
+        let running_job = RunningJob::new(
+            job.id(),
+            RunId::default(),
+            Utc::now(),
+            manager.worker_id(),
+            Utc::now(),
+        );
+        storage
+            .running_job_repo()
+            .add(running_job.clone())
+            .await
+            .unwrap();
+
+        manager
+            .context()
+            .get_required_service::<JobRunner<TestContextData>>()
+            .run(PendingJob::new(job.id(), Utc::now()), running_job.clone())
+            .await;
+
+        let running = storage.running_job_repo().get(&job.id()).await.unwrap();
+        assert_ne!(running.unwrap().run_id(), running_job.run_id());
+        assert!(storage.runs_of(&job.id()).await.unwrap().is_empty());
+        assert_eq!(manager.context().data().callbacks.load(Ordering::SeqCst), 0);
+
+        manager.stop().await.unwrap();
+    }
+}
@@ -1,3 +1,7 @@
+pub mod cancellation;
+pub mod completion;
 pub mod job;
 pub mod on_fail;
 pub mod on_success;
+pub mod recovery;
+pub mod retention;
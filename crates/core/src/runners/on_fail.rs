@@ -2,29 +2,25 @@ use crate::{
     domain::{
         job::{
             Job,
-            context::{Context, ContextData},
-            error::JobError,
+            context::{Context, ContextData, RunInfo},
             pending::PendingJob,
             running::RunningJob,
         },
-        run::failed::FailedRun,
+        run::{Run, failed::FailedRun, job_actions::OnFailFn},
     },
-    registries::job_actions::JobActionsRegistry,
-    services::{
-        time::{AnyClock, Clock},
-        verify::{ServiceMissing, VerifyService},
-    },
-    storage::{self, Storage},
+    registries::{self, job_actions::JobActionsRegistry, policies::PolicyRegistry},
+    runners::completion::RunCompletions,
+    services::verify::{ServiceMissing, VerifyService},
     verify_services,
 };
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 enum Error {
-    #[error("storage error: {0}")]
-    Storage(#[from] storage::error::Error),
     #[error("job actions not found")]
     JobActionsNotFound,
+    #[error("policy error: {0}")]
+    Policy(#[from] registries::policies::Error),
 }
 
 type Result<T> = std::result::Result<T, Error>;
@@ -34,25 +30,22 @@ pub struct OnFailRunnerInput {
     job: Job,
     pending_job: PendingJob,
     running_job: RunningJob,
-    error: JobError,
+    run: FailedRun,
 }
 
 impl OnFailRunnerInput {
-    pub fn new(
-        job: Job,
-        pending_job: PendingJob,
-        running_job: RunningJob,
-        error: JobError,
-    ) -> Self {
+    pub fn new(job: Job, pending_job: PendingJob, running_job: RunningJob, run: FailedRun) -> Self {
         Self {
             job,
             pending_job,
             running_job,
-            error,
+            run,
         }
     }
 }
 
+/// Invokes the `on_fail` callback of a job wrapped by its policies, after its failed run
+/// has been recorded.
 pub struct OnFailRunner<TData: ContextData> {
     context: Context<TData>,
 }
@@ -62,7 +55,7 @@ impl<TData: ContextData> VerifyService for OnFailRunner<TData> {
         &self,
         services: &crate::services::Services,
     ) -> std::result::Result<(), ServiceMissing> {
-        verify_services!(services, JobActionsRegistry<TData>, Storage);
+        verify_services!(services, JobActionsRegistry<TData>, PolicyRegistry<TData>);
         Ok(())
     }
 }
@@ -84,32 +77,195 @@ impl<TData: ContextData> OnFailRunner<TData> {
         if let Err(error) = self.run_internal(input).await {
             log::error!("error during on_fail callback run: {error}");
         }
+
+        if let Some(run_completions) = self.context.get_service::<RunCompletions>() {
+            run_completions.complete(Run::Failed(input.run.clone()));
+        }
     }
 
     async fn run_internal(&self, input: &OnFailRunnerInput) -> Result<()> {
-        let now = self.context.get_required_service::<AnyClock>().utc_now();
-        let failed_run = FailedRun::new(
-            input.running_job.run_id(),
-            input.job.id(),
-            input.pending_job.scheduled_at(),
-            now,
-            input.error.clone(),
-        );
-
-        self.context
-            .get_required_service::<Storage>()
-            .failed_run_repo()
-            .add(failed_run)
-            .await?;
-
         let job_actions = self
             .context
             .get_required_service::<JobActionsRegistry<TData>>()
             .get(input.job.r#impl().name())
             .ok_or(Error::JobActionsNotFound)?;
 
-        (job_actions.get_on_fail_fn())(input.job.r#impl().clone(), self.context.clone()).await;
+        let context = self.context.with_run_info(RunInfo::new(
+            input.job.id(),
+            input.running_job.run_id(),
+            input.pending_job.scheduled_at(),
+            input.running_job.started_at(),
+        ));
+        let policy_registry = self.context.get_required_service::<PolicyRegistry<TData>>();
+        let mut on_fail_fn: OnFailFn<TData> = job_actions.get_on_fail_fn();
+        for policy_name in input.job.policies().names() {
+            on_fail_fn = policy_registry.wrap_on_fail(
+                policy_name.clone(),
+                on_fail_fn,
+                input.job.policies().data(),
+            )?;
+        }
+
+        on_fail_fn(input.job.r#impl().clone(), context).await;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use async_trait::async_trait;
+    use chrono::{Duration, Utc};
+    use serde::{Deserialize, Serialize};
+    use tokio::time;
+
+    use crate::{
+        domain::job::{
+            error::{CustomError, JobResult},
+            r#impl::{JobImpl, JobImplName},
+            policy::{Policy, PolicyData, PolicyName},
+            report::Report,
+        },
+        managers::job_manager::JobManager,
+        registries::{job_actions::JobActionsRegistryBuilder, policies::PolicyRegistryBuilder},
+        storage::memory::AddMemoryStorageService,
+        workers::job::JobWorkerSettings,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestContextData {
+        failed_callbacks: AtomicUsize,
+        wrapped_callbacks: AtomicUsize,
+    }
+
+    impl ContextData for TestContextData {}
+
+    #[derive(Serialize, Deserialize)]
+    struct FailingJobImpl;
+
+    #[async_trait]
+    impl JobImpl<TestContextData> for FailingJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("failing")
+        }
+
+        async fn run(&self, _context: Context<TestContextData>) -> JobResult<Report> {
+            Err(CustomError::new("unavailable").into())
+        }
+
+        async fn on_success(&self, _context: Context<TestContextData>) {}
+
+        async fn on_fail(&self, context: Context<TestContextData>) {
+            context
+                .data()
+                .failed_callbacks
+                .fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Replaces `on_fail` with a no-op.
+    struct SuppressOnFailPolicy;
+
+    impl Policy<TestContextData> for SuppressOnFailPolicy {
+        fn name(&self) -> PolicyName {
+            PolicyName::new("test::suppress_on_fail")
+        }
+
+        fn wrap_on_fail(
+            &self,
+            _f: OnFailFn<TestContextData>,
+            _data: PolicyData,
+        ) -> OnFailFn<TestContextData> {
+            Arc::new(|_, _| Box::pin(async {}))
+        }
+    }
+
+    /// Counts `on_fail` invocations before calling the wrapped `on_fail`.
+    struct CountOnFailPolicy;
+
+    impl Policy<TestContextData> for CountOnFailPolicy {
+        fn name(&self) -> PolicyName {
+            PolicyName::new("test::count_on_fail")
+        }
+
+        fn wrap_on_fail(
+            &self,
+            f: OnFailFn<TestContextData>,
+            _data: PolicyData,
+        ) -> OnFailFn<TestContextData> {
+            Arc::new(move |serialized_job_impl, context| {
+                let f = f.clone();
+                Box::pin(async move {
+                    context
+                        .data()
+                        .wrapped_callbacks
+                        .fetch_add(1, Ordering::SeqCst);
+                    f(serialized_job_impl, context).await
+                })
+            })
+        }
+    }
+
+    /// Runs a failing job with the given policies and waits for its `on_fail` callback.
+    async fn run_failing(
+        policies: Vec<Box<dyn Policy<TestContextData>>>,
+    ) -> JobManager<TestContextData> {
+        let manager = JobManager::new_default(TestContextData::default(), |builder| {
+            builder.add_service(JobWorkerSettings::new(Duration::milliseconds(1), 32).unwrap());
+            let mut job_actions_registry = JobActionsRegistryBuilder::default();
+            job_actions_registry.register::<FailingJobImpl>();
+            builder.add_service(job_actions_registry.build());
+            let mut policy_registry = PolicyRegistryBuilder::<TestContextData>::default();
+            policy_registry.register(SuppressOnFailPolicy);
+            policy_registry.register(CountOnFailPolicy);
+            builder.add_service(policy_registry.build());
+            builder.add_memory_storage();
+        })
+        .unwrap();
+        let job = Job::from_impl(FailingJobImpl, Utc::now(), policies).unwrap();
+
+        // completes once `on_fail` has returned
+        let completion = manager
+            .schedule_with_completion(job, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        time::timeout(std::time::Duration::from_secs(5), completion.wait())
+            .await
+            .unwrap()
+            .unwrap();
+
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_policy_augments_on_fail() {
+        let manager = run_failing(vec![Box::new(CountOnFailPolicy)]).await;
+
+        let data = manager.context().data();
+        assert_eq!(data.wrapped_callbacks.load(Ordering::SeqCst), 1);
+        assert_eq!(data.failed_callbacks.load(Ordering::SeqCst), 1);
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_policy_suppresses_on_fail() {
+        // policies listed later wrap the earlier ones
+        let manager = run_failing(vec![
+            Box::new(SuppressOnFailPolicy),
+            Box::new(CountOnFailPolicy),
+        ])
+        .await;
+
+        let data = manager.context().data();
+        assert_eq!(data.wrapped_callbacks.load(Ordering::SeqCst), 1);
+        assert_eq!(data.failed_callbacks.load(Ordering::SeqCst), 0);
+        manager.stop().await.unwrap();
+    }
+}
@@ -2,29 +2,25 @@ use crate::{
     domain::{
         job::{
             self, Job,
-            context::{Context, ContextData},
+            context::{Context, ContextData, RunInfo},
             pending::PendingJob,
-            report::Report,
             running::RunningJob,
         },
-        run::successful::SuccessfulRun,
+        run::{Run, job_actions::OnSuccessFn, successful::SuccessfulRun},
     },
-    registries::job_actions::JobActionsRegistry,
-    services::{
-        time::{AnyClock, Clock},
-        verify::{ServiceMissing, VerifyService},
-    },
-    storage::{self, Storage},
+    registries::{self, job_actions::JobActionsRegistry, policies::PolicyRegistry},
+    runners::completion::RunCompletions,
+    services::verify::{ServiceMissing, VerifyService},
     verify_services,
 };
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 enum Error {
-    #[error("storage error: {0}")]
-    Storage(#[from] storage::error::Error),
     #[error("job actions not found")]
     JobActionsNotFound,
+    #[error("policy error: {0}")]
+    Policy(#[from] registries::policies::Error),
     #[error("on_success callback failed: {0}")]
     CallbackFailed(#[from] job::error::JobError),
 }
@@ -36,20 +32,27 @@ pub struct OnSuccessRunnerInput {
     job: Job,
     pending_job: PendingJob,
     running_job: RunningJob,
-    report: Report,
+    run: SuccessfulRun,
 }
 
 impl OnSuccessRunnerInput {
-    pub fn new(job: Job, pending_job: PendingJob, running_job: RunningJob, report: Report) -> Self {
+    pub fn new(
+        job: Job,
+        pending_job: PendingJob,
+        running_job: RunningJob,
+        run: SuccessfulRun,
+    ) -> Self {
         Self {
             job,
             pending_job,
             running_job,
-            report,
+            run,
         }
     }
 }
 
+/// Invokes the `on_success` callback of a job wrapped by its policies, after its successful
+/// run has been recorded.
 pub struct OnSuccessRunner<TData: ContextData> {
     context: Context<TData>,
 }
@@ -59,7 +62,7 @@ impl<TData: ContextData> VerifyService for OnSuccessRunner<TData> {
         &self,
         services: &crate::services::Services,
     ) -> std::result::Result<(), ServiceMissing> {
-        verify_services!(services, JobActionsRegistry<TData>, Storage, AnyClock);
+        verify_services!(services, JobActionsRegistry<TData>, PolicyRegistry<TData>);
         Ok(())
     }
 }
@@ -81,31 +84,36 @@ impl<TData: ContextData> OnSuccessRunner<TData> {
         if let Err(error) = self.run_internal(input).await {
             log::error!("error during on_success callback run: {error}");
         }
+
+        if let Some(run_completions) = self.context.get_service::<RunCompletions>() {
+            run_completions.complete(Run::Successful(input.run.clone()));
+        }
     }
 
     async fn run_internal(&self, input: &OnSuccessRunnerInput) -> Result<()> {
-        let now = self.context.get_required_service::<AnyClock>().utc_now();
-        let successful_run = SuccessfulRun::new(
-            input.running_job.run_id(),
-            input.job.id(),
-            input.pending_job.scheduled_at(),
-            now,
-            input.report.clone(),
-        );
-
-        self.context
-            .get_required_service::<Storage>()
-            .successful_run_repo()
-            .add(successful_run)
-            .await?;
-
         let job_actions = self
             .context
             .get_required_service::<JobActionsRegistry<TData>>()
             .get(input.job.r#impl().name())
             .ok_or(Error::JobActionsNotFound)?;
 
-        (job_actions.get_on_success_fn())(input.job.r#impl().clone(), self.context.clone()).await;
+        let context = self.context.with_run_info(RunInfo::new(
+            input.job.id(),
+            input.running_job.run_id(),
+            input.pending_job.scheduled_at(),
+            input.running_job.started_at(),
+        ));
+        let policy_registry = self.context.get_required_service::<PolicyRegistry<TData>>();
+        let mut on_success_fn: OnSuccessFn<TData> = job_actions.get_on_success_fn();
+        for policy_name in input.job.policies().names() {
+            on_success_fn = policy_registry.wrap_on_success(
+                policy_name.clone(),
+                on_success_fn,
+                input.job.policies().data(),
+            )?;
+        }
+
+        on_success_fn(input.job.r#impl().clone(), context).await;
 
         Ok(())
     }
@@ -0,0 +1,184 @@
+use super::{
+    cancellation::RunCancellations,
+    on_fail::{OnFailRunner, OnFailRunnerInput},
+};
+use crate::{
+    domain::{
+        job::{
+            context::{Context, ContextData},
+            error::JobError,
+            pending::PendingJob,
+            recovery::RecoveryPolicy,
+            running::RunningJob,
+        },
+        run::failed::FailedRun,
+    },
+    services::{
+        notify::PendingJobNotifier,
+        time::{AnyClock, Clock},
+        verify::{ServiceMissing, VerifyService},
+    },
+    storage::{self, Storage, transaction::Transaction},
+    verify_services,
+    workers::job::JobWorkerSettings,
+};
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+enum Error {
+    #[error("storage error: {0}")]
+    Storage(#[from] storage::error::Error),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Recovers running jobs whose owner is gone, according to their `RecoveryPolicy`.
+///
+/// A running job is considered orphaned when its worker hasn't renewed the lease within
+/// `JobWorkerSettings` lease duration. Orphaned runs may be reclaimed by any worker sharing
+/// the storage, so a job can run more than once, but never gets lost.
+pub struct RecoveryRunner<TData: ContextData> {
+    context: Context<TData>,
+}
+
+impl<TData: ContextData> VerifyService for RecoveryRunner<TData> {
+    fn verify(
+        &self,
+        services: &crate::services::Services,
+    ) -> std::result::Result<(), ServiceMissing> {
+        verify_services!(
+            services,
+            JobWorkerSettings,
+            Storage,
+            RunCancellations,
+            OnFailRunner<TData>
+        );
+        Ok(())
+    }
+}
+
+impl<TData: ContextData> Clone for RecoveryRunner<TData> {
+    fn clone(&self) -> Self {
+        Self {
+            context: self.context.clone(),
+        }
+    }
+}
+
+impl<TData: ContextData> RecoveryRunner<TData> {
+    pub fn new(context: Context<TData>) -> Self {
+        Self { context }
+    }
+
+    pub async fn run(&self) {
+        match self.run_internal().await {
+            Ok(0) => {}
+            Ok(recovered) => log::info!("recovered {recovered} orphaned running jobs"),
+            Err(error) => log::error!("error during orphaned running jobs recovery: {error}"),
+        }
+    }
+
+    async fn run_internal(&self) -> Result<usize> {
+        let storage = self.context.get_required_service::<Storage>();
+        let run_cancellations = self.context.get_required_service::<RunCancellations>();
+        let lease_duration = self
+            .context
+            .get_required_service::<JobWorkerSettings>()
+            .lease_duration();
+        let now = self.context.get_required_service::<AnyClock>().utc_now();
+        let heartbeat_before = now - lease_duration;
+
+        // runs in progress in this process are never reclaimed, even if renewing their lease failed
+        let orphaned = storage
+            .running_job_repo()
+            .get_all()
+            .await?
+            .into_iter()
+            .filter(|running_job| {
+                running_job.heartbeat_at() < heartbeat_before
+                    && !run_cancellations.is_running(&running_job.job_id())
+            })
+            .collect::<Vec<_>>();
+
+        let mut recovered = 0;
+        for running_job in orphaned {
+            match self.recover(&storage, running_job, heartbeat_before).await {
+                Ok(true) => recovered += 1,
+                Ok(false) => {}
+                Err(error) => log::error!("failed to recover orphaned running job: {error}"),
+            }
+        }
+
+        Ok(recovered)
+    }
+
+    /// Returns `false` if the run has been reclaimed or renewed in the meantime.
+    async fn recover(
+        &self,
+        storage: &Storage,
+        running_job: RunningJob,
+        heartbeat_before: DateTime<Utc>,
+    ) -> Result<bool> {
+        let Some(job) = storage.job_repo().get(&running_job.job_id()).await? else {
+            let deleted = storage
+                .running_job_repo()
+                .delete_expired(&running_job.job_id(), heartbeat_before)
+                .await?;
+            return Ok(deleted.is_some());
+        };
+
+        // reclaiming is a single transaction, so only one worker recovers a run
+        let transaction = Transaction::default()
+            .delete_expired_running_job(running_job.job_id(), heartbeat_before);
+        let now = self.context.get_required_service::<AnyClock>().utc_now();
+        // the original scheduled time isn't stored with the running job
+        let pending_job = PendingJob::new(job.id(), running_job.started_at());
+        let failed_run = FailedRun::new(
+            running_job.run_id(),
+            job.id(),
+            pending_job.scheduled_at(),
+            now,
+            JobError::Orphaned,
+        )
+        .with_started_at(running_job.started_at());
+
+        let transaction = match job.recovery_policy() {
+            RecoveryPolicy::Fail => transaction.add_failed_run(failed_run.clone()),
+            // already scheduled again, e.g. a recurring job that got triggered
+            RecoveryPolicy::Requeue
+                if storage.pending_job_repo().get(&job.id()).await?.is_some() =>
+            {
+                transaction
+            }
+            RecoveryPolicy::Requeue => transaction.add_pending_job(PendingJob::new(job.id(), now)),
+        };
+
+        match storage.commit(transaction).await {
+            Ok(_) => {}
+            Err(storage::error::Error::NotFound) => return Ok(false),
+            Err(error) => return Err(error.into()),
+        }
+
+        match job.recovery_policy() {
+            RecoveryPolicy::Fail => {
+                self.context
+                    .get_required_service::<OnFailRunner<TData>>()
+                    .run(&OnFailRunnerInput::new(
+                        job,
+                        pending_job,
+                        running_job,
+                        failed_run,
+                    ))
+                    .await;
+            }
+            RecoveryPolicy::Requeue => {
+                if let Some(notifier) = self.context.get_service::<PendingJobNotifier>() {
+                    notifier.notify();
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{
+    domain::job::context::{Context, ContextData},
+    registries::job_actions::JobActionsRegistry,
+    services::{
+        Services,
+        time::{AnyClock, Clock},
+        verify::{ServiceMissing, VerifyService},
+    },
+    storage::{
+        self, Storage,
+        query::{Filter, JobState},
+        transaction::Transaction,
+    },
+    verify_services,
+};
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid settings: {0}")]
+    InvalidSettings(String),
+    #[error("storage error: {0}")]
+    Storage(#[from] storage::error::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// How long finished jobs and run records are kept in storage.
+///
+/// Everything is kept forever by default.
+#[derive(Clone, Copy, Debug)]
+pub struct RetentionSettings {
+    successful_run_retention: Option<Duration>,
+    failed_run_retention: Option<Duration>,
+    finished_job_retention: Option<Duration>,
+    sweep_interval: Duration,
+}
+
+impl VerifyService for RetentionSettings {
+    fn verify(&self, _services: &Services) -> std::result::Result<(), ServiceMissing> {
+        Ok(())
+    }
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            successful_run_retention: None,
+            failed_run_retention: None,
+            finished_job_retention: None,
+            sweep_interval: Duration::hours(1),
+        }
+    }
+}
+
+impl RetentionSettings {
+    /// Deletes successful runs once they finished longer than `retention` ago.
+    pub fn with_successful_run_retention(mut self, retention: Duration) -> Result<Self> {
+        self.successful_run_retention = Some(Self::validate_retention(retention)?);
+        Ok(self)
+    }
+
+    /// Deletes failed runs once they finished longer than `retention` ago.
+    pub fn with_failed_run_retention(mut self, retention: Duration) -> Result<Self> {
+        self.failed_run_retention = Some(Self::validate_retention(retention)?);
+        Ok(self)
+    }
+
+    /// Deletes jobs that are neither pending nor running once their last run finished
+    /// longer than `retention` ago.
+    ///
+    /// Jobs that never ran and jobs whose impl opts out with
+    /// `JobImpl::retain_when_finished`, e.g. recurring ones, are kept. So are jobs whose
+    /// runs were already deleted, so `retention` shouldn't exceed the run retentions.
+    pub fn with_finished_job_retention(mut self, retention: Duration) -> Result<Self> {
+        self.finished_job_retention = Some(Self::validate_retention(retention)?);
+        Ok(self)
+    }
+
+    /// Sets how often the retention is enforced.
+    ///
+    /// Defaults to 1 hour.
+    pub fn with_sweep_interval(mut self, sweep_interval: Duration) -> Result<Self> {
+        if sweep_interval <= Duration::zero() {
+            return Err(Error::InvalidSettings(
+                "sweep_interval has to be positive".to_owned(),
+            ));
+        }
+        self.sweep_interval = sweep_interval;
+        Ok(self)
+    }
+
+    pub fn successful_run_retention(&self) -> Option<Duration> {
+        self.successful_run_retention
+    }
+
+    pub fn failed_run_retention(&self) -> Option<Duration> {
+        self.failed_run_retention
+    }
+
+    pub fn finished_job_retention(&self) -> Option<Duration> {
+        self.finished_job_retention
+    }
+
+    pub fn sweep_interval(&self) -> Duration {
+        self.sweep_interval
+    }
+
+    /// Whether anything is ever deleted.
+    pub fn is_enabled(&self) -> bool {
+        self.successful_run_retention.is_some()
+            || self.failed_run_retention.is_some()
+            || self.finished_job_retention.is_some()
+    }
+
+    fn validate_retention(retention: Duration) -> Result<Duration> {
+        if retention < Duration::zero() {
+            return Err(Error::InvalidSettings(
+                "retention can't be negative".to_owned(),
+            ));
+        }
+        Ok(retention)
+    }
+}
+
+/// Deletes finished jobs and run records older than configured in `RetentionSettings`.
+pub struct RetentionRunner<TData: ContextData> {
+    context: Context<TData>,
+}
+
+impl<TData: ContextData> VerifyService for RetentionRunner<TData> {
+    fn verify(&self, services: &Services) -> std::result::Result<(), ServiceMissing> {
+        verify_services!(
+            services,
+            RetentionSettings,
+            Storage,
+            AnyClock,
+            JobActionsRegistry<TData>
+        );
+        Ok(())
+    }
+}
+
+impl<TData: ContextData> Clone for RetentionRunner<TData> {
+    fn clone(&self) -> Self {
+        Self {
+            context: self.context.clone(),
+        }
+    }
+}
+
+impl<TData: ContextData> RetentionRunner<TData> {
+    pub fn new(context: Context<TData>) -> Self {
+        Self { context }
+    }
+
+    pub async fn run(&self) {
+        match self.run_internal().await {
+            Ok(0) => {}
+            Ok(deleted) => log::info!("deleted {deleted} expired jobs and runs"),
+            Err(error) => log::error!("error during retention sweep: {error}"),
+        }
+    }
+
+    async fn run_internal(&self) -> Result<usize> {
+        let settings = self.context.get_required_service::<RetentionSettings>();
+        let storage = self.context.get_required_service::<Storage>();
+        let now = self.context.get_required_service::<AnyClock>().utc_now();
+
+        let mut deleted = 0;
+        // jobs go first, as a job whose runs are deleted looks like it never ran
+        if let Some(retention) = settings.finished_job_retention() {
+            deleted += self.delete_finished_jobs(&storage, now - retention).await?;
+        }
+        if let Some(retention) = settings.successful_run_retention() {
+            deleted += storage
+                .successful_run_repo()
+                .delete_finished_before(now - retention)
+                .await?;
+        }
+        if let Some(retention) = settings.failed_run_retention() {
+            deleted += storage
+                .failed_run_repo()
+                .delete_finished_before(now - retention)
+                .await?;
+        }
+
+        Ok(deleted)
+    }
+
+    async fn delete_finished_jobs(
+        &self,
+        storage: &Storage,
+        finished_before: DateTime<Utc>,
+    ) -> Result<usize> {
+        let job_actions_registry = self
+            .context
+            .get_required_service::<JobActionsRegistry<TData>>();
+
+        let mut deleted = 0;
+        let mut offset = 0;
+        loop {
+            let jobs = storage
+                .job_repo()
+                .list(
+                    &Filter::default()
+                        .with_state(JobState::Finished)
+                        .with_to(finished_before)
+                        .with_offset(offset),
+                )
+                .await?;
+            if jobs.is_empty() {
+                return Ok(deleted);
+            }
+
+            for job in jobs {
+                // jobs of unknown impls are kept, as it's not known whether they opted out
+                let retained = job_actions_registry
+                    .get(job.r#impl().name())
+                    .is_none_or(|job_actions| job_actions.retain_when_finished());
+                if retained {
+                    offset += 1;
+                    continue;
+                }
+
+                // the storage checks again that the job is finished, as it might have
+                // been scheduled since it was listed, and keeps jobs that never ran
+                let result = storage
+                    .commit(Transaction::default().delete_finished_job(job.id(), finished_before))
+                    .await;
+                match result {
+                    Ok(()) => deleted += 1,
+                    Err(storage::error::Error::NotFound) => offset += 1,
+                    Err(error) => return Err(error.into()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        domain::{
+            job::{
+                Job,
+                context::EmptyContextData,
+                error::JobResult,
+                id::JobId,
+                r#impl::{JobImpl, JobImplName, SerializedJobImpl},
+                pending::PendingJob,
+                policy::{Policies, PolicyData},
+                report::Report,
+            },
+            run::{id::RunId, successful::SuccessfulRun},
+        },
+        registries::job_actions::JobActionsRegistryBuilder,
+        services::time::FixedClock,
+        storage::memory::AddMemoryStorageService,
+    };
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJobImpl;
+
+    #[async_trait]
+    impl JobImpl<EmptyContextData> for TestJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("test")
+        }
+
+        async fn run(&self, _context: Context<EmptyContextData>) -> JobResult<Report> {
+            Ok(Report::new())
+        }
+
+        async fn on_success(&self, _context: Context<EmptyContextData>) {}
+
+        async fn on_fail(&self, _context: Context<EmptyContextData>) {}
+    }
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    fn job() -> Job {
+        Job::new(
+            JobId::default(),
+            at(0),
+            SerializedJobImpl::new(JobImplName::new("test"), serde_json::Value::Null),
+            Policies::new(Vec::new(), PolicyData::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_retention_deletes_finished_job() {
+        let services = Services::default();
+        services.add_service(AnyClock::new(FixedClock(at(1000))));
+        services.add_service(
+            RetentionSettings::default()
+                .with_successful_run_retention(Duration::zero())
+                .unwrap()
+                .with_finished_job_retention(Duration::zero())
+                .unwrap(),
+        );
+        let mut job_actions_registry = JobActionsRegistryBuilder::<EmptyContextData>::default();
+        job_actions_registry.register::<TestJobImpl>();
+        services.add_service(job_actions_registry.build());
+        services.add_memory_storage();
+        let storage = services.get_required_service::<Storage>();
+        let finished = job();
+        let pending = job();
+        let never_run = job();
+        for job in [&finished, &pending, &never_run] {
+            storage.job_repo().add(job.clone()).await.unwrap();
+        }
+        storage
+            .successful_run_repo()
+            .add(SuccessfulRun::new(
+                RunId::default(),
+                finished.id(),
+                at(0),
+                at(1),
+                Report::new(),
+            ))
+            .await
+            .unwrap();
+        storage
+            .pending_job_repo()
+            .add(PendingJob::new(pending.id(), at(2000)))
+            .await
+            .unwrap();
+
+        RetentionRunner::<EmptyContextData>::new(Context::new(EmptyContextData, services))
+            .run()
+            .await;
+
+        assert!(
+            storage
+                .job_repo()
+                .get(&finished.id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(storage.runs_of(&finished.id()).await.unwrap().is_empty());
+        for job in [&pending, &never_run] {
+            assert!(storage.job_repo().get(&job.id()).await.unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn test_invalid_retention() {
+        let result = RetentionSettings::default().with_failed_run_retention(Duration::days(-1));
+
+        assert!(result.is_err());
+    }
+}
@@ -1,3 +1,5 @@
+pub mod notify;
+pub mod rate_limit;
 pub mod time;
 pub mod verify;
 
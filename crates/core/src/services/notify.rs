@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+use super::{
+    Services,
+    verify::{ServiceMissing, VerifyService},
+};
+
+/// Wakes the job worker when pending jobs change, so a newly scheduled job
+/// doesn't wait for the next poll to be picked up.
+#[derive(Clone, Default)]
+pub struct PendingJobNotifier {
+    inner: Arc<Notify>,
+}
+
+impl VerifyService for PendingJobNotifier {
+    fn verify(&self, _services: &Services) -> Result<(), ServiceMissing> {
+        Ok(())
+    }
+}
+
+impl PendingJobNotifier {
+    pub fn notify(&self) {
+        self.inner.notify_one();
+    }
+
+    /// Waits for a notification, returning immediately if one has been sent
+    /// since the previous wait.
+    pub async fn notified(&self) {
+        self.inner.notified().await;
+    }
+}
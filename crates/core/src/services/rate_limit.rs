@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+use super::{
+    Services,
+    verify::{ServiceMissing, VerifyService},
+};
+use crate::storage;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid rate: {0}")]
+    InvalidRate(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Tolerance of the floating point token count, so refills don't miss a token by a rounding error.
+const EPSILON: f64 = 1e-9;
+
+/// Budget of a token bucket, `amount` tokens refilled evenly over `per`.
+///
+/// A full bucket holds `amount` tokens, so that many runs may start at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rate {
+    amount: u32,
+    per: Duration,
+}
+
+impl Rate {
+    pub fn new(amount: u32, per: Duration) -> Result<Self> {
+        if amount == 0 {
+            return Err(Error::InvalidRate("amount has to be positive".to_owned()));
+        }
+        // buckets are refilled per millisecond
+        if per < Duration::milliseconds(1) {
+            return Err(Error::InvalidRate(
+                "per has to be at least 1 millisecond".to_owned(),
+            ));
+        }
+        Ok(Self { amount, per })
+    }
+
+    pub fn per_second(amount: u32) -> Result<Self> {
+        Self::new(amount, Duration::seconds(1))
+    }
+
+    pub fn amount(&self) -> u32 {
+        self.amount
+    }
+
+    pub fn per(&self) -> Duration {
+        self.per
+    }
+
+    /// Tokens added to a bucket every millisecond.
+    pub fn tokens_per_milli(&self) -> f64 {
+        self.amount as f64 / self.per.num_milliseconds() as f64
+    }
+
+    /// When a bucket holding `tokens` at `now` holds a whole token.
+    pub fn next_token_at(&self, tokens: f64, now: DateTime<Utc>) -> DateTime<Utc> {
+        let millis = ((1.0 - tokens) / self.tokens_per_milli() - EPSILON)
+            .ceil()
+            .max(0.0);
+        now + Duration::milliseconds(millis as i64)
+    }
+}
+
+/// Outcome of taking a token from a bucket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Permit {
+    Granted,
+    /// The bucket is empty until `retry_at`.
+    Denied {
+        retry_at: DateTime<Utc>,
+    },
+}
+
+/// Keeps token buckets, e.g. in memory of the process or in storage shared by processes.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Refills the bucket of `key` according to `rate` and takes a token if there is one.
+    async fn try_acquire(
+        &self,
+        key: &str,
+        rate: Rate,
+        now: DateTime<Utc>,
+    ) -> storage::error::Result<Permit>;
+}
+
+/// Keeps token buckets in memory, so they're shared by workers of a single process.
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: DateTime<Utc>,
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn try_acquire(
+        &self,
+        key: &str,
+        rate: Rate,
+        now: DateTime<Utc>,
+    ) -> storage::error::Result<Permit> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: rate.amount() as f64,
+            updated_at: now,
+        });
+
+        let elapsed = (now - bucket.updated_at).num_milliseconds().max(0);
+        bucket.tokens =
+            (bucket.tokens + elapsed as f64 * rate.tokens_per_milli()).min(rate.amount() as f64);
+        // only the counted milliseconds move the bucket forward, so frequent calls still refill it
+        bucket.updated_at += Duration::milliseconds(elapsed);
+
+        if bucket.tokens < 1.0 - EPSILON {
+            return Ok(Permit::Denied {
+                retry_at: rate.next_token_at(bucket.tokens, now),
+            });
+        }
+        bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+        Ok(Permit::Granted)
+    }
+}
+
+/// Token buckets used by `RateLimitPolicy`, kept in memory unless created with another store.
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl VerifyService for RateLimiter {
+    fn verify(&self, _services: &Services) -> std::result::Result<(), ServiceMissing> {
+        Ok(())
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(MemoryRateLimitStore::default())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(store: impl RateLimitStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    pub async fn try_acquire(
+        &self,
+        key: &str,
+        rate: Rate,
+        now: DateTime<Utc>,
+    ) -> storage::error::Result<Permit> {
+        self.store.try_acquire(key, rate, now).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let rate_limiter = RateLimiter::default();
+        let rate = Rate::per_second(2).unwrap();
+
+        for _ in 0..2 {
+            let permit = rate_limiter.try_acquire("test", rate, at(0)).await.unwrap();
+            assert_eq!(permit, Permit::Granted);
+        }
+        let permit = rate_limiter
+            .try_acquire("test", rate, at(100))
+            .await
+            .unwrap();
+        assert_eq!(permit, Permit::Denied { retry_at: at(500) });
+
+        let permit = rate_limiter
+            .try_acquire("test", rate, at(500))
+            .await
+            .unwrap();
+        assert_eq!(permit, Permit::Granted);
+        let permit = rate_limiter
+            .try_acquire("other", rate, at(500))
+            .await
+            .unwrap();
+        assert_eq!(permit, Permit::Granted);
+    }
+
+    #[test]
+    fn test_invalid_rate() {
+        assert!(Rate::per_second(0).is_err());
+        assert!(Rate::new(1, Duration::zero()).is_err());
+        assert!(Rate::new(1, Duration::microseconds(500)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_refills_below_millisecond() {
+        let rate_limiter = RateLimiter::default();
+        let rate = Rate::new(1, Duration::milliseconds(2)).unwrap();
+        let at_micros = |micros| DateTime::from_timestamp_micros(micros).unwrap();
+
+        let permit = rate_limiter.try_acquire("test", rate, at(0)).await.unwrap();
+        assert_eq!(permit, Permit::Granted);
+        for micros in [600, 1200] {
+            let permit = rate_limiter
+                .try_acquire("test", rate, at_micros(micros))
+                .await
+                .unwrap();
+            assert!(matches!(permit, Permit::Denied { .. }));
+        }
+
+        let permit = rate_limiter
+            .try_acquire("test", rate, at_micros(2100))
+            .await
+            .unwrap();
+        assert_eq!(permit, Permit::Granted);
+    }
+}
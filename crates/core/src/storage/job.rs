@@ -1,11 +1,19 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 
-use crate::domain::job::{
-    Job, id::JobId, pending::PendingJob, policy::Policies, running::RunningJob,
+use crate::domain::{
+    job::{
+        Job,
+        id::JobId,
+        r#impl::{JobImplName, SerializedJobImpl},
+        pending::PendingJob,
+        policy::Policies,
+        running::RunningJob,
+    },
+    worker::id::WorkerId,
 };
 
-use super::error::Result;
+use super::{error::Result, query::Filter};
 
 /// Repository interface for managing `Job` entities.
 ///
@@ -56,6 +64,35 @@ pub trait JobRepo: Send + Sync + 'static {
     async fn delete(&self, job_id: &JobId) -> Result<Job>;
 
     async fn update_policies(&self, job_id: &JobId, policies: Policies) -> Result<()>;
+
+    /// Replaces the serialized implementation of a job, e.g. when its definition changes.
+    ///
+    /// Fails with `NotFound` if the job doesn't exist.
+    async fn update_impl(&self, job_id: &JobId, r#impl: SerializedJobImpl) -> Result<()>;
+
+    /// Retrieves all jobs whose implementation has the given name.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the job implementation, as returned by `JobImpl::name`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<Job>>` - Returns the matching jobs in no particular order,
+    ///   or an error if the retrieval operation failed.
+    async fn get_by_impl_name(&self, name: &JobImplName) -> Result<Vec<Job>>;
+
+    /// Lists jobs matching a filter, ordered by `created_at`.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter` - The filter and page of the listing.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<Job>>` - Returns the page of matching jobs,
+    ///   or an error if the retrieval operation failed.
+    async fn list(&self, filter: &Filter) -> Result<Vec<Job>>;
 }
 
 /// Repository interface for managing `PendingJob` entities.
@@ -116,6 +153,46 @@ pub trait PendingJobRepo: Send + Sync + 'static {
     ///   None if no jobs are scheduled, or an error if the operation failed. There is no guarantee
     ///   on order of retrieved jobs.
     async fn pop_scheduled(&self, now: DateTime<Utc>) -> Result<Option<PendingJob>>;
+
+    /// Atomically removes the next scheduled pending job and adds a running job for it,
+    /// so a job is never lost between being popped and being recorded as running.
+    ///
+    /// # Parameters
+    ///
+    /// * `now` - The current time, used as `started_at` and `heartbeat_at` of the running job.
+    /// * `worker_id` - The identity of the worker starting the run.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(PendingJob, RunningJob)>>` - Returns the claimed pending job with its
+    ///   new running job, None if no jobs are scheduled, or an error if the operation failed,
+    ///   in which case the pending job stays scheduled.
+    async fn claim(
+        &self,
+        now: DateTime<Utc>,
+        worker_id: WorkerId,
+    ) -> Result<Option<(PendingJob, RunningJob)>>;
+
+    /// Retrieves the earliest `scheduled_at` of all pending jobs.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<DateTime<Utc>>>` - Returns the earliest scheduled time,
+    ///   None if there are no pending jobs, or an error if the retrieval operation failed.
+    async fn next_scheduled_at(&self) -> Result<Option<DateTime<Utc>>>;
+
+    /// Lists pending jobs matching a filter, ordered by `scheduled_at`.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter` - The filter and page of the listing, `impl_name` is matched
+    ///   against the job of a pending job.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<PendingJob>>` - Returns the page of matching pending jobs,
+    ///   or an error if the retrieval operation failed.
+    async fn list(&self, filter: &Filter) -> Result<Vec<PendingJob>>;
 }
 
 /// Repository interface for managing `RunningJob` entities.
@@ -165,4 +242,43 @@ pub trait RunningJobRepo: Send + Sync + 'static {
     /// * `Result<RunningJob>` - Returns the deleted running job on success,
     ///   or an error if the deletion operation failed or the job was not found.
     async fn delete(&self, job_id: &JobId) -> Result<RunningJob>;
+
+    /// Retrieves all running jobs.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<RunningJob>>` - Returns the running jobs in no particular order,
+    ///   or an error if the retrieval operation failed.
+    async fn get_all(&self) -> Result<Vec<RunningJob>>;
+
+    /// Renews the leases of all running jobs owned by a worker.
+    ///
+    /// # Parameters
+    ///
+    /// * `worker_id` - The worker owning the running jobs.
+    /// * `now` - The new heartbeat timestamp.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Returns success if the leases were renewed,
+    ///   or an error if the operation failed.
+    async fn heartbeat(&self, worker_id: &WorkerId, now: DateTime<Utc>) -> Result<()>;
+
+    /// Atomically deletes a running job whose lease has expired and returns it.
+    ///
+    /// # Parameters
+    ///
+    /// * `job_id` - The job_id of the running job to delete.
+    /// * `heartbeat_before` - The running job is deleted only if its last heartbeat
+    ///   is earlier than this timestamp.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<RunningJob>>` - Returns the deleted running job, None if it wasn't found
+    ///   or its lease hasn't expired, or an error if the deletion operation failed.
+    async fn delete_expired(
+        &self,
+        job_id: &JobId,
+        heartbeat_before: DateTime<Utc>,
+    ) -> Result<Option<RunningJob>>;
 }
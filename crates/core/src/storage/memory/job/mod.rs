@@ -4,15 +4,53 @@ pub mod running;
 use std::sync::{Arc, RwLock};
 
 use async_trait::async_trait;
+use tokio::sync::RwLock as AsyncRwLock;
 
 use crate::{
-    domain::job::{Job, id::JobId, policy::Policies},
-    storage::{error::Error, job::JobRepo},
+    domain::job::{
+        Job,
+        id::JobId,
+        r#impl::{JobImplName, SerializedJobImpl},
+        pending::PendingJob,
+        policy::Policies,
+        running::RunningJob,
+    },
+    storage::{
+        error::Error,
+        job::JobRepo,
+        query::{Filter, JobState},
+    },
 };
 
 #[derive(Default)]
 pub struct MemoryJobRepo {
-    elements: Arc<RwLock<Vec<Job>>>,
+    pub(crate) elements: Arc<RwLock<Vec<Job>>>,
+    pub(crate) pending_jobs: Arc<AsyncRwLock<Vec<PendingJob>>>,
+    pub(crate) running_jobs: Arc<AsyncRwLock<Vec<RunningJob>>>,
+}
+
+impl MemoryJobRepo {
+    async fn state(&self, job_id: JobId) -> JobState {
+        if self
+            .running_jobs
+            .read()
+            .await
+            .iter()
+            .any(|job| job.job_id() == job_id)
+        {
+            JobState::Running
+        } else if self
+            .pending_jobs
+            .read()
+            .await
+            .iter()
+            .any(|job| job.job_id() == job_id)
+        {
+            JobState::Pending
+        } else {
+            JobState::Finished
+        }
+    }
 }
 
 #[async_trait]
@@ -70,4 +108,58 @@ impl JobRepo for MemoryJobRepo {
 
         Ok(())
     }
+
+    async fn update_impl(
+        &self,
+        job_id: &JobId,
+        r#impl: SerializedJobImpl,
+    ) -> crate::storage::error::Result<()> {
+        let mut elements = self.elements.write().unwrap();
+        let job = elements
+            .iter_mut()
+            .find(|job| job.id() == *job_id)
+            .ok_or(Error::NotFound)?;
+        job.update_impl(r#impl);
+
+        Ok(())
+    }
+
+    async fn get_by_impl_name(
+        &self,
+        name: &JobImplName,
+    ) -> crate::storage::error::Result<Vec<Job>> {
+        let jobs = self
+            .elements
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|job| job.r#impl().name() == name)
+            .cloned()
+            .collect();
+        Ok(jobs)
+    }
+
+    async fn list(&self, filter: &Filter) -> crate::storage::error::Result<Vec<Job>> {
+        let mut jobs = self
+            .elements
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|job| filter.matches_job(job) && filter.contains(job.created_at()))
+            .cloned()
+            .collect::<Vec<_>>();
+        jobs.sort_by_key(|job| job.created_at());
+
+        if let Some(state) = filter.state() {
+            let mut matching = Vec::with_capacity(jobs.len());
+            for job in jobs {
+                if self.state(job.id()).await == state {
+                    matching.push(job);
+                }
+            }
+            jobs = matching;
+        }
+
+        Ok(filter.paginate(jobs))
+    }
 }
@@ -5,13 +5,19 @@ use chrono::{DateTime, Utc};
 use tokio::sync::RwLock;
 
 use crate::{
-    domain::job::pending::PendingJob,
-    storage::{error::Error, job::PendingJobRepo},
+    domain::{
+        job::{Job, pending::PendingJob, running::RunningJob},
+        run::id::RunId,
+        worker::id::WorkerId,
+    },
+    storage::{error::Error, job::PendingJobRepo, memory::matches_job_id, query::Filter},
 };
 
 #[derive(Default)]
 pub struct MemoryPendingJobRepo {
-    elements: Arc<RwLock<Vec<PendingJob>>>,
+    pub(crate) elements: Arc<RwLock<Vec<PendingJob>>>,
+    pub(crate) jobs: Arc<std::sync::RwLock<Vec<Job>>>,
+    pub(crate) running_jobs: Arc<RwLock<Vec<RunningJob>>>,
 }
 
 #[async_trait]
@@ -83,4 +89,58 @@ impl PendingJobRepo for MemoryPendingJobRepo {
             None => Ok(None),
         }
     }
+
+    async fn claim(
+        &self,
+        now: DateTime<Utc>,
+        worker_id: WorkerId,
+    ) -> crate::storage::error::Result<Option<(PendingJob, RunningJob)>> {
+        // both locks are held at once, so nobody observes the job neither pending nor running
+        let mut elements = self.elements.write().await;
+        let mut running_jobs = self.running_jobs.write().await;
+
+        let Some(index) = elements.iter().position(|job| job.scheduled_at() < now) else {
+            return Ok(None);
+        };
+        if running_jobs
+            .iter()
+            .any(|job| job.job_id() == elements[index].job_id())
+        {
+            return Err(Error::AlreadyExists);
+        }
+
+        let pending_job = elements.swap_remove(index);
+        let running_job =
+            RunningJob::new(pending_job.job_id(), RunId::default(), now, worker_id, now);
+        running_jobs.push(running_job.clone());
+        Ok(Some((pending_job, running_job)))
+    }
+
+    async fn next_scheduled_at(&self) -> crate::storage::error::Result<Option<DateTime<Utc>>> {
+        let next_scheduled_at = self
+            .elements
+            .read()
+            .await
+            .iter()
+            .map(|job| job.scheduled_at())
+            .min();
+        Ok(next_scheduled_at)
+    }
+
+    async fn list(&self, filter: &Filter) -> crate::storage::error::Result<Vec<PendingJob>> {
+        let mut jobs = self
+            .elements
+            .read()
+            .await
+            .iter()
+            .filter(|job| {
+                filter.contains(job.scheduled_at())
+                    && matches_job_id(&self.jobs, filter, job.job_id())
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        jobs.sort_by_key(|job| job.scheduled_at());
+
+        Ok(filter.paginate(jobs))
+    }
 }
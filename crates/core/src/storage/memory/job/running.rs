@@ -1,16 +1,20 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use tokio::sync::RwLock;
 
 use crate::{
-    domain::job::running::RunningJob,
+    domain::{
+        job::{id::JobId, running::RunningJob},
+        worker::id::WorkerId,
+    },
     storage::{error::Error, job::RunningJobRepo},
 };
 
 #[derive(Default)]
 pub struct MemoryRunningJobRepo {
-    elements: Arc<RwLock<Vec<RunningJob>>>,
+    pub(crate) elements: Arc<RwLock<Vec<RunningJob>>>,
 }
 
 #[async_trait]
@@ -60,4 +64,35 @@ impl RunningJobRepo for MemoryRunningJobRepo {
             None => Err(Error::NotFound),
         }
     }
+
+    async fn get_all(&self) -> crate::storage::error::Result<Vec<RunningJob>> {
+        Ok(self.elements.read().await.clone())
+    }
+
+    async fn heartbeat(
+        &self,
+        worker_id: &WorkerId,
+        now: DateTime<Utc>,
+    ) -> crate::storage::error::Result<()> {
+        self.elements
+            .write()
+            .await
+            .iter_mut()
+            .filter(|job| job.worker_id() == *worker_id)
+            .for_each(|job| job.update_heartbeat_at(now));
+        Ok(())
+    }
+
+    async fn delete_expired(
+        &self,
+        job_id: &JobId,
+        heartbeat_before: DateTime<Utc>,
+    ) -> crate::storage::error::Result<Option<RunningJob>> {
+        let mut elements = self.elements.write().await;
+        let expired_index = elements
+            .iter()
+            .position(|job| job.job_id() == *job_id && job.heartbeat_at() < heartbeat_before);
+
+        Ok(expired_index.map(|index| elements.swap_remove(index)))
+    }
 }
@@ -1,14 +1,20 @@
 use job::{pending::MemoryPendingJobRepo, running::MemoryRunningJobRepo, MemoryJobRepo};
 use run::{failed::MemoryFailedRunRepo, successful::MemorySuccessfulRunRepo};
+use transaction::MemoryTransactionRepo;
 
-use crate::services::Services;
+use std::sync::{Arc, RwLock};
 
-use super::Storage;
+use crate::{
+    domain::job::{Job, id::JobId},
+    services::Services,
+};
+
+use super::{Storage, query::Filter};
 
 pub mod job;
 pub mod run;
+pub mod transaction;
 
-#[derive(Default)]
 pub struct MemoryStorage {
     job_repo: MemoryJobRepo,
     pending_job_repo: MemoryPendingJobRepo,
@@ -17,14 +23,56 @@ pub struct MemoryStorage {
     failed_run_repo: MemoryFailedRunRepo,
 }
 
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        let jobs = Arc::<RwLock<Vec<Job>>>::default();
+        let running_job_repo = MemoryRunningJobRepo::default();
+        let pending_job_repo = MemoryPendingJobRepo {
+            jobs: jobs.clone(),
+            running_jobs: running_job_repo.elements.clone(),
+            ..Default::default()
+        };
+        let successful_run_repo = MemorySuccessfulRunRepo {
+            jobs: jobs.clone(),
+            ..Default::default()
+        };
+        let failed_run_repo = MemoryFailedRunRepo {
+            jobs: jobs.clone(),
+            ..Default::default()
+        };
+        let job_repo = MemoryJobRepo {
+            elements: jobs,
+            pending_jobs: pending_job_repo.elements.clone(),
+            running_jobs: running_job_repo.elements.clone(),
+        };
+
+        Self {
+            job_repo,
+            pending_job_repo,
+            running_job_repo,
+            successful_run_repo,
+            failed_run_repo,
+        }
+    }
+}
+
 impl From<MemoryStorage> for Storage {
     fn from(value: MemoryStorage) -> Self {
+        let transaction_repo = MemoryTransactionRepo::new(
+            value.job_repo.elements.clone(),
+            value.pending_job_repo.elements.clone(),
+            value.running_job_repo.elements.clone(),
+            value.successful_run_repo.elements.clone(),
+            value.failed_run_repo.elements.clone(),
+        );
+
         Storage::new(
             Box::new(value.job_repo),
             Box::new(value.pending_job_repo),
             Box::new(value.running_job_repo),
             Box::new(value.successful_run_repo),
             Box::new(value.failed_run_repo),
+            Box::new(transaction_repo),
         )
     }
 }
@@ -40,3 +88,326 @@ impl AddMemoryStorageService for Services {
         self.clone()
     }
 }
+
+/// Whether the job of a pending job or a run matches `job_id` and `impl_name` of a filter.
+fn matches_job_id(jobs: &RwLock<Vec<Job>>, filter: &Filter, job_id: JobId) -> bool {
+    if filter
+        .job_id()
+        .is_some_and(|filter_job_id| filter_job_id != job_id)
+    {
+        return false;
+    }
+    if filter.impl_name().is_none() {
+        return true;
+    }
+
+    jobs.read()
+        .unwrap()
+        .iter()
+        .find(|job| job.id() == job_id)
+        .is_some_and(|job| filter.matches_job(job))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::{
+        domain::{
+            job::{
+                error::JobError,
+                r#impl::{JobImplName, SerializedJobImpl},
+                pending::PendingJob,
+                policy::{Policies, PolicyData},
+                report::Report,
+                running::RunningJob,
+            },
+            run::{failed::FailedRun, id::RunId, successful::SuccessfulRun},
+            worker::id::WorkerId,
+        },
+        storage::{error::Error, query::JobState, transaction::Transaction},
+    };
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    fn job(name: &str, created_at: i64) -> Job {
+        Job::new(
+            JobId::default(),
+            at(created_at),
+            SerializedJobImpl::new(JobImplName::new(name), serde_json::Value::Null),
+            Policies::new(Vec::new(), PolicyData::default()),
+        )
+    }
+
+    fn ids(jobs: &[Job]) -> Vec<JobId> {
+        jobs.iter().map(|job| job.id()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs() {
+        let storage = Storage::from(MemoryStorage::default());
+        let pending = job("test", 3);
+        let running = job("test", 2);
+        let finished = job("test", 1);
+        let other = job("other", 4);
+        for job in [&pending, &running, &finished, &other] {
+            storage.job_repo().add(job.clone()).await.unwrap();
+        }
+        storage
+            .pending_job_repo()
+            .add(PendingJob::new(pending.id(), at(5)))
+            .await
+            .unwrap();
+        storage
+            .running_job_repo()
+            .add(RunningJob::new(
+                running.id(),
+                RunId::default(),
+                at(5),
+                WorkerId::default(),
+                at(5),
+            ))
+            .await
+            .unwrap();
+        let repo = storage.job_repo();
+
+        let all = repo.list(&Filter::default()).await.unwrap();
+        assert_eq!(
+            ids(&all),
+            vec![finished.id(), running.id(), pending.id(), other.id()]
+        );
+
+        let by_impl = Filter::default().with_impl_name(JobImplName::new("test"));
+        let page = repo
+            .list(&by_impl.clone().with_offset(1).with_limit(1))
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec![running.id()]);
+
+        for (state, expected) in [
+            (JobState::Pending, pending.id()),
+            (JobState::Running, running.id()),
+            (JobState::Finished, finished.id()),
+        ] {
+            let jobs = repo.list(&by_impl.clone().with_state(state)).await.unwrap();
+            assert_eq!(ids(&jobs), vec![expected]);
+        }
+
+        let in_range = repo
+            .list(&Filter::default().with_from(at(2)).with_to(at(4)))
+            .await
+            .unwrap();
+        assert_eq!(ids(&in_range), vec![running.id(), pending.id()]);
+
+        let by_id = repo
+            .list(&Filter::default().with_job_id(other.id()))
+            .await
+            .unwrap();
+        assert_eq!(ids(&by_id), vec![other.id()]);
+    }
+
+    #[tokio::test]
+    async fn test_runs_of() {
+        let storage = Storage::from(MemoryStorage::default());
+        let job_id = JobId::default();
+        let first = FailedRun::new(RunId::default(), job_id, at(0), at(1), JobError::Orphaned)
+            .with_started_at(at(0));
+        let second = FailedRun::new(RunId::default(), job_id, at(0), at(2), JobError::Orphaned);
+        let third = SuccessfulRun::new(RunId::default(), job_id, at(0), at(3), Report::new())
+            .with_started_at(at(2));
+        storage
+            .successful_run_repo()
+            .add(third.clone())
+            .await
+            .unwrap();
+        storage.failed_run_repo().add(second.clone()).await.unwrap();
+        storage.failed_run_repo().add(first.clone()).await.unwrap();
+
+        let runs = storage.runs_of(&job_id).await.unwrap();
+
+        let run_ids = runs.iter().map(|run| run.run_id()).collect::<Vec<_>>();
+        assert_eq!(
+            run_ids,
+            vec![first.run_id(), second.run_id(), third.run_id()]
+        );
+        let started_at = runs.iter().map(|run| run.started_at()).collect::<Vec<_>>();
+        assert_eq!(started_at, vec![Some(at(0)), None, Some(at(2))]);
+        assert!(runs[0].error().is_some());
+        assert!(runs[2].error().is_none());
+        assert!(storage.runs_of(&JobId::default()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_claim() {
+        let storage = Storage::from(MemoryStorage::default());
+        let job = job("test", 1);
+        storage
+            .pending_job_repo()
+            .add(PendingJob::new(job.id(), at(5)))
+            .await
+            .unwrap();
+        let worker_id = WorkerId::default();
+
+        let before = storage
+            .pending_job_repo()
+            .claim(at(5), worker_id)
+            .await
+            .unwrap();
+        let (claimed, running_job) = storage
+            .pending_job_repo()
+            .claim(at(6), worker_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(before.is_none());
+        assert_eq!(claimed.job_id(), job.id());
+        assert_eq!(running_job.worker_id(), worker_id);
+        assert_eq!(running_job.started_at(), at(6));
+        assert!(
+            storage
+                .pending_job_repo()
+                .get(&job.id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let running = storage.running_job_repo().get(&job.id()).await.unwrap();
+        assert_eq!(running.unwrap().run_id(), running_job.run_id());
+    }
+
+    #[tokio::test]
+    async fn test_update_impl() {
+        let storage = Storage::from(MemoryStorage::default());
+        let job = job("test", 1);
+        storage.job_repo().add(job.clone()).await.unwrap();
+        let r#impl = SerializedJobImpl::new(JobImplName::new("other"), serde_json::Value::Null);
+
+        storage
+            .job_repo()
+            .update_impl(&job.id(), r#impl.clone())
+            .await
+            .unwrap();
+        let result = storage
+            .job_repo()
+            .update_impl(&JobId::default(), r#impl)
+            .await;
+
+        let updated = storage.job_repo().get(&job.id()).await.unwrap().unwrap();
+        assert_eq!(updated.r#impl().name(), &JobImplName::new("other"));
+        assert!(matches!(result, Err(Error::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_list_failed_runs() {
+        let storage = Storage::from(MemoryStorage::default());
+        let tested = job("test", 1);
+        let other = job("other", 1);
+        storage.job_repo().add(tested.clone()).await.unwrap();
+        storage.job_repo().add(other.clone()).await.unwrap();
+        let mut run_ids = Vec::new();
+        for (job_id, finished_at) in [(tested.id(), 1), (other.id(), 2), (tested.id(), 3)] {
+            let run_id = RunId::default();
+            storage
+                .failed_run_repo()
+                .add(FailedRun::new(
+                    run_id,
+                    job_id,
+                    at(0),
+                    at(finished_at),
+                    JobError::Orphaned,
+                ))
+                .await
+                .unwrap();
+            run_ids.push(run_id);
+        }
+
+        let runs = storage
+            .failed_run_repo()
+            .list(&Filter::default().with_impl_name(JobImplName::new("test")))
+            .await
+            .unwrap()
+            .iter()
+            .map(|run| run.run_id())
+            .collect::<Vec<_>>();
+        assert_eq!(runs, vec![run_ids[2], run_ids[0]]);
+
+        let runs = storage
+            .failed_run_repo()
+            .list(&Filter::default().with_from(at(2)).with_limit(1))
+            .await
+            .unwrap()
+            .iter()
+            .map(|run| run.run_id())
+            .collect::<Vec<_>>();
+        assert_eq!(runs, vec![run_ids[2]]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_finished_job() {
+        let storage = Storage::from(MemoryStorage::default());
+        let finished = job("test", 1);
+        let recently_finished = job("test", 1);
+        let pending = job("test", 1);
+        let never_run = job("test", 1);
+        for job in [&finished, &recently_finished, &pending, &never_run] {
+            storage.job_repo().add(job.clone()).await.unwrap();
+        }
+        storage
+            .pending_job_repo()
+            .add(PendingJob::new(pending.id(), at(20)))
+            .await
+            .unwrap();
+        for job in [&finished, &pending] {
+            storage
+                .successful_run_repo()
+                .add(SuccessfulRun::new(
+                    RunId::default(),
+                    job.id(),
+                    at(1),
+                    at(5),
+                    Report::new(),
+                ))
+                .await
+                .unwrap();
+        }
+        storage
+            .failed_run_repo()
+            .add(FailedRun::new(
+                RunId::default(),
+                recently_finished.id(),
+                at(1),
+                at(10),
+                JobError::Orphaned,
+            ))
+            .await
+            .unwrap();
+
+        for job in [&recently_finished, &pending, &never_run] {
+            let result = storage
+                .commit(Transaction::default().delete_finished_job(job.id(), at(10)))
+                .await;
+            assert!(matches!(result, Err(Error::NotFound)));
+        }
+        storage
+            .commit(Transaction::default().delete_finished_job(finished.id(), at(10)))
+            .await
+            .unwrap();
+
+        let jobs = storage
+            .job_repo()
+            .list(&Filter::default())
+            .await
+            .unwrap()
+            .iter()
+            .map(|job| job.id())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            jobs,
+            vec![recently_finished.id(), pending.id(), never_run.id()]
+        );
+    }
+}
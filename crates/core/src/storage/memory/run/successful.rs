@@ -1,14 +1,19 @@
 use crate::{
-    domain::run::successful::SuccessfulRun,
-    storage::{error::Error, run::SuccessfulRunRepo},
+    domain::{
+        job::{Job, id::JobId},
+        run::successful::SuccessfulRun,
+    },
+    storage::{error::Error, memory::matches_job_id, query::Filter, run::SuccessfulRunRepo},
 };
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 #[derive(Default)]
 pub struct MemorySuccessfulRunRepo {
-    elements: Arc<RwLock<Vec<SuccessfulRun>>>,
+    pub(crate) elements: Arc<RwLock<Vec<SuccessfulRun>>>,
+    pub(crate) jobs: Arc<std::sync::RwLock<Vec<Job>>>,
 }
 
 #[async_trait]
@@ -36,4 +41,49 @@ impl SuccessfulRunRepo for MemorySuccessfulRunRepo {
         self.elements.write().await.push(run);
         Ok(())
     }
+
+    async fn list(&self, filter: &Filter) -> crate::storage::error::Result<Vec<SuccessfulRun>> {
+        let mut runs = self
+            .elements
+            .read()
+            .await
+            .iter()
+            .filter(|run| {
+                filter.contains(run.finished_at())
+                    && matches_job_id(&self.jobs, filter, run.job_id())
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|run| std::cmp::Reverse(run.finished_at()));
+
+        Ok(filter.paginate(runs))
+    }
+
+    async fn get_by_job_id(
+        &self,
+        job_id: &JobId,
+    ) -> crate::storage::error::Result<Vec<SuccessfulRun>> {
+        let mut runs = self
+            .elements
+            .read()
+            .await
+            .iter()
+            .filter(|run| run.job_id() == *job_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|run| run.finished_at());
+
+        Ok(runs)
+    }
+
+    async fn delete_finished_before(
+        &self,
+        finished_before: DateTime<Utc>,
+    ) -> crate::storage::error::Result<usize> {
+        let mut elements = self.elements.write().await;
+        let len = elements.len();
+        elements.retain(|run| run.finished_at() >= finished_before);
+
+        Ok(len - elements.len())
+    }
 }
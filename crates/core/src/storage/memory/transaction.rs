@@ -0,0 +1,344 @@
+use std::sync::{Arc, RwLock as StdRwLock};
+
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        job::{Job, pending::PendingJob, policy::Policies, running::RunningJob},
+        run::{failed::FailedRun, successful::SuccessfulRun},
+    },
+    storage::{
+        error::{Error, Result},
+        transaction::{Operation, Transaction, TransactionRepo},
+    },
+};
+
+/// Applies transactions while holding the locks of all memory repos at once,
+/// undoing already applied operations if a later one fails.
+pub struct MemoryTransactionRepo {
+    jobs: Arc<StdRwLock<Vec<Job>>>,
+    pending_jobs: Arc<RwLock<Vec<PendingJob>>>,
+    running_jobs: Arc<RwLock<Vec<RunningJob>>>,
+    successful_runs: Arc<RwLock<Vec<SuccessfulRun>>>,
+    failed_runs: Arc<RwLock<Vec<FailedRun>>>,
+}
+
+impl MemoryTransactionRepo {
+    pub(crate) fn new(
+        jobs: Arc<StdRwLock<Vec<Job>>>,
+        pending_jobs: Arc<RwLock<Vec<PendingJob>>>,
+        running_jobs: Arc<RwLock<Vec<RunningJob>>>,
+        successful_runs: Arc<RwLock<Vec<SuccessfulRun>>>,
+        failed_runs: Arc<RwLock<Vec<FailedRun>>>,
+    ) -> Self {
+        Self {
+            jobs,
+            pending_jobs,
+            running_jobs,
+            successful_runs,
+            failed_runs,
+        }
+    }
+}
+
+/// Reverts a single applied operation.
+enum Undo {
+    AddJob,
+    DeleteFinishedJob(usize, Job),
+    UpdatePolicies(usize, Policies),
+    AddPendingJob,
+    DeletePendingJob(PendingJob),
+    DeleteRunningJob(RunningJob),
+    AddSuccessfulRun,
+    AddFailedRun,
+}
+
+struct State<'a> {
+    jobs: &'a mut Vec<Job>,
+    pending_jobs: &'a mut Vec<PendingJob>,
+    running_jobs: &'a mut Vec<RunningJob>,
+    successful_runs: &'a mut Vec<SuccessfulRun>,
+    failed_runs: &'a mut Vec<FailedRun>,
+}
+
+impl State<'_> {
+    fn apply(&mut self, operation: Operation) -> Result<Undo> {
+        match operation {
+            Operation::AddJob(job) => {
+                if self.jobs.iter().any(|existing| existing.id() == job.id()) {
+                    return Err(Error::AlreadyExists);
+                }
+                self.jobs.push(job);
+                Ok(Undo::AddJob)
+            }
+            Operation::DeleteFinishedJob {
+                job_id,
+                finished_before,
+            } => {
+                let finished = !self.pending_jobs.iter().any(|job| job.job_id() == job_id)
+                    && !self.running_jobs.iter().any(|job| job.job_id() == job_id);
+                let successful_runs = self
+                    .successful_runs
+                    .iter()
+                    .filter(|run| run.job_id() == job_id)
+                    .map(|run| run.finished_at());
+                let failed_runs = self
+                    .failed_runs
+                    .iter()
+                    .filter(|run| run.job_id() == job_id)
+                    .map(|run| run.finished_at());
+                let last_finished_at = successful_runs.chain(failed_runs).max();
+                let index = self
+                    .jobs
+                    .iter()
+                    .position(|job| job.id() == job_id)
+                    .filter(|_| {
+                        finished
+                            && last_finished_at
+                                .is_some_and(|finished_at| finished_at < finished_before)
+                    })
+                    .ok_or(Error::NotFound)?;
+                // removed in place, so indices of earlier undos stay valid
+                let job = self.jobs.remove(index);
+                Ok(Undo::DeleteFinishedJob(index, job))
+            }
+            Operation::UpdatePolicies { job_id, policies } => {
+                let index = self
+                    .jobs
+                    .iter()
+                    .position(|job| job.id() == job_id)
+                    .ok_or(Error::NotFound)?;
+                let previous = self.jobs[index].policies().clone();
+                self.jobs[index].update_policies(policies);
+                Ok(Undo::UpdatePolicies(index, previous))
+            }
+            Operation::AddPendingJob(pending_job) => {
+                if self
+                    .pending_jobs
+                    .iter()
+                    .any(|existing| existing.job_id() == pending_job.job_id())
+                {
+                    return Err(Error::AlreadyExists);
+                }
+                self.pending_jobs.push(pending_job);
+                Ok(Undo::AddPendingJob)
+            }
+            Operation::DeletePendingJob(job_id) => {
+                let index = self
+                    .pending_jobs
+                    .iter()
+                    .position(|job| job.job_id() == job_id)
+                    .ok_or(Error::NotFound)?;
+                Ok(Undo::DeletePendingJob(self.pending_jobs.swap_remove(index)))
+            }
+            Operation::DeleteRunningJob { job_id, run_id } => {
+                let index = self
+                    .running_jobs
+                    .iter()
+                    .position(|job| job.job_id() == job_id && job.run_id() == run_id)
+                    .ok_or(Error::NotFound)?;
+                Ok(Undo::DeleteRunningJob(self.running_jobs.swap_remove(index)))
+            }
+            Operation::DeleteExpiredRunningJob {
+                job_id,
+                heartbeat_before,
+            } => {
+                let index = self
+                    .running_jobs
+                    .iter()
+                    .position(|job| job.job_id() == job_id && job.heartbeat_at() < heartbeat_before)
+                    .ok_or(Error::NotFound)?;
+                Ok(Undo::DeleteRunningJob(self.running_jobs.swap_remove(index)))
+            }
+            Operation::AddSuccessfulRun(run) => {
+                if self
+                    .successful_runs
+                    .iter()
+                    .any(|existing| existing.run_id() == run.run_id())
+                {
+                    return Err(Error::AlreadyExists);
+                }
+                self.successful_runs.push(run);
+                Ok(Undo::AddSuccessfulRun)
+            }
+            Operation::AddFailedRun(run) => {
+                if self
+                    .failed_runs
+                    .iter()
+                    .any(|existing| existing.run_id() == run.run_id())
+                {
+                    return Err(Error::AlreadyExists);
+                }
+                self.failed_runs.push(run);
+                Ok(Undo::AddFailedRun)
+            }
+        }
+    }
+
+    fn undo(&mut self, undo: Undo) {
+        match undo {
+            Undo::AddJob => {
+                self.jobs.pop();
+            }
+            Undo::DeleteFinishedJob(index, job) => self.jobs.insert(index, job),
+            Undo::UpdatePolicies(index, policies) => self.jobs[index].update_policies(policies),
+            Undo::AddPendingJob => {
+                self.pending_jobs.pop();
+            }
+            Undo::DeletePendingJob(pending_job) => self.pending_jobs.push(pending_job),
+            Undo::DeleteRunningJob(running_job) => self.running_jobs.push(running_job),
+            Undo::AddSuccessfulRun => {
+                self.successful_runs.pop();
+            }
+            Undo::AddFailedRun => {
+                self.failed_runs.pop();
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionRepo for MemoryTransactionRepo {
+    async fn commit(&self, transaction: Transaction) -> Result<()> {
+        let mut pending_jobs = self.pending_jobs.write().await;
+        let mut running_jobs = self.running_jobs.write().await;
+        let mut successful_runs = self.successful_runs.write().await;
+        let mut failed_runs = self.failed_runs.write().await;
+        // taken last, as it can't be held across an await
+        let mut jobs = self.jobs.write().unwrap();
+
+        let mut state = State {
+            jobs: &mut jobs,
+            pending_jobs: &mut pending_jobs,
+            running_jobs: &mut running_jobs,
+            successful_runs: &mut successful_runs,
+            failed_runs: &mut failed_runs,
+        };
+
+        let mut undos = Vec::new();
+        for operation in transaction.into_operations() {
+            match state.apply(operation) {
+                Ok(undo) => undos.push(undo),
+                Err(error) => {
+                    while let Some(undo) = undos.pop() {
+                        state.undo(undo);
+                    }
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        domain::{
+            job::{
+                id::JobId,
+                r#impl::{JobImplName, SerializedJobImpl},
+                policy::PolicyData,
+            },
+            run::id::RunId,
+        },
+        storage::{Storage, memory::MemoryStorage},
+    };
+
+    fn job() -> Job {
+        Job::new(
+            JobId::default(),
+            Utc::now(),
+            SerializedJobImpl::new(JobImplName::new("test"), serde_json::Value::Null),
+            Policies::new(Vec::new(), PolicyData::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_commit() {
+        let storage = Storage::from(MemoryStorage::default());
+        let job = job();
+
+        storage
+            .commit(
+                Transaction::default()
+                    .add_job(job.clone())
+                    .add_pending_job(PendingJob::new(job.id(), Utc::now())),
+            )
+            .await
+            .unwrap();
+
+        assert!(storage.job_repo().get(&job.id()).await.unwrap().is_some());
+        assert!(
+            storage
+                .pending_job_repo()
+                .get(&job.id())
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_rolls_back_on_error() {
+        let storage = Storage::from(MemoryStorage::default());
+        let job = job();
+
+        let result = storage
+            .commit(
+                Transaction::default()
+                    .add_job(job.clone())
+                    .add_pending_job(PendingJob::new(job.id(), Utc::now()))
+                    .delete_running_job(job.id(), RunId::default()),
+            )
+            .await;
+
+        assert!(matches!(result, Err(Error::NotFound)));
+        assert!(storage.job_repo().get(&job.id()).await.unwrap().is_none());
+        assert!(
+            storage
+                .pending_job_repo()
+                .get(&job.id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_delete_pending_job() {
+        let storage = Storage::from(MemoryStorage::default());
+        let job = job();
+        storage
+            .commit(
+                Transaction::default()
+                    .add_job(job.clone())
+                    .add_pending_job(PendingJob::new(job.id(), job.created_at())),
+            )
+            .await
+            .unwrap();
+
+        storage
+            .commit(Transaction::default().delete_pending_job(job.id()))
+            .await
+            .unwrap();
+        let result = storage
+            .commit(Transaction::default().delete_pending_job(job.id()))
+            .await;
+
+        assert!(matches!(result, Err(Error::NotFound)));
+        assert!(
+            storage
+                .pending_job_repo()
+                .get(&job.id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -1,12 +1,16 @@
 pub mod error;
 pub mod job;
 pub mod memory;
+pub mod query;
 pub mod run;
+pub mod transaction;
 
 use job::{JobRepo, PendingJobRepo, RunningJobRepo};
 use run::{FailedRunRepo, SuccessfulRunRepo};
 use std::sync::Arc;
+use transaction::{Transaction, TransactionRepo};
 
+use crate::domain::{job::id::JobId, run::Run};
 use crate::services::{verify::VerifyService, Services};
 
 #[derive(Clone)]
@@ -29,6 +33,7 @@ struct StorageInner {
     running_job_repo: Box<dyn RunningJobRepo>,
     successful_run_repo: Box<dyn SuccessfulRunRepo>,
     failed_run_repo: Box<dyn FailedRunRepo>,
+    transaction_repo: Box<dyn TransactionRepo>,
 }
 
 impl Storage {
@@ -38,6 +43,7 @@ impl Storage {
         running_job_repo: Box<dyn RunningJobRepo>,
         successful_run_repo: Box<dyn SuccessfulRunRepo>,
         failed_run_repo: Box<dyn FailedRunRepo>,
+        transaction_repo: Box<dyn TransactionRepo>,
     ) -> Self {
         Self {
             inner: Arc::new(StorageInner {
@@ -46,6 +52,7 @@ impl Storage {
                 running_job_repo,
                 successful_run_repo,
                 failed_run_repo,
+                transaction_repo,
             }),
         }
     }
@@ -69,6 +76,30 @@ impl Storage {
     pub fn failed_run_repo(&self) -> &dyn FailedRunRepo {
         self.inner.failed_run_repo.as_ref()
     }
+
+    pub fn transaction_repo(&self) -> &dyn TransactionRepo {
+        self.inner.transaction_repo.as_ref()
+    }
+
+    /// Applies all operations of a transaction atomically, see `TransactionRepo::commit`.
+    pub async fn commit(&self, transaction: Transaction) -> error::Result<()> {
+        self.transaction_repo().commit(transaction).await
+    }
+
+    /// Retrieves successful and failed runs of a job, ordered by the time they finished.
+    pub async fn runs_of(&self, job_id: &JobId) -> error::Result<Vec<Run>> {
+        let successful_runs = self.successful_run_repo().get_by_job_id(job_id).await?;
+        let failed_runs = self.failed_run_repo().get_by_job_id(job_id).await?;
+
+        let mut runs = successful_runs
+            .into_iter()
+            .map(Run::from)
+            .chain(failed_runs.into_iter().map(Run::from))
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|run| (run.finished_at(), *run.run_id().value()));
+
+        Ok(runs)
+    }
 }
 
 pub trait AddStorageService {
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::job::{Job, id::JobId, r#impl::JobImplName};
+
+/// Maximum number of entries returned by a listing unless `Filter::with_limit` is used.
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// State of a job, derived from the repositories it is present in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    /// The job has a `PendingJob`.
+    Pending,
+    /// The job has a `RunningJob`.
+    Running,
+    /// The job is neither pending nor running, e.g. its last run has finished.
+    Finished,
+}
+
+/// Filter and page used to list entities of the storage repositories.
+///
+/// The time range is inclusive of `from` and exclusive of `to`, and applies to
+/// `Job::created_at`, `PendingJob::scheduled_at` or the `finished_at` of a run,
+/// depending on the listed entity. `state` applies to jobs only.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    job_id: Option<JobId>,
+    impl_name: Option<JobImplName>,
+    state: Option<JobState>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    offset: usize,
+    limit: usize,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            job_id: None,
+            impl_name: None,
+            state: None,
+            from: None,
+            to: None,
+            offset: 0,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl Filter {
+    pub fn with_job_id(mut self, job_id: JobId) -> Self {
+        self.job_id = Some(job_id);
+        self
+    }
+
+    pub fn with_impl_name(mut self, impl_name: JobImplName) -> Self {
+        self.impl_name = Some(impl_name);
+        self
+    }
+
+    pub fn with_state(mut self, state: JobState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_from(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn with_to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn job_id(&self) -> Option<JobId> {
+        self.job_id
+    }
+
+    pub fn impl_name(&self) -> Option<&JobImplName> {
+        self.impl_name.as_ref()
+    }
+
+    pub fn state(&self) -> Option<JobState> {
+        self.state
+    }
+
+    pub fn from(&self) -> Option<DateTime<Utc>> {
+        self.from
+    }
+
+    pub fn to(&self) -> Option<DateTime<Utc>> {
+        self.to
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Whether `at` lies within the time range.
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| at >= from) && self.to.is_none_or(|to| at < to)
+    }
+
+    /// Whether the job matches `job_id` and `impl_name`.
+    pub fn matches_job(&self, job: &Job) -> bool {
+        self.job_id.is_none_or(|job_id| job.id() == job_id)
+            && self
+                .impl_name
+                .as_ref()
+                .is_none_or(|impl_name| job.r#impl().name() == impl_name)
+    }
+
+    /// Applies `offset` and `limit` to already filtered and ordered entities.
+    pub fn paginate<T>(&self, entities: impl IntoIterator<Item = T>) -> Vec<T> {
+        entities
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect()
+    }
+}
@@ -1,6 +1,10 @@
-use super::error::Result;
-use crate::domain::run::{failed::FailedRun, id::RunId, successful::SuccessfulRun};
+use super::{error::Result, query::Filter};
+use crate::domain::{
+    job::id::JobId,
+    run::{failed::FailedRun, id::RunId, successful::SuccessfulRun},
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 /// Repository interface for managing `SuccessfulRun` entities.
 ///
@@ -37,6 +41,39 @@ pub trait SuccessfulRunRepo: Send + Sync + 'static {
     /// Implementation may fail if a successful run with the same run_id already exists
     /// in storage.
     async fn add(&self, run: SuccessfulRun) -> Result<()>;
+
+    /// Lists successful runs matching a filter, most recently finished first.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter` - The filter and page of the listing, `impl_name` is matched
+    ///   against the job of a run.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<SuccessfulRun>>` - Returns the page of matching successful runs,
+    ///   or an error if the retrieval operation failed.
+    async fn list(&self, filter: &Filter) -> Result<Vec<SuccessfulRun>>;
+
+    /// Retrieves all successful runs of a job, ordered by `finished_at`.
+    ///
+    /// # Parameters
+    ///
+    /// * `job_id` - The job_id of the job the runs belong to.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<SuccessfulRun>>` - Returns the successful runs of the job,
+    ///   or an error if the retrieval operation failed.
+    async fn get_by_job_id(&self, job_id: &JobId) -> Result<Vec<SuccessfulRun>>;
+
+    /// Deletes successful runs that finished before a timestamp.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize>` - Returns the number of deleted runs,
+    ///   or an error if the deletion operation failed.
+    async fn delete_finished_before(&self, finished_before: DateTime<Utc>) -> Result<usize>;
 }
 
 /// Repository interface for managing `FailedRun` entities.
@@ -74,4 +111,37 @@ pub trait FailedRunRepo: Send + Sync + 'static {
     /// Implementation may fail if a failed run with the same run_id already exists
     /// in storage.
     async fn add(&self, run: FailedRun) -> Result<()>;
+
+    /// Lists failed runs matching a filter, most recently finished first.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter` - The filter and page of the listing, `impl_name` is matched
+    ///   against the job of a run.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<FailedRun>>` - Returns the page of matching failed runs,
+    ///   or an error if the retrieval operation failed.
+    async fn list(&self, filter: &Filter) -> Result<Vec<FailedRun>>;
+
+    /// Retrieves all failed runs of a job, ordered by `finished_at`.
+    ///
+    /// # Parameters
+    ///
+    /// * `job_id` - The job_id of the job the runs belong to.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<FailedRun>>` - Returns the failed runs of the job,
+    ///   or an error if the retrieval operation failed.
+    async fn get_by_job_id(&self, job_id: &JobId) -> Result<Vec<FailedRun>>;
+
+    /// Deletes failed runs that finished before a timestamp.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize>` - Returns the number of deleted runs,
+    ///   or an error if the deletion operation failed.
+    async fn delete_finished_before(&self, finished_before: DateTime<Utc>) -> Result<usize>;
 }
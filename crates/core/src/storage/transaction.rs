@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::domain::{
+    job::{Job, id::JobId, pending::PendingJob, policy::Policies},
+    run::{failed::FailedRun, id::RunId, successful::SuccessfulRun},
+};
+
+use super::error::Result;
+
+/// Change to the storage applied as part of a `Transaction`.
+#[derive(Clone)]
+pub enum Operation {
+    /// Adds a job, fails with `AlreadyExists` if a job with the same id exists.
+    AddJob(Job),
+    /// Deletes a job that is neither pending nor running, has at least one run and none
+    /// of whose runs finished at or after `finished_before`, fails with `NotFound` if
+    /// there is no such job.
+    DeleteFinishedJob {
+        job_id: JobId,
+        finished_before: DateTime<Utc>,
+    },
+    /// Replaces policies of a job, fails with `NotFound` if the job doesn't exist.
+    UpdatePolicies { job_id: JobId, policies: Policies },
+    /// Adds a pending job, fails with `AlreadyExists` if the job is already pending.
+    AddPendingJob(PendingJob),
+    /// Deletes a pending job, fails with `NotFound` if the job isn't pending.
+    DeletePendingJob(JobId),
+    /// Deletes the running job of a run, fails with `NotFound` if the job isn't running
+    /// or is running another run, e.g. after the lease on `run_id` has been reclaimed.
+    DeleteRunningJob { job_id: JobId, run_id: RunId },
+    /// Deletes a running job whose last heartbeat is earlier than `heartbeat_before`,
+    /// fails with `NotFound` if there is no such running job.
+    DeleteExpiredRunningJob {
+        job_id: JobId,
+        heartbeat_before: DateTime<Utc>,
+    },
+    /// Adds a successful run, fails with `AlreadyExists` if a run with the same id exists.
+    AddSuccessfulRun(SuccessfulRun),
+    /// Adds a failed run, fails with `AlreadyExists` if a run with the same id exists.
+    AddFailedRun(FailedRun),
+}
+
+/// Storage changes that are applied together, either all or none of them.
+#[derive(Clone, Default)]
+pub struct Transaction {
+    operations: Vec<Operation>,
+}
+
+impl Transaction {
+    pub fn add_job(self, job: Job) -> Self {
+        self.push(Operation::AddJob(job))
+    }
+
+    pub fn delete_finished_job(self, job_id: JobId, finished_before: DateTime<Utc>) -> Self {
+        self.push(Operation::DeleteFinishedJob {
+            job_id,
+            finished_before,
+        })
+    }
+
+    pub fn update_policies(self, job_id: JobId, policies: Policies) -> Self {
+        self.push(Operation::UpdatePolicies { job_id, policies })
+    }
+
+    pub fn add_pending_job(self, pending_job: PendingJob) -> Self {
+        self.push(Operation::AddPendingJob(pending_job))
+    }
+
+    pub fn delete_pending_job(self, job_id: JobId) -> Self {
+        self.push(Operation::DeletePendingJob(job_id))
+    }
+
+    pub fn delete_running_job(self, job_id: JobId, run_id: RunId) -> Self {
+        self.push(Operation::DeleteRunningJob { job_id, run_id })
+    }
+
+    pub fn delete_expired_running_job(
+        self,
+        job_id: JobId,
+        heartbeat_before: DateTime<Utc>,
+    ) -> Self {
+        self.push(Operation::DeleteExpiredRunningJob {
+            job_id,
+            heartbeat_before,
+        })
+    }
+
+    pub fn add_successful_run(self, run: SuccessfulRun) -> Self {
+        self.push(Operation::AddSuccessfulRun(run))
+    }
+
+    pub fn add_failed_run(self, run: FailedRun) -> Self {
+        self.push(Operation::AddFailedRun(run))
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    pub fn into_operations(self) -> Vec<Operation> {
+        self.operations
+    }
+
+    fn push(mut self, operation: Operation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+}
+
+/// Repository interface for applying `Transaction`s.
+///
+/// Implementations apply operations in order and atomically: if any operation fails,
+/// none of the changes of the transaction are visible, and the error of the failed
+/// operation is returned.
+#[async_trait]
+pub trait TransactionRepo: Send + Sync + 'static {
+    /// Applies all operations of a transaction, or none of them.
+    ///
+    /// # Parameters
+    ///
+    /// * `transaction` - The transaction to apply.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Returns success if all operations were applied,
+    ///   or the error of the first operation that failed.
+    async fn commit(&self, transaction: Transaction) -> Result<()>;
+}
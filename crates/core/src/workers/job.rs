@@ -1,19 +1,26 @@
 use chrono::{DateTime, Duration, Utc};
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
 use tokio::{
-    sync::{RwLock, mpsc, oneshot},
+    sync::{Mutex, OwnedSemaphorePermit, RwLock, Semaphore, mpsc, oneshot},
+    task::{self, JoinError, JoinSet},
     time,
 };
 
 use crate::{
-    domain::job::{
-        context::{Context, ContextData},
-        pending::PendingJob,
+    domain::{
+        job::{
+            context::{Context, ContextData},
+            id::JobId,
+            pending::PendingJob,
+            running::RunningJob,
+        },
+        worker::id::WorkerId,
     },
-    runners::job::JobRunner,
+    runners::{cancellation::RunCancellations, job::JobRunner, recovery::RecoveryRunner},
     services::{
         Services,
+        notify::PendingJobNotifier,
         time::{AnyClock, Clock},
         verify::{ServiceMissing, VerifyService},
     },
@@ -36,13 +43,15 @@ pub enum Error {
     StorageError(#[from] storage::error::Error),
     #[error("channel closed")]
     ChannelClosed,
+    #[error("semaphore closed")]
+    SemaphoreClosed,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub(crate) enum JobWorkerCommand {
-    Stop(oneshot::Sender<Result<()>>),
+    Stop(oneshot::Sender<Result<StopSummary>>),
 }
 
 #[derive(Clone)]
@@ -56,7 +65,7 @@ impl JobWorkerHandle {
         Self { tx, state }
     }
 
-    pub async fn stop(&self) -> Result<()> {
+    pub async fn stop(&self) -> Result<StopSummary> {
         let (tx, rx) = oneshot::channel();
         self.tx
             .send(JobWorkerCommand::Stop(tx))
@@ -71,10 +80,35 @@ impl JobWorkerHandle {
     }
 }
 
+/// Outcome of runs that were in flight when the worker was stopped.
+#[derive(Clone, Debug, Default)]
+pub struct StopSummary {
+    finished: Vec<JobId>,
+    abandoned: Vec<JobId>,
+}
+
+impl StopSummary {
+    /// Jobs whose runs finished before the shutdown timeout.
+    pub fn finished(&self) -> &[JobId] {
+        &self.finished
+    }
+
+    /// Jobs whose runs were still in flight after the shutdown timeout and got cancelled.
+    pub fn abandoned(&self) -> &[JobId] {
+        &self.abandoned
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct JobWorkerSettings {
+    worker_id: WorkerId,
     poll_rate: Duration,
     command_channel_size: usize,
+    max_concurrent_runs: Option<usize>,
+    shutdown_timeout: Duration,
+    shutdown_grace_period: Duration,
+    recovery_interval: Option<Duration>,
+    lease_duration: Duration,
 }
 
 impl VerifyService for JobWorkerSettings {
@@ -84,23 +118,133 @@ impl VerifyService for JobWorkerSettings {
 }
 
 impl JobWorkerSettings {
+    /// Creates settings of the worker.
+    ///
+    /// The worker sleeps until the earliest pending job is due and is woken up when jobs
+    /// are scheduled through `JobScheduler`. `poll_rate` caps the sleep, which bounds the
+    /// latency of jobs scheduled by other processes sharing the same storage. It has to be
+    /// at least 1 millisecond, so an idle worker doesn't spin.
     pub fn new(poll_rate: Duration, command_channel_size: usize) -> Result<Self> {
-        if poll_rate < Duration::zero() {
+        if poll_rate < Duration::milliseconds(1) {
             return Err(Error::InvalidSettings(
-                "poll_rate can't be negative".to_owned(),
+                "poll_rate has to be at least 1 millisecond".to_owned(),
             ));
         }
 
         Ok(Self {
+            worker_id: WorkerId::default(),
             poll_rate,
             command_channel_size,
+            max_concurrent_runs: None,
+            shutdown_timeout: Duration::seconds(30),
+            shutdown_grace_period: Duration::seconds(5),
+            recovery_interval: Some(Duration::minutes(1)),
+            lease_duration: Duration::seconds(30),
         })
     }
+
+    /// Sets the identity of the worker, recorded on every run it starts.
+    ///
+    /// Defaults to a random id.
+    pub fn with_worker_id(mut self, worker_id: WorkerId) -> Self {
+        self.worker_id = worker_id;
+        self
+    }
+
+    /// Sets how long a run stays leased to the worker without a heartbeat.
+    ///
+    /// The worker renews the leases of its runs every third of this duration. Runs whose
+    /// lease has expired are considered orphaned and may be reclaimed by any worker.
+    /// Has to be at least 3 milliseconds, so that the renewals are at least 1 millisecond
+    /// apart. Defaults to 30 seconds.
+    pub fn with_lease_duration(mut self, lease_duration: Duration) -> Result<Self> {
+        if lease_duration < Duration::milliseconds(3) {
+            return Err(Error::InvalidSettings(
+                "lease_duration has to be at least 3 milliseconds".to_owned(),
+            ));
+        }
+
+        self.lease_duration = lease_duration;
+        Ok(self)
+    }
+
+    pub fn worker_id(&self) -> WorkerId {
+        self.worker_id
+    }
+
+    pub fn lease_duration(&self) -> Duration {
+        self.lease_duration
+    }
+
+    pub fn poll_rate(&self) -> Duration {
+        self.poll_rate
+    }
+
+    /// Limits the number of jobs running at the same time.
+    ///
+    /// While the limit is reached, the worker doesn't pop further pending jobs,
+    /// so they stay in storage until a run finishes. By default there is no limit.
+    pub fn with_max_concurrent_runs(mut self, max_concurrent_runs: usize) -> Result<Self> {
+        if max_concurrent_runs == 0 {
+            return Err(Error::InvalidSettings(
+                "max_concurrent_runs has to be positive".to_owned(),
+            ));
+        }
+
+        self.max_concurrent_runs = Some(max_concurrent_runs);
+        Ok(self)
+    }
+
+    /// Sets how long stopping waits for in-flight runs before cancelling them.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Result<Self> {
+        if shutdown_timeout < Duration::zero() {
+            return Err(Error::InvalidSettings(
+                "shutdown_timeout can't be negative".to_owned(),
+            ));
+        }
+
+        self.shutdown_timeout = shutdown_timeout;
+        Ok(self)
+    }
+
+    /// Sets how long runs cancelled on stop may take to finish before they're aborted,
+    /// like `JobManager::cancel_with_grace_period`.
+    ///
+    /// Aborted runs are still recorded as failed with `JobError::JobCancelled`. If that
+    /// doesn't finish within another grace period either, the runs are dropped and left
+    /// to recovery. Defaults to 5 seconds.
+    pub fn with_shutdown_grace_period(mut self, shutdown_grace_period: Duration) -> Result<Self> {
+        if shutdown_grace_period < Duration::zero() {
+            return Err(Error::InvalidSettings(
+                "shutdown_grace_period can't be negative".to_owned(),
+            ));
+        }
+
+        self.shutdown_grace_period = shutdown_grace_period;
+        Ok(self)
+    }
+
+    /// Sets how often runs with an expired lease are recovered, see `RecoveryRunner`.
+    /// Recovery also runs when the worker starts.
+    ///
+    /// Defaults to 1 minute, `None` disables recovery.
+    pub fn with_recovery_interval(mut self, recovery_interval: Option<Duration>) -> Result<Self> {
+        if recovery_interval.is_some_and(|interval| interval <= Duration::zero()) {
+            return Err(Error::InvalidSettings(
+                "recovery_interval has to be positive".to_owned(),
+            ));
+        }
+
+        self.recovery_interval = recovery_interval;
+        Ok(self)
+    }
 }
 
 impl Default for JobWorkerSettings {
     fn default() -> Self {
-        Self::new(Duration::milliseconds(100), 32).unwrap()
+        Self::new(Duration::seconds(1), 32).unwrap()
     }
 }
 
@@ -112,11 +256,79 @@ pub enum State {
     Stopped,
 }
 
+/// Runs spawned by the worker that haven't been reaped yet.
+#[derive(Default)]
+struct InFlightRuns {
+    join_set: JoinSet<JobId>,
+    job_ids: HashMap<task::Id, JobId>,
+}
+
+impl InFlightRuns {
+    fn spawn(&mut self, job_id: JobId, run: impl Future<Output = ()> + Send + 'static) {
+        let handle = self.join_set.spawn(async move {
+            run.await;
+            job_id
+        });
+        self.job_ids.insert(handle.id(), job_id);
+    }
+
+    /// Removes runs that have already finished.
+    fn reap(&mut self) {
+        while let Some(result) = self.join_set.try_join_next_with_id() {
+            self.complete(result, &mut StopSummary::default());
+        }
+    }
+
+    fn complete(
+        &mut self,
+        result: std::result::Result<(task::Id, JobId), JoinError>,
+        summary: &mut StopSummary,
+    ) {
+        match result {
+            Ok((id, job_id)) => {
+                self.job_ids.remove(&id);
+                summary.finished.push(job_id);
+            }
+            Err(error) => {
+                let Some(job_id) = self.job_ids.remove(&error.id()) else {
+                    return;
+                };
+
+                if error.is_cancelled() {
+                    summary.abandoned.push(job_id);
+                } else {
+                    log::error!("run of job {job_id} panicked");
+                    summary.finished.push(job_id);
+                }
+            }
+        }
+    }
+
+    /// Removes a run that was cancelled on stop, however it ended.
+    ///
+    /// Returns the job of a run that was dropped or panicked before the runner finished it.
+    fn abandon(
+        &mut self,
+        result: std::result::Result<(task::Id, JobId), JoinError>,
+        summary: &mut StopSummary,
+    ) -> Option<JobId> {
+        let (job_id, unfinished) = match result {
+            Ok((id, _)) => (self.job_ids.remove(&id)?, false),
+            Err(error) => (self.job_ids.remove(&error.id())?, true),
+        };
+
+        summary.abandoned.push(job_id);
+        unfinished.then_some(job_id)
+    }
+}
+
 pub(crate) struct JobWorker<TData: ContextData> {
     settings: JobWorkerSettings,
     context: Context<TData>,
     job_runner: JobRunner<TData>,
     state: Arc<RwLock<State>>,
+    run_permits: Arc<Semaphore>,
+    runs: Mutex<InFlightRuns>,
 }
 
 impl<TData: ContextData> JobWorker<TData> {
@@ -125,11 +337,17 @@ impl<TData: ContextData> JobWorker<TData> {
         context: Context<TData>,
         job_runner: JobRunner<TData>,
     ) -> Self {
+        let run_permits = settings
+            .max_concurrent_runs
+            .unwrap_or(Semaphore::MAX_PERMITS);
+
         Self {
             settings,
             context,
             job_runner,
             state: Arc::new(RwLock::new(State::Stopped)),
+            run_permits: Arc::new(Semaphore::new(run_permits)),
+            runs: Mutex::new(InFlightRuns::default()),
         }
     }
 
@@ -175,42 +393,173 @@ impl<TData: ContextData> JobWorker<TData> {
         }
     }
 
-    async fn hadle_stop_command(&self) -> Result<()> {
+    async fn hadle_stop_command(&self) -> Result<StopSummary> {
         if self.read_state().await != State::Started {
             return Err(Error::AlreadyStopped);
         }
 
         self.write_state(State::Stopping).await;
 
-        Ok(())
+        Ok(self.drain_runs().await)
+    }
+
+    /// Waits for in-flight runs until the shutdown timeout, then cancels the remaining ones.
+    ///
+    /// Cancelled runs finish through the runner, so their outcome is recorded. Only runs
+    /// that don't finish even after being aborted are dropped.
+    async fn drain_runs(&self) -> StopSummary {
+        let mut runs = self.runs.lock().await;
+        let mut summary = StopSummary::default();
+        let shutdown_timeout = self.settings.shutdown_timeout.to_std().unwrap();
+
+        let drained = time::timeout(shutdown_timeout, async {
+            while let Some(result) = runs.join_set.join_next_with_id().await {
+                runs.complete(result, &mut summary);
+            }
+        })
+        .await;
+        if drained.is_ok() {
+            return summary;
+        }
+
+        log::warn!(
+            "shutdown timeout elapsed, cancelling {} runs",
+            runs.join_set.len()
+        );
+        let run_cancellations = self.context.get_required_service::<RunCancellations>();
+        let grace_period = self.settings.shutdown_grace_period;
+        run_cancellations.cancel_all(Some(grace_period));
+
+        // one grace period until the runs are aborted, another one for recording them
+        let cancelled = time::timeout((grace_period * 2).to_std().unwrap(), async {
+            while let Some(result) = runs.join_set.join_next_with_id().await {
+                if let Some(job_id) = runs.abandon(result, &mut summary) {
+                    run_cancellations.unregister(&job_id);
+                }
+            }
+        })
+        .await;
+
+        if cancelled.is_err() {
+            log::warn!("dropping {} runs that didn't finish", runs.join_set.len());
+            runs.join_set.abort_all();
+            while let Some(result) = runs.join_set.join_next_with_id().await {
+                if let Some(job_id) = runs.abandon(result, &mut summary) {
+                    run_cancellations.unregister(&job_id);
+                }
+            }
+        }
+
+        summary
     }
 
-    async fn get_next_pending_job(&self, now: DateTime<Utc>) -> Result<PendingJob> {
-        let mut interval = time::interval(self.settings.poll_rate.to_std().unwrap());
+    /// Waits for a free slot and claims the next scheduled job, moving it to running jobs.
+    async fn get_next_pending_job(&self) -> Result<(PendingJob, RunningJob, OwnedSemaphorePermit)> {
+        // wait for a free slot before popping, so jobs stay in storage while at capacity
+        let permit = self
+            .run_permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::SemaphoreClosed)?;
+
+        let storage = self.context.get_required_service::<Storage>();
+        let notifier = self.context.get_required_service::<PendingJobNotifier>();
         loop {
-            interval.tick().await;
+            let now = self.context.get_required_service::<AnyClock>().utc_now();
 
-            match self
-                .context
-                .get_required_service::<Storage>()
+            let claimed = storage
                 .pending_job_repo()
-                .pop_scheduled(now)
-                .await?
-            {
-                Some(pending_job) => return Ok(pending_job),
-                None => continue,
+                .claim(now, self.settings.worker_id())
+                .await?;
+            if let Some((pending_job, running_job)) = claimed {
+                return Ok((pending_job, running_job, permit));
+            }
+
+            let timeout = self.time_until_next_job(&storage, now).await?;
+            tokio::select! {
+                _ = notifier.notified() => {}
+                _ = time::sleep(timeout) => {}
             }
         }
     }
 
-    async fn handle_pending_job(&self, pending_job: PendingJob) {
+    /// Returns how long to sleep until the earliest pending job is due.
+    ///
+    /// Sleeping is capped by `poll_rate`, so jobs scheduled by other processes sharing
+    /// the storage are still picked up.
+    async fn time_until_next_job(
+        &self,
+        storage: &Storage,
+        now: DateTime<Utc>,
+    ) -> Result<std::time::Duration> {
+        let timeout = match storage.pending_job_repo().next_scheduled_at().await? {
+            // a due job that wasn't popped yet, e.g. because storage compares with lower precision
+            Some(next_scheduled_at) => {
+                (next_scheduled_at - now).clamp(Duration::milliseconds(1), self.settings.poll_rate)
+            }
+            None => self.settings.poll_rate,
+        };
+
+        Ok(timeout.to_std().unwrap_or_default())
+    }
+
+    async fn handle_pending_job(
+        &self,
+        pending_job: PendingJob,
+        running_job: RunningJob,
+        permit: OwnedSemaphorePermit,
+    ) {
         log::trace!("handling pending_job with id: {:?}", pending_job.job_id());
         let job_runner = self.job_runner.clone();
-        tokio::spawn(async move {
-            job_runner.run(pending_job).await;
+        let mut runs = self.runs.lock().await;
+        runs.reap();
+        runs.spawn(pending_job.job_id(), async move {
+            job_runner.run(pending_job, running_job).await;
+            drop(permit);
         });
     }
 
+    /// Spawns periodic renewal of the leases on runs of this worker.
+    fn start_heartbeat(&self) -> task::JoinHandle<()> {
+        let worker_id = self.settings.worker_id;
+        let heartbeat_interval = (self.settings.lease_duration / 3).to_std().unwrap();
+        let context = self.context.clone();
+
+        tokio::spawn(async move {
+            let mut interval = time::interval(heartbeat_interval);
+            interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let now = context.get_required_service::<AnyClock>().utc_now();
+                let result = context
+                    .get_required_service::<Storage>()
+                    .running_job_repo()
+                    .heartbeat(&worker_id, now)
+                    .await;
+
+                if let Err(error) = result {
+                    log::error!("failed to renew leases of worker {worker_id}: {error}");
+                }
+            }
+        })
+    }
+
+    /// Spawns periodic recovery of orphaned running jobs, starting right away.
+    fn start_recovery(&self) -> Option<task::JoinHandle<()>> {
+        let recovery_interval = self.settings.recovery_interval?.to_std().unwrap();
+        let recovery_runner = self.context.get_required_service::<RecoveryRunner<TData>>();
+
+        Some(tokio::spawn(async move {
+            let mut interval = time::interval(recovery_interval);
+            interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                recovery_runner.run().await;
+            }
+        }))
+    }
+
     async fn run(&self, mut rx: mpsc::Receiver<JobWorkerCommand>) -> Result<()> {
         if self.read_state().await != State::Stopped {
             return Err(Error::NotStopped);
@@ -218,10 +567,16 @@ impl<TData: ContextData> JobWorker<TData> {
 
         log::trace!("JobWorker starting");
         self.write_state(State::Starting).await;
+        let heartbeat = self.start_heartbeat();
+        let recovery = self.start_recovery();
 
         loop {
             log::debug!("run loop start");
             if self.read_state().await == State::Stopping {
+                heartbeat.abort();
+                if let Some(recovery) = &recovery {
+                    recovery.abort();
+                }
                 self.write_state(State::Stopped).await;
                 log::trace!("JobWorker stopped");
                 break;
@@ -230,8 +585,6 @@ impl<TData: ContextData> JobWorker<TData> {
             log::trace!("JobWorker started");
             self.write_state(State::Started).await;
 
-            let now = self.context.get_required_service::<AnyClock>().utc_now();
-
             tokio::select! {
                 command = self.get_next_command(&mut rx) => {
                     match command {
@@ -244,9 +597,11 @@ impl<TData: ContextData> JobWorker<TData> {
                         Err(error) => log::error!("error ocurred: {:?}", error),
                     }
                 }
-                pending_job = self.get_next_pending_job(now) => {
+                pending_job = self.get_next_pending_job() => {
                     match pending_job {
-                        Ok(pending_job) => self.handle_pending_job(pending_job).await,
+                        Ok((pending_job, running_job, permit)) => {
+                            self.handle_pending_job(pending_job, running_job, permit).await
+                        }
                         Err(error) => log::error!("error ocurred: {:?}", error),
                     }
                 }
@@ -256,3 +611,463 @@ impl<TData: ContextData> JobWorker<TData> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        domain::{
+            job::{
+                Job,
+                error::{CustomError, JobError, JobResult},
+                r#impl::{JobImpl, JobImplName},
+                recovery::RecoveryPolicy,
+                report::Report,
+                running::RunningJob,
+            },
+            run::{Run, id::RunId},
+        },
+        managers::job_manager::JobManager,
+        registries::{job_actions::JobActionsRegistryBuilder, policies::PolicyRegistryBuilder},
+        storage::memory::AddMemoryStorageService,
+    };
+
+    #[derive(Default)]
+    struct TestContextData {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+        finished: AtomicUsize,
+        run_ids: Mutex<Vec<RunId>>,
+    }
+
+    impl ContextData for TestContextData {}
+
+    #[derive(Serialize, Deserialize)]
+    struct SleepJobImpl {
+        millis: u64,
+    }
+
+    #[async_trait]
+    impl JobImpl<TestContextData> for SleepJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("sleep")
+        }
+
+        async fn run(&self, context: Context<TestContextData>) -> JobResult<Report> {
+            let data = context.data();
+            let running = data.running.fetch_add(1, Ordering::SeqCst) + 1;
+            data.max_running.fetch_max(running, Ordering::SeqCst);
+
+            time::sleep(std::time::Duration::from_millis(self.millis)).await;
+
+            data.running.fetch_sub(1, Ordering::SeqCst);
+            data.finished.fetch_add(1, Ordering::SeqCst);
+            Ok(Report::new())
+        }
+
+        async fn on_success(&self, _context: Context<TestContextData>) {}
+
+        async fn on_fail(&self, _context: Context<TestContextData>) {}
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct CancellableJobImpl {
+        ignore_cancellation: bool,
+    }
+
+    #[async_trait]
+    impl JobImpl<TestContextData> for CancellableJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("cancellable")
+        }
+
+        async fn run(&self, context: Context<TestContextData>) -> JobResult<Report> {
+            let data = context.data();
+            let run_id = context.run_info().unwrap().run_id();
+            data.run_ids.lock().unwrap().push(run_id);
+            data.running.fetch_add(1, Ordering::SeqCst);
+
+            match self.ignore_cancellation {
+                true => time::sleep(std::time::Duration::from_secs(60)).await,
+                false => context.cancelled().await,
+            }
+
+            Err(JobError::Custom(CustomError::new("interrupted")))
+        }
+
+        async fn on_success(&self, _context: Context<TestContextData>) {}
+
+        async fn on_fail(&self, _context: Context<TestContextData>) {}
+    }
+
+    fn test_manager(settings: JobWorkerSettings) -> JobManager<TestContextData> {
+        JobManager::new_default(TestContextData::default(), |builder| {
+            builder.add_service(settings);
+            let mut job_actions_registry = JobActionsRegistryBuilder::default();
+            job_actions_registry.register::<SleepJobImpl>();
+            job_actions_registry.register::<CancellableJobImpl>();
+            builder.add_service(job_actions_registry.build());
+            builder.add_service(PolicyRegistryBuilder::<TestContextData>::default().build());
+            builder.add_memory_storage();
+        })
+        .unwrap()
+    }
+
+    async fn schedule_sleep(manager: &JobManager<TestContextData>, millis: u64) -> JobId {
+        schedule_sleep_at(manager, millis, Utc::now() - Duration::seconds(1)).await
+    }
+
+    async fn schedule_sleep_at(
+        manager: &JobManager<TestContextData>,
+        millis: u64,
+        at: DateTime<Utc>,
+    ) -> JobId {
+        let job = Job::from_impl(SleepJobImpl { millis }, Utc::now(), Vec::new()).unwrap();
+        manager.schedule(job, at).await.unwrap()
+    }
+
+    async fn cancel_running(
+        manager: &JobManager<TestContextData>,
+        ignore_cancellation: bool,
+        grace_period: Option<Duration>,
+    ) -> JobError {
+        let job = Job::from_impl(
+            CancellableJobImpl {
+                ignore_cancellation,
+            },
+            Utc::now(),
+            Vec::new(),
+        )
+        .unwrap();
+        let job_id = manager
+            .schedule(job, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+
+        let data = manager.context().data();
+        wait_until(|| data.running.load(Ordering::SeqCst) == 1).await;
+
+        match grace_period {
+            Some(grace_period) => manager
+                .cancel_with_grace_period(&job_id, grace_period)
+                .await
+                .unwrap(),
+            None => manager.cancel(&job_id).await.unwrap(),
+        }
+
+        let run_id = data.run_ids.lock().unwrap()[0];
+        let storage = manager.context().get_required_service::<Storage>();
+        let mut failed_run = None;
+        time::timeout(std::time::Duration::from_secs(5), async {
+            while failed_run.is_none() {
+                failed_run = storage.failed_run_repo().get(&run_id).await.unwrap();
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        failed_run.unwrap().error().clone()
+    }
+
+    async fn wait_until(predicate: impl Fn() -> bool) {
+        time::timeout(std::time::Duration::from_secs(5), async {
+            while !predicate() {
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_runs() {
+        let manager = test_manager(
+            JobWorkerSettings::new(Duration::milliseconds(1), 32)
+                .unwrap()
+                .with_max_concurrent_runs(2)
+                .unwrap(),
+        );
+
+        for _ in 0..6 {
+            schedule_sleep(&manager, 50).await;
+        }
+
+        let data = manager.context().data();
+        wait_until(|| data.finished.load(Ordering::SeqCst) == 6).await;
+
+        assert_eq!(data.max_running.load(Ordering::SeqCst), 2);
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stop_drains_runs() {
+        let manager = test_manager(
+            JobWorkerSettings::new(Duration::milliseconds(1), 32)
+                .unwrap()
+                .with_shutdown_timeout(Duration::milliseconds(200))
+                .unwrap()
+                .with_shutdown_grace_period(Duration::milliseconds(50))
+                .unwrap(),
+        );
+
+        let short = schedule_sleep(&manager, 50).await;
+        // ignores cancellation, so it gets aborted after the grace period
+        let long = schedule_sleep(&manager, 10_000).await;
+
+        let data = manager.context().data();
+        let storage = manager.context().get_required_service::<Storage>();
+        let run_cancellations = manager.context().get_required_service::<RunCancellations>();
+        wait_until(|| data.running.load(Ordering::SeqCst) == 2).await;
+
+        let summary = manager.stop().await.unwrap();
+
+        assert_eq!(summary.finished(), &[short]);
+        assert_eq!(summary.abandoned(), &[long]);
+        assert_eq!(data.finished.load(Ordering::SeqCst), 1);
+
+        assert!(
+            storage
+                .running_job_repo()
+                .get_all()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let runs = storage.runs_of(&long).await.unwrap();
+        assert!(
+            matches!(runs[..], [Run::Failed(ref run)] if matches!(run.error(), JobError::JobCancelled))
+        );
+        assert!(!run_cancellations.is_running(&long));
+    }
+
+    #[tokio::test]
+    async fn test_stop_cancels_runs() {
+        let manager = test_manager(
+            JobWorkerSettings::new(Duration::milliseconds(1), 32)
+                .unwrap()
+                .with_shutdown_timeout(Duration::zero())
+                .unwrap(),
+        );
+        let job = Job::from_impl(
+            CancellableJobImpl {
+                ignore_cancellation: false,
+            },
+            Utc::now(),
+            Vec::new(),
+        )
+        .unwrap();
+        let job_id = manager
+            .schedule(job, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+
+        let data = manager.context().data();
+        let storage = manager.context().get_required_service::<Storage>();
+        wait_until(|| data.running.load(Ordering::SeqCst) == 1).await;
+        // observes the cancellation well within the default grace period
+        let summary = time::timeout(std::time::Duration::from_secs(1), manager.stop())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(summary.abandoned(), &[job_id]);
+        let runs = storage.runs_of(&job_id).await.unwrap();
+        assert!(matches!(runs[0].error(), Some(JobError::JobCancelled)));
+    }
+
+    #[test]
+    fn test_invalid_poll_rate() {
+        for poll_rate in [Duration::zero(), Duration::microseconds(500)] {
+            let result = JobWorkerSettings::new(poll_rate, 32);
+
+            assert!(matches!(result, Err(Error::InvalidSettings(_))));
+        }
+    }
+
+    #[test]
+    fn test_invalid_max_concurrent_runs() {
+        let result = JobWorkerSettings::default().with_max_concurrent_runs(0);
+
+        assert!(matches!(result, Err(Error::InvalidSettings(_))));
+    }
+
+    #[tokio::test]
+    async fn test_schedule_wakes_worker() {
+        // long enough for the test to time out if the worker relied on polling
+        let manager = test_manager(JobWorkerSettings::new(Duration::seconds(60), 32).unwrap());
+        let data = manager.context().data();
+
+        // let the worker go to sleep with no pending jobs
+        time::sleep(std::time::Duration::from_millis(20)).await;
+        schedule_sleep(&manager, 0).await;
+        wait_until(|| data.finished.load(Ordering::SeqCst) == 1).await;
+
+        schedule_sleep_at(&manager, 0, Utc::now() + Duration::milliseconds(100)).await;
+        wait_until(|| data.finished.load(Ordering::SeqCst) == 2).await;
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_job() {
+        let manager = test_manager(JobWorkerSettings::new(Duration::milliseconds(1), 32).unwrap());
+
+        let error = cancel_running(&manager, false, None).await;
+
+        assert!(matches!(error, JobError::JobCancelled));
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_job_with_grace_period() {
+        let manager = test_manager(JobWorkerSettings::new(Duration::milliseconds(1), 32).unwrap());
+
+        let error = cancel_running(&manager, true, Some(Duration::milliseconds(50))).await;
+
+        assert!(matches!(error, JobError::JobCancelled));
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_unknown_job() {
+        let manager = test_manager(JobWorkerSettings::default());
+
+        let result = manager.cancel(&JobId::default()).await;
+
+        assert!(result.is_err());
+        manager.stop().await.unwrap();
+    }
+
+    async fn add_orphan(
+        manager: &JobManager<TestContextData>,
+        recovery_policy: RecoveryPolicy,
+    ) -> RunId {
+        let job = Job::from_impl(SleepJobImpl { millis: 0 }, Utc::now(), Vec::new())
+            .unwrap()
+            .with_recovery_policy(recovery_policy);
+        // left behind by a worker that stopped renewing its lease
+        let abandoned_at = Utc::now() - Duration::minutes(5);
+        let running_job = RunningJob::new(
+            job.id(),
+            RunId::default(),
+            abandoned_at,
+            WorkerId::default(),
+            abandoned_at,
+        );
+        let run_id = running_job.run_id();
+
+        let storage = manager.context().get_required_service::<Storage>();
+        storage.job_repo().add(job).await.unwrap();
+        storage.running_job_repo().add(running_job).await.unwrap();
+        run_id
+    }
+
+    fn recovery_settings() -> JobWorkerSettings {
+        JobWorkerSettings::new(Duration::milliseconds(1), 32)
+            .unwrap()
+            .with_recovery_interval(Some(Duration::milliseconds(10)))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_recover_orphan_as_failed() {
+        let manager = test_manager(recovery_settings());
+        let run_id = add_orphan(&manager, RecoveryPolicy::Fail).await;
+
+        let storage = manager.context().get_required_service::<Storage>();
+        let mut failed_run = None;
+        time::timeout(std::time::Duration::from_secs(5), async {
+            while failed_run.is_none() {
+                failed_run = storage.failed_run_repo().get(&run_id).await.unwrap();
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert!(matches!(failed_run.unwrap().error(), JobError::Orphaned));
+        assert!(
+            storage
+                .running_job_repo()
+                .get_all()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(manager.context().data().finished.load(Ordering::SeqCst), 0);
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recover_orphan_by_requeue() {
+        let manager = test_manager(recovery_settings());
+        add_orphan(&manager, RecoveryPolicy::Requeue).await;
+
+        let data = manager.context().data();
+        wait_until(|| data.finished.load(Ordering::SeqCst) == 1).await;
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_renews_lease() {
+        let manager = test_manager(
+            recovery_settings()
+                .with_lease_duration(Duration::milliseconds(30))
+                .unwrap(),
+        );
+        let job_id = schedule_sleep(&manager, 200).await;
+
+        let data = manager.context().data();
+        let storage = manager.context().get_required_service::<Storage>();
+        wait_until(|| data.running.load(Ordering::SeqCst) == 1).await;
+        let running_job = storage
+            .running_job_repo()
+            .get(&job_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(running_job.worker_id(), manager.worker_id());
+
+        time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let renewed = storage.running_job_repo().get(&job_id).await.unwrap();
+                if renewed.unwrap().heartbeat_at() > running_job.heartbeat_at() {
+                    break;
+                }
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        manager.stop().await.unwrap();
+    }
+
+    #[test]
+    fn test_invalid_lease_duration() {
+        for lease_duration in [Duration::zero(), Duration::nanoseconds(2)] {
+            let result = JobWorkerSettings::default().with_lease_duration(lease_duration);
+
+            assert!(matches!(result, Err(Error::InvalidSettings(_))));
+        }
+    }
+
+    #[test]
+    fn test_invalid_recovery_interval() {
+        let result = JobWorkerSettings::default().with_recovery_interval(Some(Duration::zero()));
+
+        assert!(matches!(result, Err(Error::InvalidSettings(_))));
+    }
+}
@@ -0,0 +1,81 @@
+use jobfire_core::{
+    async_trait,
+    domain::job::{
+        context::{Context, EmptyContextData},
+        error::JobResult,
+        r#impl::{JobImpl, JobImplName},
+        report::Report,
+    },
+    managers::job_manager::JobManager,
+    registries::{job_actions::JobActionsRegistryBuilder, policies::PolicyRegistryBuilder},
+    storage::memory::AddMemoryStorageService,
+};
+use jobfire_recurring::{
+    AddRecurringExtension, RegisterRecurringJob, ScheduleRecurringJob, misfire::MisfirePolicy,
+    recurrence::Recurrence,
+};
+use serde::{Deserialize, Serialize};
+use simple_logger::SimpleLogger;
+use tokio::signal::ctrl_c;
+
+#[derive(Serialize, Deserialize)]
+struct HeartbeatJobImpl {
+    message: String,
+}
+
+#[async_trait]
+impl JobImpl<EmptyContextData> for HeartbeatJobImpl {
+    fn name() -> JobImplName {
+        JobImplName::new("heartbeat")
+    }
+
+    async fn run(&self, _context: Context<EmptyContextData>) -> JobResult<Report> {
+        log::info!("{}", self.message);
+        Ok(Report::new())
+    }
+
+    async fn on_success(&self, _context: Context<EmptyContextData>) {}
+
+    async fn on_fail(&self, _context: Context<EmptyContextData>) {}
+}
+
+#[tokio::main]
+async fn main() {
+    SimpleLogger::new()
+        .with_level(log::LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let manager = JobManager::new_default(EmptyContextData, |builder| {
+        let mut job_actions_registry = JobActionsRegistryBuilder::<EmptyContextData>::default();
+        job_actions_registry.register::<HeartbeatJobImpl>();
+        job_actions_registry.register_recurring_job();
+        builder.add_service(job_actions_registry.build());
+        builder.add_service(PolicyRegistryBuilder::<EmptyContextData>::default().build());
+
+        builder.add_recurring_extension();
+        builder.add_memory_storage();
+    })
+    .unwrap();
+
+    manager
+        .define_recurring_job(
+            "heartbeat",
+            HeartbeatJobImpl {
+                message: "hello every 5 seconds".to_owned(),
+            },
+            Recurrence::cron("*/5 * * * * *").unwrap(),
+            MisfirePolicy::Skip,
+            Vec::new(),
+        )
+        .await
+        .unwrap();
+    manager
+        .reconcile_recurring_jobs(&["heartbeat"])
+        .await
+        .unwrap();
+
+    ctrl_c().await.unwrap();
+
+    manager.stop().await.unwrap();
+}
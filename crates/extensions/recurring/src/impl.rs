@@ -0,0 +1,205 @@
+use chrono::{DateTime, Utc};
+use jobfire_core::{
+    async_trait,
+    domain::job::{
+        context::{Context, ContextData},
+        error::{JobError, JobResult},
+        r#impl::{JobImpl, JobImplName, SerializedJobImpl},
+        report::Report,
+    },
+    registries::job_actions::JobActionsRegistry,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    misfire::MisfirePolicy, recurrence::Recurrence, recurring_scheduler::RecurringScheduler,
+};
+
+/// Custom metric set on the report of a fire that was skipped, because the job was paused
+/// or the fire was missed and the misfire policy skips it.
+pub const SKIPPED_METRIC: &str = "skipped";
+
+/// Wraps a user job implementation and schedules its next occurrence after
+/// every run, regardless of whether the run succeeded or failed.
+///
+/// The recurrence is stored together with the wrapped implementation,
+/// so it is persisted by whatever storage keeps the `Job`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RecurringJobImpl {
+    recurrence: Recurrence,
+    #[serde(default)]
+    misfire_policy: MisfirePolicy,
+    /// Stable key of a recurring job defined with `ScheduleRecurringJob::define_recurring_job`.
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    pause: Option<Pause>,
+    inner: SerializedJobImpl,
+}
+
+/// State of a paused recurring job.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Pause {
+    paused_at: DateTime<Utc>,
+    pending_at: Option<DateTime<Utc>>,
+}
+
+impl Pause {
+    pub fn new(paused_at: DateTime<Utc>, pending_at: Option<DateTime<Utc>>) -> Self {
+        Self {
+            paused_at,
+            pending_at,
+        }
+    }
+
+    pub fn paused_at(&self) -> DateTime<Utc> {
+        self.paused_at
+    }
+
+    /// Time of the occurrence that was pending when the job was paused, if any.
+    pub fn pending_at(&self) -> Option<DateTime<Utc>> {
+        self.pending_at
+    }
+}
+
+impl RecurringJobImpl {
+    pub fn new(
+        recurrence: Recurrence,
+        misfire_policy: MisfirePolicy,
+        inner: SerializedJobImpl,
+    ) -> Self {
+        Self {
+            recurrence,
+            misfire_policy,
+            key: None,
+            pause: None,
+            inner,
+        }
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn recurrence(&self) -> &Recurrence {
+        &self.recurrence
+    }
+
+    pub fn misfire_policy(&self) -> MisfirePolicy {
+        self.misfire_policy
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    pub fn pause(&self) -> Option<&Pause> {
+        self.pause.as_ref()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pause.is_some()
+    }
+
+    pub fn update_pause(&mut self, pause: Option<Pause>) {
+        self.pause = pause;
+    }
+
+    pub fn inner(&self) -> &SerializedJobImpl {
+        &self.inner
+    }
+
+    fn is_skipped<TData: ContextData>(&self, context: &Context<TData>) -> bool {
+        self.is_paused()
+            || context.run_info().is_some_and(|run_info| {
+                !self.misfire_policy.should_run(
+                    &self.recurrence,
+                    run_info.scheduled_at(),
+                    run_info.started_at(),
+                )
+            })
+    }
+
+    async fn schedule_next<TData: ContextData>(&self, context: &Context<TData>) {
+        let run_info = match context.run_info() {
+            Some(run_info) => run_info,
+            None => {
+                log::error!("recurring job callback invoked without run info");
+                return;
+            }
+        };
+
+        let result = context
+            .get_required_service::<RecurringScheduler>()
+            .schedule_next::<TData>(&run_info.job_id(), run_info.scheduled_at())
+            .await;
+
+        if let Err(error) = result {
+            log::error!(
+                "failed to schedule next occurrence of job {}: {error}",
+                run_info.job_id()
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl<TData: ContextData> JobImpl<TData> for RecurringJobImpl {
+    fn name() -> JobImplName {
+        JobImplName::new("recurring-job")
+    }
+
+    async fn run(&self, context: Context<TData>) -> JobResult<Report> {
+        // reported as a successful run, so skipped fires don't show up as failures
+        if self.is_skipped(&context) {
+            log::info!("skipping missed fire of paused or overdue recurring job");
+            return Ok(Report::new().with_metric(SKIPPED_METRIC, true));
+        }
+
+        let run_fn = context
+            .get_required_service::<JobActionsRegistry<TData>>()
+            .get_run_fn(self.inner.name());
+
+        match run_fn {
+            Some(run_fn) => run_fn(self.inner.clone(), context).await,
+            None => Err(JobError::JobImplBuildFailed),
+        }
+    }
+
+    async fn on_success(&self, context: Context<TData>) {
+        if self.is_skipped(&context) {
+            self.schedule_next(&context).await;
+            return;
+        }
+
+        let on_success_fn = context
+            .get_required_service::<JobActionsRegistry<TData>>()
+            .get_on_success_fn(self.inner.name());
+
+        match on_success_fn {
+            Some(on_success_fn) => on_success_fn(self.inner.clone(), context.clone()).await,
+            None => log::error!("failed to find on_success_fn of recurring job"),
+        }
+
+        self.schedule_next(&context).await;
+    }
+
+    async fn on_fail(&self, context: Context<TData>) {
+        let on_fail_fn = context
+            .get_required_service::<JobActionsRegistry<TData>>()
+            .get_on_fail_fn(self.inner.name());
+
+        match on_fail_fn {
+            Some(on_fail_fn) => on_fail_fn(self.inner.clone(), context.clone()).await,
+            None => log::error!("failed to find on_fail_fn of recurring job"),
+        }
+
+        self.schedule_next(&context).await;
+    }
+
+    // a paused recurring job has no pending job, but has to keep its definition
+    fn retain_when_finished() -> bool {
+        true
+    }
+}
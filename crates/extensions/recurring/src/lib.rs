@@ -1,14 +1,897 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+use chrono::{DateTime, Utc};
+use r#impl::RecurringJobImpl;
+use jobfire_core::{
+    async_trait,
+    domain::job::{
+        Job,
+        context::ContextData,
+        id::JobId,
+        r#impl::{JobImpl, SerializedJobImpl},
+        policy::Policy,
+    },
+    managers::{self, job_manager::JobManager},
+    registries::job_actions::JobActionsRegistryBuilder,
+    services::{
+        Services,
+        time::{AnyClock, Clock},
+    },
+};
+use misfire::MisfirePolicy;
+use recurrence::Recurrence;
+use recurring_scheduler::RecurringScheduler;
+
+pub mod r#impl;
+pub mod misfire;
+pub mod recurrence;
+pub mod recurring_scheduler;
+
+pub trait AddRecurringExtension {
+    fn add_recurring_extension(&self) -> Self;
+}
+
+impl AddRecurringExtension for Services {
+    fn add_recurring_extension(&self) -> Self {
+        self.add_service(RecurringScheduler::new(self.clone()))
+            .clone()
+    }
+}
+
+pub trait RegisterRecurringJob {
+    fn register_recurring_job(&mut self);
+}
+
+impl<TData: ContextData> RegisterRecurringJob for JobActionsRegistryBuilder<TData> {
+    fn register_recurring_job(&mut self) {
+        self.register::<RecurringJobImpl>();
+    }
+}
+
+#[async_trait]
+pub trait ScheduleRecurringJob<TData: ContextData> {
+    /// Schedules `job_impl` to run on every fire time of `recurrence`, handling missed fires
+    /// according to `misfire_policy`.
+    ///
+    /// Both `job_impl` and `RecurringJobImpl` have to be registered in `JobActionsRegistry`.
+    async fn schedule_recurring_job<TJobImpl: JobImpl<TData>>(
+        &self,
+        job_impl: TJobImpl,
+        recurrence: Recurrence,
+        misfire_policy: MisfirePolicy,
+        policies: Vec<Box<dyn Policy<TData>>>,
+    ) -> managers::job_manager::Result<JobId>;
+
+    /// Creates or updates a recurring job identified by a stable `key`.
+    ///
+    /// The id of the job is derived from `key`, so calling this on every startup
+    /// keeps a single stored definition up to date instead of creating duplicates.
+    async fn define_recurring_job<TJobImpl: JobImpl<TData>>(
+        &self,
+        key: &str,
+        job_impl: TJobImpl,
+        recurrence: Recurrence,
+        misfire_policy: MisfirePolicy,
+        policies: Vec<Box<dyn Policy<TData>>>,
+    ) -> managers::job_manager::Result<JobId>;
+
+    /// Removes the recurring job defined with `key`.
+    ///
+    /// Returns `false` if no such job was stored.
+    async fn remove_recurring_job(&self, key: &str) -> managers::job_manager::Result<bool>;
+
+    /// Removes all recurring jobs defined with a key other than one of `keys`,
+    /// e.g. after defining every recurring job declared in code.
+    ///
+    /// Returns ids of the removed jobs.
+    async fn reconcile_recurring_jobs(
+        &self,
+        keys: &[&str],
+    ) -> managers::job_manager::Result<Vec<JobId>>;
+
+    /// Pauses a recurring job until it is resumed, also across restarts.
+    async fn pause_recurring_job(&self, job_id: &JobId) -> managers::job_manager::Result<()>;
+
+    /// Resumes a paused recurring job, optionally skipping the fires missed while paused.
+    ///
+    /// Returns the time of the next scheduled occurrence, if any.
+    async fn resume_recurring_job(
+        &self,
+        job_id: &JobId,
+        skip_missed: bool,
+    ) -> managers::job_manager::Result<Option<DateTime<Utc>>>;
+
+    /// Schedules an extra run of a recurring job to run now, without shifting its occurrences.
+    ///
+    /// Returns the id of the job of the extra run.
+    async fn trigger_recurring_job(&self, job_id: &JobId) -> managers::job_manager::Result<JobId>;
+}
+
+fn get_recurring_scheduler<TData: ContextData>(
+    job_manager: &JobManager<TData>,
+) -> managers::job_manager::Result<RecurringScheduler> {
+    job_manager
+        .context()
+        .get_service::<RecurringScheduler>()
+        .ok_or(managers::job_manager::Error::ServiceMissing(
+            "RecurringScheduler".to_owned(),
+        ))
+}
+
+#[async_trait]
+impl<TData: ContextData> ScheduleRecurringJob<TData> for JobManager<TData> {
+    async fn schedule_recurring_job<TJobImpl: JobImpl<TData>>(
+        &self,
+        job_impl: TJobImpl,
+        recurrence: Recurrence,
+        misfire_policy: MisfirePolicy,
+        policies: Vec<Box<dyn Policy<TData>>>,
+    ) -> managers::job_manager::Result<JobId> {
+        let recurring_scheduler = get_recurring_scheduler(self)?;
+
+        let first_fire = recurring_scheduler.first_fire(&recurrence).ok_or(
+            managers::job_manager::Error::InternalError(
+                "recurrence has no upcoming fire time".to_owned(),
+            ),
+        )?;
+
+        let inner = SerializedJobImpl::from_job_impl(job_impl)
+            .map_err(|_| managers::job_manager::Error::JobBuildFailed)?;
+        let now = self.context().get_required_service::<AnyClock>().utc_now();
+
+        self.schedule(
+            Job::from_impl::<TData>(
+                RecurringJobImpl::new(recurrence, misfire_policy, inner),
+                now,
+                policies,
+            )
+            .map_err(|_| managers::job_manager::Error::JobBuildFailed)?,
+            first_fire,
+        )
+        .await
+    }
+
+    async fn define_recurring_job<TJobImpl: JobImpl<TData>>(
+        &self,
+        key: &str,
+        job_impl: TJobImpl,
+        recurrence: Recurrence,
+        misfire_policy: MisfirePolicy,
+        policies: Vec<Box<dyn Policy<TData>>>,
+    ) -> managers::job_manager::Result<JobId> {
+        let recurring_scheduler = get_recurring_scheduler(self)?;
+
+        let inner = SerializedJobImpl::from_job_impl(job_impl)
+            .map_err(|_| managers::job_manager::Error::JobBuildFailed)?;
+        let recurring_job_impl =
+            RecurringJobImpl::new(recurrence, misfire_policy, inner).with_key(key);
+        let now = self.context().get_required_service::<AnyClock>().utc_now();
+        let job = Job::from_impl_with_id::<TData>(
+            JobId::from_key(key),
+            recurring_job_impl.clone(),
+            now,
+            policies,
+        )
+        .map_err(|_| managers::job_manager::Error::JobBuildFailed)?;
+        let job_id = job.id();
+
+        recurring_scheduler
+            .upsert::<TData>(job, &recurring_job_impl)
+            .await?;
+
+        Ok(job_id)
+    }
+
+    async fn remove_recurring_job(&self, key: &str) -> managers::job_manager::Result<bool> {
+        let recurring_scheduler = get_recurring_scheduler(self)?;
+
+        Ok(recurring_scheduler.remove(&JobId::from_key(key)).await?)
+    }
+
+    async fn reconcile_recurring_jobs(
+        &self,
+        keys: &[&str],
+    ) -> managers::job_manager::Result<Vec<JobId>> {
+        let recurring_scheduler = get_recurring_scheduler(self)?;
+
+        Ok(recurring_scheduler.retain_keys::<TData>(keys).await?)
+    }
+
+    async fn pause_recurring_job(&self, job_id: &JobId) -> managers::job_manager::Result<()> {
+        let recurring_scheduler = get_recurring_scheduler(self)?;
+
+        Ok(recurring_scheduler.pause::<TData>(job_id).await?)
+    }
+
+    async fn resume_recurring_job(
+        &self,
+        job_id: &JobId,
+        skip_missed: bool,
+    ) -> managers::job_manager::Result<Option<DateTime<Utc>>> {
+        let recurring_scheduler = get_recurring_scheduler(self)?;
+
+        Ok(recurring_scheduler
+            .resume::<TData>(job_id, skip_missed)
+            .await?)
+    }
+
+    async fn trigger_recurring_job(&self, job_id: &JobId) -> managers::job_manager::Result<JobId> {
+        let recurring_scheduler = get_recurring_scheduler(self)?;
+
+        Ok(recurring_scheduler.trigger::<TData>(job_id).await?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::Duration;
+    use jobfire_core::{
+        domain::{
+            job::{
+                context::{Context, EmptyContextData, RunInfo},
+                error::{CustomError, JobError, JobResult},
+                r#impl::JobImplName,
+                report::Report,
+            },
+            run::{id::RunId, successful::SuccessfulRun},
+        },
+        managers::job_scheduler::JobScheduler,
+        policies::backoff_retry::{Backoff, BackoffRetryPolicy},
+        registries::policies::PolicyRegistryBuilder,
+        runners::retention::{RetentionRunner, RetentionSettings},
+        services::time::FixedClock,
+        storage::{Storage, memory::AddMemoryStorageService},
+    };
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
     use super::*;
+    use crate::r#impl::{Pause, SKIPPED_METRIC};
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJobImpl;
+
+    #[async_trait]
+    impl JobImpl<EmptyContextData> for TestJobImpl {
+        fn name() -> JobImplName {
+            JobImplName::new("test")
+        }
+
+        async fn run(&self, _context: Context<EmptyContextData>) -> JobResult<Report> {
+            Err(JobError::Custom(CustomError::new("test")))
+        }
+
+        async fn on_success(&self, _context: Context<EmptyContextData>) {}
+
+        async fn on_fail(&self, _context: Context<EmptyContextData>) {}
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2025-01-01T10:00:00Z".parse().unwrap()
+    }
+
+    fn build_services(builder: &Services) {
+        build_services_at(builder, now());
+    }
+
+    fn build_services_at(builder: &Services, now: DateTime<Utc>) {
+        builder.add_service(AnyClock::new(FixedClock(now)));
+        builder.add_memory_storage();
+        builder.add_recurring_extension();
+
+        let mut job_actions_registry = JobActionsRegistryBuilder::<EmptyContextData>::default();
+        job_actions_registry.register::<TestJobImpl>();
+        job_actions_registry.register_recurring_job();
+        builder.add_service(job_actions_registry.build());
+        builder.add_service(PolicyRegistryBuilder::<EmptyContextData>::default().build());
+    }
+
+    async fn add_recurring_job(
+        context: &Context<EmptyContextData>,
+        misfire_policy: MisfirePolicy,
+    ) -> (Job, RecurringJobImpl) {
+        let recurring_job_impl = RecurringJobImpl::new(
+            Recurrence::cron("0 0 * * * *").unwrap(),
+            misfire_policy,
+            SerializedJobImpl::from_job_impl(TestJobImpl).unwrap(),
+        );
+        let job = add_job(context, recurring_job_impl.clone()).await;
+
+        (job, recurring_job_impl)
+    }
+
+    async fn add_job(
+        context: &Context<EmptyContextData>,
+        recurring_job_impl: RecurringJobImpl,
+    ) -> Job {
+        let job =
+            Job::from_impl::<EmptyContextData>(recurring_job_impl, now(), Vec::new()).unwrap();
+
+        context
+            .get_required_service::<Storage>()
+            .job_repo()
+            .add(job.clone())
+            .await
+            .unwrap();
+
+        job
+    }
+
+    fn paused_job_impl() -> RecurringJobImpl {
+        let mut recurring_job_impl = RecurringJobImpl::new(
+            Recurrence::cron("0 0 * * * *").unwrap(),
+            MisfirePolicy::default(),
+            SerializedJobImpl::from_job_impl(TestJobImpl).unwrap(),
+        );
+        recurring_job_impl.update_pause(Some(Pause::new(
+            now() - Duration::hours(3),
+            Some(now() - Duration::hours(2)),
+        )));
+        recurring_job_impl
+    }
+
+    fn test_context() -> Context<EmptyContextData> {
+        let services = Services::default();
+        let context = Context::new(EmptyContextData, services.clone());
+        build_services(&services);
+        services.add_service(JobScheduler::new(services.clone()));
+        services.verify().unwrap();
+        context
+    }
+
+    #[tokio::test]
+    async fn test_schedule_recurring_job() {
+        let manager = JobManager::new_default(EmptyContextData, build_services).unwrap();
+
+        let job_id = manager
+            .schedule_recurring_job(
+                TestJobImpl,
+                Recurrence::cron("0 0 * * * *").unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        let pending_job = manager
+            .context()
+            .get_required_service::<Storage>()
+            .pending_job_repo()
+            .get(&job_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pending_job.scheduled_at(),
+            "2025-01-01T11:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_schedule_recurring_job_across_dst() {
+        // clocks in Warsaw go forward from 02:00 to 03:00 on 2025-03-30
+        let now = "2025-03-29T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let manager =
+            JobManager::new_default(EmptyContextData, |builder| build_services_at(builder, now))
+                .unwrap();
+
+        let job_id = manager
+            .schedule_recurring_job(
+                TestJobImpl,
+                Recurrence::cron_in("0 0 9 * * *", chrono_tz::Europe::Warsaw).unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        let pending_job = manager
+            .context()
+            .get_required_service::<Storage>()
+            .pending_job_repo()
+            .get(&job_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pending_job.scheduled_at(),
+            "2025-03-30T07:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_on_success_schedules_next() {
+        let context = test_context();
+        let (job, recurring_job_impl) = add_recurring_job(&context, MisfirePolicy::default()).await;
+        let run_context =
+            context.with_run_info(RunInfo::new(job.id(), RunId::default(), now(), now()));
+
+        JobImpl::<EmptyContextData>::on_success(&recurring_job_impl, run_context).await;
+
+        let pending_job = context
+            .get_required_service::<Storage>()
+            .pending_job_repo()
+            .get(&job.id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pending_job.scheduled_at(),
+            "2025-01-01T11:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_fail_schedules_next() {
+        let context = test_context();
+        let (job, recurring_job_impl) = add_recurring_job(&context, MisfirePolicy::default()).await;
+        let run_context =
+            context.with_run_info(RunInfo::new(job.id(), RunId::default(), now(), now()));
+
+        JobImpl::<EmptyContextData>::on_fail(&recurring_job_impl, run_context).await;
+
+        let pending_job = context
+            .get_required_service::<Storage>()
+            .pending_job_repo()
+            .get(&job.id())
+            .await
+            .unwrap();
+        assert!(pending_job.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_skip_missed_fire() {
+        let context = test_context();
+        let (job, recurring_job_impl) = add_recurring_job(&context, MisfirePolicy::Skip).await;
+        let run_context = context.with_run_info(RunInfo::new(
+            job.id(),
+            RunId::default(),
+            now() - Duration::hours(3),
+            now(),
+        ));
+
+        let report = JobImpl::<EmptyContextData>::run(&recurring_job_impl, run_context.clone())
+            .await
+            .unwrap();
+        assert_eq!(report.metrics().get(SKIPPED_METRIC), Some(&json!(true)));
+
+        JobImpl::<EmptyContextData>::on_success(&recurring_job_impl, run_context).await;
+
+        let pending_job = context
+            .get_required_service::<Storage>()
+            .pending_job_repo()
+            .get(&job.id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pending_job.scheduled_at(),
+            "2025-01-01T11:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fire_all_schedules_missed_fires() {
+        let context = test_context();
+        let (job, recurring_job_impl) = add_recurring_job(&context, MisfirePolicy::FireAll).await;
+        let run_context = context.with_run_info(RunInfo::new(
+            job.id(),
+            RunId::default(),
+            now() - Duration::hours(3),
+            now(),
+        ));
+
+        JobImpl::<EmptyContextData>::on_success(&recurring_job_impl, run_context).await;
+
+        let pending_job = context
+            .get_required_service::<Storage>()
+            .pending_job_repo()
+            .get(&job.id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pending_job.scheduled_at(),
+            "2025-01-01T08:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    async fn pending_at(
+        manager: &JobManager<EmptyContextData>,
+        job_id: &JobId,
+    ) -> Option<DateTime<Utc>> {
+        manager
+            .context()
+            .get_required_service::<Storage>()
+            .pending_job_repo()
+            .get(job_id)
+            .await
+            .unwrap()
+            .map(|pending_job| pending_job.scheduled_at())
+    }
+
+    #[tokio::test]
+    async fn test_define_recurring_job_is_idempotent() {
+        let manager = JobManager::new_default(EmptyContextData, build_services).unwrap();
+
+        let first = manager
+            .define_recurring_job(
+                "hourly",
+                TestJobImpl,
+                Recurrence::cron("0 0 * * * *").unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+        let second = manager
+            .define_recurring_job(
+                "hourly",
+                TestJobImpl,
+                Recurrence::cron("0 0 * * * *").unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first, JobId::from_key("hourly"));
+        assert_eq!(
+            pending_at(&manager, &first).await,
+            Some("2025-01-01T11:00:00Z".parse().unwrap())
+        );
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_define_recurring_job_updates_recurrence() {
+        let manager = JobManager::new_default(EmptyContextData, build_services).unwrap();
+
+        manager
+            .define_recurring_job(
+                "report",
+                TestJobImpl,
+                Recurrence::cron("0 0 * * * *").unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+        let job_id = manager
+            .define_recurring_job(
+                "report",
+                TestJobImpl,
+                Recurrence::cron("0 30 2 * * *").unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        let job = manager
+            .context()
+            .get_required_service::<Storage>()
+            .job_repo()
+            .get(&job_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let recurring_job_impl = job
+            .r#impl()
+            .deserialize::<EmptyContextData, RecurringJobImpl>()
+            .unwrap();
+        assert_eq!(
+            recurring_job_impl.recurrence(),
+            &Recurrence::cron("0 30 2 * * *").unwrap()
+        );
+        assert_eq!(
+            pending_at(&manager, &job_id).await,
+            Some("2025-01-02T02:30:00Z".parse().unwrap())
+        );
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_remove_recurring_job() {
+        let manager = JobManager::new_default(EmptyContextData, build_services).unwrap();
+
+        let job_id = manager
+            .define_recurring_job(
+                "hourly",
+                TestJobImpl,
+                Recurrence::cron("0 0 * * * *").unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        assert!(manager.remove_recurring_job("hourly").await.unwrap());
+        assert!(!manager.remove_recurring_job("hourly").await.unwrap());
+        assert_eq!(pending_at(&manager, &job_id).await, None);
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_recurring_jobs() {
+        let manager = JobManager::new_default(EmptyContextData, build_services).unwrap();
+
+        for key in ["kept", "stale"] {
+            manager
+                .define_recurring_job(
+                    key,
+                    TestJobImpl,
+                    Recurrence::cron("0 0 * * * *").unwrap(),
+                    MisfirePolicy::default(),
+                    Vec::new(),
+                )
+                .await
+                .unwrap();
+        }
+        let unkeyed = manager
+            .schedule_recurring_job(
+                TestJobImpl,
+                Recurrence::cron("0 0 * * * *").unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        let removed = manager.reconcile_recurring_jobs(&["kept"]).await.unwrap();
+
+        assert_eq!(removed, vec![JobId::from_key("stale")]);
+        assert!(
+            pending_at(&manager, &JobId::from_key("kept"))
+                .await
+                .is_some()
+        );
+        assert!(pending_at(&manager, &unkeyed).await.is_some());
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pause_recurring_job() {
+        let manager = JobManager::new_default(EmptyContextData, build_services).unwrap();
+        let define = async || {
+            manager
+                .define_recurring_job(
+                    "hourly",
+                    TestJobImpl,
+                    Recurrence::cron("0 0 * * * *").unwrap(),
+                    MisfirePolicy::default(),
+                    Vec::new(),
+                )
+                .await
+                .unwrap()
+        };
+
+        let job_id = define().await;
+        manager.pause_recurring_job(&job_id).await.unwrap();
+        // redefining on startup keeps the job paused
+        define().await;
+
+        let job = manager
+            .context()
+            .get_required_service::<Storage>()
+            .job_repo()
+            .get(&job_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let recurring_job_impl = job
+            .r#impl()
+            .deserialize::<EmptyContextData, RecurringJobImpl>()
+            .unwrap();
+        assert_eq!(
+            recurring_job_impl.pause(),
+            Some(&Pause::new(
+                now(),
+                Some("2025-01-01T11:00:00Z".parse().unwrap())
+            ))
+        );
+        assert_eq!(pending_at(&manager, &job_id).await, None);
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_paused_run_is_skipped() {
+        let context = test_context();
+        let recurring_job_impl = paused_job_impl();
+        let job = add_job(&context, recurring_job_impl.clone()).await;
+        let run_context =
+            context.with_run_info(RunInfo::new(job.id(), RunId::default(), now(), now()));
+
+        let report = JobImpl::<EmptyContextData>::run(&recurring_job_impl, run_context.clone())
+            .await
+            .unwrap();
+        assert_eq!(report.metrics().get(SKIPPED_METRIC), Some(&json!(true)));
+
+        JobImpl::<EmptyContextData>::on_success(&recurring_job_impl, run_context).await;
+
+        let pending_job = context
+            .get_required_service::<Storage>()
+            .pending_job_repo()
+            .get(&job.id())
+            .await
+            .unwrap();
+        assert!(pending_job.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_retention_keeps_paused_recurring_job() {
+        let context = test_context();
+        context.services().add_service(
+            RetentionSettings::default()
+                .with_finished_job_retention(Duration::zero())
+                .unwrap(),
+        );
+        let storage = context.get_required_service::<Storage>();
+        let created_at = now() - Duration::hours(4);
+        let paused =
+            Job::from_impl::<EmptyContextData>(paused_job_impl(), created_at, Vec::new()).unwrap();
+        let finished =
+            Job::from_impl::<EmptyContextData>(TestJobImpl, created_at, Vec::new()).unwrap();
+        for job in [&paused, &finished] {
+            storage.job_repo().add(job.clone()).await.unwrap();
+        }
+        for job in [&paused, &finished] {
+            storage
+                .successful_run_repo()
+                .add(SuccessfulRun::new(
+                    RunId::default(),
+                    job.id(),
+                    now() - Duration::hours(3),
+                    now() - Duration::hours(3),
+                    Report::new(),
+                ))
+                .await
+                .unwrap();
+        }
+
+        RetentionRunner::new(context).run().await;
+
+        assert!(
+            storage
+                .job_repo()
+                .get(&paused.id())
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            storage
+                .job_repo()
+                .get(&finished.id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resume_recurring_job() {
+        let context = test_context();
+        let job = add_job(&context, paused_job_impl()).await;
+
+        let next = context
+            .get_required_service::<RecurringScheduler>()
+            .resume::<EmptyContextData>(&job.id(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(next, Some(now() - Duration::hours(2)));
+    }
+
+    #[tokio::test]
+    async fn test_resume_recurring_job_skipping_missed() {
+        let context = test_context();
+        let job = add_job(&context, paused_job_impl()).await;
+        let recurring_scheduler = context.get_required_service::<RecurringScheduler>();
+
+        let next = recurring_scheduler
+            .resume::<EmptyContextData>(&job.id(), true)
+            .await
+            .unwrap();
+        let resumed_again = recurring_scheduler
+            .resume::<EmptyContextData>(&job.id(), true)
+            .await
+            .unwrap();
+
+        assert_eq!(next, Some("2025-01-01T11:00:00Z".parse().unwrap()));
+        assert_eq!(resumed_again, None);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_recurring_job() {
+        let manager = JobManager::new_default(EmptyContextData, build_services).unwrap();
+        let job_id = manager
+            .define_recurring_job(
+                "hourly",
+                TestJobImpl,
+                Recurrence::cron("0 0 * * * *").unwrap(),
+                MisfirePolicy::default(),
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        let triggered_id = manager.trigger_recurring_job(&job_id).await.unwrap();
+
+        let triggered_job = manager
+            .context()
+            .get_required_service::<Storage>()
+            .job_repo()
+            .get(&triggered_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(triggered_id, job_id);
+        assert_eq!(triggered_job.r#impl().name(), &JobImplName::new("test"));
+        assert_eq!(pending_at(&manager, &triggered_id).await, Some(now()));
+        assert_eq!(
+            pending_at(&manager, &job_id).await,
+            Some("2025-01-01T11:00:00Z".parse().unwrap())
+        );
+
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trigger_recurring_job_with_fresh_policy_data() {
+        let policy = || {
+            BackoffRetryPolicy::<EmptyContextData>::new(3, Backoff::Linear(Duration::seconds(10)))
+                .unwrap()
+        };
+        let services = Services::default();
+        build_services(&services);
+        let mut policy_registry = PolicyRegistryBuilder::<EmptyContextData>::default();
+        policy_registry.register(policy());
+        services.add_service(policy_registry.build());
+        services.add_service(JobScheduler::new(services.clone()));
+        services.verify().unwrap();
+
+        let recurring_job_impl = RecurringJobImpl::new(
+            Recurrence::cron("0 0 * * * *").unwrap(),
+            MisfirePolicy::default(),
+            SerializedJobImpl::from_job_impl(TestJobImpl).unwrap(),
+        );
+        let job =
+            Job::from_impl::<EmptyContextData>(recurring_job_impl, now(), vec![Box::new(policy())])
+                .unwrap();
+        // the recurring job has used up one of its retries
+        let error = JobError::Custom(CustomError::new("test"));
+        policy().retry_at(&error, job.policies().data(), now());
+        let storage = services.get_required_service::<Storage>();
+        storage.job_repo().add(job.clone()).await.unwrap();
+
+        let triggered_id = services
+            .get_required_service::<RecurringScheduler>()
+            .trigger::<EmptyContextData>(&job.id())
+            .await
+            .unwrap();
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+        let triggered_job = storage
+            .job_repo()
+            .get(&triggered_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            policy().retry_at(&error, triggered_job.policies().data(), now()),
+            Some(now() + Duration::seconds(10))
+        );
+        assert_eq!(
+            policy().retry_at(&error, job.policies().data(), now()),
+            Some(now() + Duration::seconds(20))
+        );
     }
 }
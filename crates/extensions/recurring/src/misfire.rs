@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::recurrence::Recurrence;
+
+/// Decides what happens with fires that were missed, e.g. because no worker was running.
+///
+/// A fire is missed when the following fire time had already passed by the time its run
+/// started. A fire that starts late, but before the following fire time, always runs.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MisfirePolicy {
+    /// Runs once for all missed fires, then continues with the next future fire time.
+    #[default]
+    FireOnce,
+    /// Runs once for every missed fire, one after another, until caught up.
+    FireAll,
+    /// Does not run missed fires and continues with the next future fire time.
+    Skip,
+}
+
+impl MisfirePolicy {
+    pub fn is_missed(
+        recurrence: &Recurrence,
+        scheduled_at: DateTime<Utc>,
+        started_at: DateTime<Utc>,
+    ) -> bool {
+        recurrence
+            .next_after(scheduled_at)
+            .is_some_and(|next| next <= started_at)
+    }
+
+    /// Whether a fire scheduled at `scheduled_at` and started at `started_at` should run.
+    pub fn should_run(
+        &self,
+        recurrence: &Recurrence,
+        scheduled_at: DateTime<Utc>,
+        started_at: DateTime<Utc>,
+    ) -> bool {
+        *self != Self::Skip || !Self::is_missed(recurrence, scheduled_at, started_at)
+    }
+
+    /// Returns the time after which the fire following `scheduled_at` is looked up.
+    pub fn next_fire_after(
+        &self,
+        scheduled_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> DateTime<Utc> {
+        match self {
+            Self::FireAll => scheduled_at,
+            Self::FireOnce | Self::Skip => now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn anchor() -> DateTime<Utc> {
+        "2025-01-01T10:00:00Z".parse().unwrap()
+    }
+
+    fn every_hour() -> Recurrence {
+        Recurrence::interval(Duration::hours(1), anchor()).unwrap()
+    }
+
+    #[test]
+    fn test_late_fire_is_not_missed() {
+        let started_at = anchor() + Duration::minutes(59);
+
+        assert!(!MisfirePolicy::is_missed(
+            &every_hour(),
+            anchor(),
+            started_at
+        ));
+        assert!(MisfirePolicy::Skip.should_run(&every_hour(), anchor(), started_at));
+    }
+
+    #[test]
+    fn test_overdue_fire_is_missed() {
+        let started_at = anchor() + Duration::hours(3);
+
+        assert!(MisfirePolicy::is_missed(
+            &every_hour(),
+            anchor(),
+            started_at
+        ));
+        assert!(MisfirePolicy::FireOnce.should_run(&every_hour(), anchor(), started_at));
+        assert!(MisfirePolicy::FireAll.should_run(&every_hour(), anchor(), started_at));
+        assert!(!MisfirePolicy::Skip.should_run(&every_hour(), anchor(), started_at));
+    }
+
+    #[test]
+    fn test_next_fire_after() {
+        let now = anchor() + Duration::hours(3);
+
+        assert_eq!(
+            MisfirePolicy::FireAll.next_fire_after(anchor(), now),
+            anchor()
+        );
+        assert_eq!(MisfirePolicy::FireOnce.next_fire_after(anchor(), now), now);
+        assert_eq!(MisfirePolicy::Skip.next_fire_after(anchor(), now), now);
+    }
+}
@@ -0,0 +1,273 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, LocalResult, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid cron expression: {0}")]
+    InvalidCronExpression(String),
+    #[error("interval has to be at least 1ms")]
+    InvalidInterval,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Describes when a recurring job fires.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Recurrence {
+    /// Fires on every match of a cron expression evaluated in the local time of `timezone`.
+    ///
+    /// Expressions use the `cron` crate syntax, which starts with a seconds field,
+    /// e.g. `0 30 2 * * *` fires every day at 02:30:00.
+    ///
+    /// Local times that don't exist because clocks go forward are skipped, and local times
+    /// that occur twice because clocks go back fire only once, at their earlier occurrence.
+    Cron {
+        schedule: Box<cron::Schedule>,
+        #[serde(default)]
+        timezone: Tz,
+    },
+
+    /// Fires every `every`, starting at `anchor`.
+    ///
+    /// Intervals are measured in absolute time, so they are not affected by DST changes.
+    Interval {
+        anchor: DateTime<Utc>,
+        #[serde(with = "duration_millis")]
+        every: Duration,
+    },
+}
+
+impl Recurrence {
+    /// Creates a recurrence firing on every match of `expression` in UTC.
+    pub fn cron(expression: &str) -> Result<Self> {
+        Self::cron_in(expression, Tz::UTC)
+    }
+
+    /// Creates a recurrence firing on every match of `expression` in `timezone`,
+    /// e.g. `chrono_tz::Europe::Warsaw`.
+    pub fn cron_in(expression: &str, timezone: Tz) -> Result<Self> {
+        let schedule = cron::Schedule::from_str(expression)
+            .map_err(|e| Error::InvalidCronExpression(e.to_string()))?;
+
+        Ok(Self::Cron {
+            schedule: Box::new(schedule),
+            timezone,
+        })
+    }
+
+    /// Creates a recurrence firing every `every`, e.g. every 15 minutes, aligned to `anchor`.
+    ///
+    /// Intervals are kept with millisecond precision, so `every` has to be at least 1ms.
+    pub fn interval(every: Duration, anchor: DateTime<Utc>) -> Result<Self> {
+        if every < Duration::milliseconds(1) {
+            return Err(Error::InvalidInterval);
+        }
+
+        Ok(Self::Interval { anchor, every })
+    }
+
+    /// Returns the first fire time strictly after `after`,
+    /// or `None` if the recurrence has no more fire times.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Cron { schedule, timezone } => schedule
+                .after(&after.with_timezone(timezone))
+                .find(|candidate| *candidate > after && !is_repeated_local_time(candidate))
+                .map(|candidate| candidate.with_timezone(&Utc)),
+            Self::Interval { anchor, every } => {
+                if after < *anchor {
+                    return Some(*anchor);
+                }
+
+                let every = every.num_milliseconds();
+                let elapsed = (after - *anchor).num_milliseconds();
+                let slots = elapsed / every + 1;
+                anchor.checked_add_signed(Duration::milliseconds(slots.checked_mul(every)?))
+            }
+        }
+    }
+}
+
+/// Whether `time` is the later occurrence of a local time repeated when clocks go back.
+fn is_repeated_local_time(time: &DateTime<Tz>) -> bool {
+    match time.timezone().from_local_datetime(&time.naive_local()) {
+        LocalResult::Ambiguous(earliest, _) => earliest != *time,
+        _ => false,
+    }
+}
+
+mod duration_millis {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(duration.num_milliseconds())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let millis = i64::deserialize(deserializer)?;
+        if millis < 1 {
+            return Err(de::Error::custom("interval has to be at least 1ms"));
+        }
+
+        Ok(Duration::milliseconds(millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cron_next_after() {
+        let recurrence = Recurrence::cron("0 30 2 * * *").unwrap();
+        let after = "2025-01-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let next = recurrence.next_after(after).unwrap();
+
+        assert_eq!(
+            next,
+            "2025-01-02T02:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cron_next_after_is_exclusive() {
+        let recurrence = Recurrence::cron("0 30 2 * * *").unwrap();
+        let after = "2025-01-01T02:30:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let next = recurrence.next_after(after).unwrap();
+
+        assert_eq!(
+            next,
+            "2025-01-02T02:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cron_exhausted() {
+        let recurrence = Recurrence::cron("0 0 0 1 1 * 2020").unwrap();
+        let after = "2025-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        assert!(recurrence.next_after(after).is_none());
+    }
+
+    #[test]
+    fn test_cron_in_timezone() {
+        let recurrence = Recurrence::cron_in("0 30 2 * * *", chrono_tz::Europe::Warsaw).unwrap();
+        let after = "2025-01-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let next = recurrence.next_after(after).unwrap();
+
+        assert_eq!(
+            next,
+            "2025-01-02T01:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cron_skips_nonexistent_local_time() {
+        // clocks in Warsaw go forward from 02:00 to 03:00 on 2025-03-30
+        let recurrence = Recurrence::cron_in("0 30 2 * * *", chrono_tz::Europe::Warsaw).unwrap();
+        let after = "2025-03-29T02:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let next = recurrence.next_after(after).unwrap();
+
+        assert_eq!(
+            next,
+            "2025-03-31T00:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cron_fires_once_for_ambiguous_local_time() {
+        // clocks in Warsaw go back from 03:00 to 02:00 on 2025-10-26
+        let recurrence = Recurrence::cron_in("0 30 2 * * *", chrono_tz::Europe::Warsaw).unwrap();
+        let after = "2025-10-25T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let first = recurrence.next_after(after).unwrap();
+        let second = recurrence.next_after(first).unwrap();
+        let between = recurrence
+            .next_after("2025-10-26T01:00:00Z".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            first,
+            "2025-10-26T00:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            second,
+            "2025-10-27T01:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(between, second);
+    }
+
+    #[test]
+    fn test_cron_serialization() {
+        let recurrence = Recurrence::cron_in("0 30 2 * * *", chrono_tz::Europe::Warsaw).unwrap();
+        let after = "2025-01-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let value = serde_json::to_value(&recurrence).unwrap();
+        let deserialized: Recurrence = serde_json::from_value(value).unwrap();
+
+        assert_eq!(deserialized.next_after(after), recurrence.next_after(after));
+    }
+
+    #[test]
+    fn test_interval_next_after() {
+        let anchor = "2025-01-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let recurrence = Recurrence::interval(Duration::minutes(15), anchor).unwrap();
+
+        assert_eq!(
+            recurrence.next_after(anchor - Duration::hours(1)),
+            Some(anchor)
+        );
+        assert_eq!(
+            recurrence.next_after(anchor),
+            Some(anchor + Duration::minutes(15))
+        );
+        assert_eq!(
+            recurrence.next_after(anchor + Duration::minutes(40)),
+            Some(anchor + Duration::minutes(45))
+        );
+    }
+
+    #[test]
+    fn test_interval_serialization() {
+        let anchor = "2025-01-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let recurrence = Recurrence::interval(Duration::minutes(15), anchor).unwrap();
+
+        let value = serde_json::to_value(&recurrence).unwrap();
+        let deserialized: Recurrence = serde_json::from_value(value).unwrap();
+
+        assert_eq!(
+            deserialized.next_after(anchor),
+            Some(anchor + Duration::minutes(15))
+        );
+    }
+
+    #[test]
+    fn test_invalid_interval() {
+        for every in [Duration::zero(), Duration::microseconds(500)] {
+            let result = Recurrence::interval(every, Utc::now());
+
+            assert!(matches!(result, Err(Error::InvalidInterval)));
+        }
+
+        let value = serde_json::json!({
+            "Interval": { "anchor": "2025-01-01T10:00:00Z", "every": 0 }
+        });
+        assert!(serde_json::from_value::<Recurrence>(value).is_err());
+    }
+
+    #[test]
+    fn test_invalid_cron() {
+        let result = Recurrence::cron("not a cron");
+
+        assert!(matches!(result, Err(Error::InvalidCronExpression(_))));
+    }
+}
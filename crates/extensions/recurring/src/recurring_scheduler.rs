@@ -0,0 +1,416 @@
+use chrono::{DateTime, Utc};
+use jobfire_core::{
+    domain::job::{
+        self, Job,
+        context::ContextData,
+        id::JobId,
+        r#impl::{JobImpl, SerializedJobImpl},
+        policy::{Policies, PolicyData},
+    },
+    managers::{
+        job_manager,
+        job_scheduler::{self, JobScheduler},
+    },
+    registries::{self, policies::PolicyRegistry},
+    services::{
+        Services,
+        time::{AnyClock, Clock},
+        verify::{ServiceMissing, VerifyService},
+    },
+    storage::{self, Storage},
+    verify_services,
+};
+use thiserror::Error;
+
+use crate::{
+    r#impl::{Pause, RecurringJobImpl},
+    recurrence::Recurrence,
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("scheduler error: {0}")]
+    Scheduler(#[from] job_scheduler::Error),
+    #[error("storage error: {0}")]
+    Storage(#[from] storage::error::Error),
+    #[error("recurrence has no upcoming fire time")]
+    NoUpcomingFire,
+    #[error("job is not a recurring job")]
+    NotRecurring,
+    #[error("job impl error: {0}")]
+    JobImpl(#[from] job::r#impl::Error),
+    #[error("policy error: {0}")]
+    Policy(#[from] registries::policies::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl From<Error> for job_manager::Error {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Scheduler(error) => job_manager::Error::Scheduler(error),
+            Error::Storage(error) => job_manager::Error::Storage(error),
+            Error::NoUpcomingFire | Error::NotRecurring | Error::JobImpl(_) | Error::Policy(_) => {
+                job_manager::Error::InternalError(value.to_string())
+            }
+        }
+    }
+}
+
+/// Computes and schedules upcoming occurrences of recurring jobs.
+pub struct RecurringScheduler {
+    services: Services,
+}
+
+impl Clone for RecurringScheduler {
+    fn clone(&self) -> Self {
+        Self {
+            services: self.services.clone(),
+        }
+    }
+}
+
+impl VerifyService for RecurringScheduler {
+    fn verify(&self, services: &Services) -> std::result::Result<(), ServiceMissing> {
+        verify_services!(services, JobScheduler, AnyClock, Storage);
+        Ok(())
+    }
+}
+
+impl RecurringScheduler {
+    pub fn new(services: Services) -> Self {
+        Self { services }
+    }
+
+    /// Returns the first fire time after the current time.
+    pub fn first_fire(&self, recurrence: &Recurrence) -> Option<DateTime<Utc>> {
+        let now = self.services.get_required_service::<AnyClock>().utc_now();
+        recurrence.next_after(now)
+    }
+
+    /// Schedules the occurrence following the one scheduled at `scheduled_at`
+    /// of an already stored recurring job.
+    ///
+    /// The recurrence and misfire policy are read from storage, so changes made while
+    /// the job was running are respected.
+    ///
+    /// Returns the time of the scheduled occurrence, or `None` if the job has been
+    /// removed or paused, or its recurrence has no more fire times.
+    pub async fn schedule_next<TData: ContextData>(
+        &self,
+        job_id: &JobId,
+        scheduled_at: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let recurring_job_impl = match self.get::<TData>(job_id).await? {
+            Some((_, recurring_job_impl)) => recurring_job_impl,
+            None => {
+                log::info!("recurring job {job_id} has been removed");
+                return Ok(None);
+            }
+        };
+        if recurring_job_impl.is_paused() {
+            log::info!("recurring job {job_id} is paused");
+            return Ok(None);
+        }
+
+        let now = self.services.get_required_service::<AnyClock>().utc_now();
+        let after = recurring_job_impl
+            .misfire_policy()
+            .next_fire_after(scheduled_at, now);
+        let next = match recurring_job_impl.recurrence().next_after(after) {
+            Some(next) => next,
+            None => {
+                log::info!("recurrence of job {job_id} has no more fire times");
+                return Ok(None);
+            }
+        };
+
+        self.services
+            .get_required_service::<JobScheduler>()
+            .schedule_existing(job_id, next)
+            .await?;
+
+        Ok(Some(next))
+    }
+
+    /// Stores `job`, whose implementation is `recurring_job_impl`, and schedules its first
+    /// occurrence, or updates the job already stored under the same id.
+    ///
+    /// When updating, the pending occurrence is moved to the first fire time of the new
+    /// recurrence only if the recurrence has changed. Policy data is kept unless the set
+    /// of policies has changed. A paused job stays paused.
+    pub async fn upsert<TData: ContextData>(
+        &self,
+        job: Job,
+        recurring_job_impl: &RecurringJobImpl,
+    ) -> Result<()> {
+        let storage = self.services.get_required_service::<Storage>();
+        let job_scheduler = self.services.get_required_service::<JobScheduler>();
+        let job_id = job.id();
+        let first_fire = self.first_fire(recurring_job_impl.recurrence());
+
+        let existing = match storage.job_repo().get(&job_id).await? {
+            Some(existing) => existing,
+            None => {
+                let first_fire = first_fire.ok_or(Error::NoUpcomingFire)?;
+                return match job_scheduler.schedule(job, first_fire).await {
+                    // defined concurrently, e.g. by another instance starting up
+                    Ok(_) | Err(job_scheduler::Error::AlreadyScheduled) => Ok(()),
+                    Err(error) => Err(error.into()),
+                };
+            }
+        };
+
+        let previous = existing
+            .r#impl()
+            .deserialize::<TData, RecurringJobImpl>()
+            .ok();
+        let recurrence_changed = previous
+            .as_ref()
+            .is_none_or(|previous| previous.recurrence() != recurring_job_impl.recurrence());
+        // an occurrence pending under the previous recurrence is not resumed
+        let pause = previous
+            .and_then(|previous| previous.pause().copied())
+            .map(|pause| match recurrence_changed {
+                true => Pause::new(pause.paused_at(), None),
+                false => pause,
+            });
+
+        let mut recurring_job_impl = recurring_job_impl.clone();
+        recurring_job_impl.update_pause(pause);
+        self.update::<TData>(&job_id, &recurring_job_impl).await?;
+        if existing.policies().names() != job.policies().names() {
+            storage
+                .job_repo()
+                .update_policies(&job_id, job.policies().clone())
+                .await?;
+        }
+
+        if recurring_job_impl.is_paused() {
+            return Ok(());
+        }
+
+        let pending_job = storage.pending_job_repo().get(&job_id).await?;
+        let running_job = storage.running_job_repo().get(&job_id).await?;
+
+        match (pending_job, first_fire) {
+            (Some(_), Some(first_fire)) if recurrence_changed => {
+                job_scheduler.reschedule(&job_id, first_fire).await?;
+            }
+            (Some(_), None) if recurrence_changed => {
+                job_scheduler.cancel(&job_id).await?;
+            }
+            // a recurring job whose recurrence had been exhausted
+            (None, Some(first_fire)) if running_job.is_none() => {
+                self.schedule_existing(&job_id, first_fire).await?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Pauses a stored recurring job, removing its pending occurrence.
+    ///
+    /// The pause is persisted, so the job stays paused across restarts.
+    /// Pausing an already paused job does nothing.
+    pub async fn pause<TData: ContextData>(&self, job_id: &JobId) -> Result<()> {
+        let storage = self.services.get_required_service::<Storage>();
+        let (_, mut recurring_job_impl) = self
+            .get::<TData>(job_id)
+            .await?
+            .ok_or(job_scheduler::Error::JobNotFound)?;
+        if recurring_job_impl.is_paused() {
+            return Ok(());
+        }
+
+        let now = self.services.get_required_service::<AnyClock>().utc_now();
+        let pending_at = storage
+            .pending_job_repo()
+            .get(job_id)
+            .await?
+            .map(|pending_job| pending_job.scheduled_at());
+
+        // the pause is stored first, so an occurrence popped in the meantime is skipped
+        recurring_job_impl.update_pause(Some(Pause::new(now, pending_at)));
+        self.update::<TData>(job_id, &recurring_job_impl).await?;
+
+        match storage.pending_job_repo().delete(job_id).await {
+            Ok(_) | Err(storage::error::Error::NotFound) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Resumes a paused recurring job.
+    ///
+    /// With `skip_missed`, the job continues with the first fire time after now.
+    /// Otherwise the occurrence that was pending when the job was paused is scheduled again,
+    /// and the fires missed while paused are handled by the misfire policy of the job.
+    /// Resuming a job that isn't paused does nothing.
+    ///
+    /// Returns the time of the scheduled occurrence, or `None` if nothing was scheduled.
+    pub async fn resume<TData: ContextData>(
+        &self,
+        job_id: &JobId,
+        skip_missed: bool,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let (_, mut recurring_job_impl) = self
+            .get::<TData>(job_id)
+            .await?
+            .ok_or(job_scheduler::Error::JobNotFound)?;
+        let pause = match recurring_job_impl.pause() {
+            Some(pause) => *pause,
+            None => return Ok(None),
+        };
+
+        let next = match skip_missed {
+            true => self.first_fire(recurring_job_impl.recurrence()),
+            false => pause.pending_at().or_else(|| {
+                recurring_job_impl
+                    .recurrence()
+                    .next_after(pause.paused_at())
+            }),
+        };
+
+        recurring_job_impl.update_pause(None);
+        self.update::<TData>(job_id, &recurring_job_impl).await?;
+
+        if let Some(next) = next {
+            self.schedule_existing(job_id, next).await?;
+        }
+
+        Ok(next)
+    }
+
+    /// Schedules a one-off run of a stored recurring job to run now.
+    ///
+    /// The run is a separate job with the wrapped implementation and the same policies,
+    /// so it doesn't shift the regular occurrences and works for paused jobs as well.
+    /// Its policies start with fresh data, e.g. without retry attempts used by the
+    /// recurring job.
+    ///
+    /// Returns the id of the one-off job.
+    pub async fn trigger<TData: ContextData>(&self, job_id: &JobId) -> Result<JobId> {
+        let (job, recurring_job_impl) = self
+            .get::<TData>(job_id)
+            .await?
+            .ok_or(job_scheduler::Error::JobNotFound)?;
+
+        let now = self.services.get_required_service::<AnyClock>().utc_now();
+        let policy_registry = self
+            .services
+            .get_required_service::<PolicyRegistry<TData>>();
+        let data = PolicyData::default();
+        for name in job.policies().names() {
+            policy_registry.init(name.clone(), data.clone())?;
+        }
+        let policies = Policies::new(job.policies().names().clone(), data);
+        let one_off_job = Job::new(
+            JobId::default(),
+            now,
+            recurring_job_impl.inner().clone(),
+            policies,
+        );
+        let one_off_job_id = one_off_job.id();
+
+        self.services
+            .get_required_service::<JobScheduler>()
+            .schedule(one_off_job, now)
+            .await?;
+
+        Ok(one_off_job_id)
+    }
+
+    async fn get<TData: ContextData>(
+        &self,
+        job_id: &JobId,
+    ) -> Result<Option<(Job, RecurringJobImpl)>> {
+        let storage = self.services.get_required_service::<Storage>();
+        let job = match storage.job_repo().get(job_id).await? {
+            Some(job) => job,
+            None => return Ok(None),
+        };
+
+        let recurring_job_impl = job
+            .r#impl()
+            .deserialize::<TData, RecurringJobImpl>()
+            .map_err(|_| Error::NotRecurring)?;
+
+        Ok(Some((job, recurring_job_impl)))
+    }
+
+    async fn update<TData: ContextData>(
+        &self,
+        job_id: &JobId,
+        recurring_job_impl: &RecurringJobImpl,
+    ) -> Result<()> {
+        let storage = self.services.get_required_service::<Storage>();
+        let r#impl = SerializedJobImpl::from_job_impl::<TData, _>(recurring_job_impl.clone())?;
+        storage.job_repo().update_impl(job_id, r#impl).await?;
+
+        Ok(())
+    }
+
+    async fn schedule_existing(&self, job_id: &JobId, scheduled_at: DateTime<Utc>) -> Result<()> {
+        match self
+            .services
+            .get_required_service::<JobScheduler>()
+            .schedule_existing(job_id, scheduled_at)
+            .await
+        {
+            Ok(_) | Err(job_scheduler::Error::AlreadyScheduled) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Removes a stored recurring job together with its pending occurrence.
+    ///
+    /// Returns `false` if there was no such job.
+    pub async fn remove(&self, job_id: &JobId) -> Result<bool> {
+        let storage = self.services.get_required_service::<Storage>();
+
+        match storage.pending_job_repo().delete(job_id).await {
+            Ok(_) | Err(storage::error::Error::NotFound) => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        match storage.job_repo().delete(job_id).await {
+            Ok(_) => Ok(true),
+            Err(storage::error::Error::NotFound) => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Removes all recurring jobs defined with a key that is not in `keys`.
+    ///
+    /// Recurring jobs scheduled without a key are left untouched.
+    /// Returns ids of the removed jobs.
+    pub async fn retain_keys<TData: ContextData>(&self, keys: &[&str]) -> Result<Vec<JobId>> {
+        let storage = self.services.get_required_service::<Storage>();
+        let jobs = storage
+            .job_repo()
+            .get_by_impl_name(&<RecurringJobImpl as JobImpl<TData>>::name())
+            .await?;
+
+        let mut removed = Vec::new();
+        for job in jobs {
+            let recurring_job_impl = match job.r#impl().deserialize::<TData, RecurringJobImpl>() {
+                Ok(recurring_job_impl) => recurring_job_impl,
+                Err(_) => {
+                    log::warn!("failed to deserialize recurring job {}", job.id());
+                    continue;
+                }
+            };
+
+            if let Some(key) = recurring_job_impl.key()
+                && !keys.contains(&key)
+                && self.remove(&job.id()).await?
+            {
+                log::info!("removed recurring job {key} no longer defined");
+                removed.push(job.id());
+            }
+        }
+
+        Ok(removed)
+    }
+}
@@ -51,9 +51,9 @@ impl JobImpl<SimpleContextData> for SimpleJobImpl {
         let context = context.data();
         context.increment();
         log::info!("job number {} run", context.read());
-        Err(jobfire_core::domain::job::error::JobError::Custom {
-            message: "test error".to_owned(),
-        })
+        Err(jobfire_core::domain::job::error::JobError::Custom(
+            jobfire_core::domain::job::error::CustomError::new("test error"),
+        ))
     }
 
     async fn on_success(&self, _context: Context<SimpleContextData>) {
@@ -1,14 +1,21 @@
 pub mod pending;
 pub mod running;
 
-use crate::{SqliteStorageSettings, map_sqlx_error};
+use crate::{SqliteStorageSettings, add_column_if_missing, map_sqlx_error, query::list_query};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use jobfire_core::{
-    domain::job::{Job, id::JobId, policy::Policies},
-    storage::{self, job::JobRepo},
+    domain::job::{
+        Job,
+        id::JobId,
+        r#impl::{JobImplName, SerializedJobImpl},
+        policy::Policies,
+    },
+    storage::{self, job::JobRepo, query::Filter},
 };
-use sqlx::SqlitePool;
+use sqlx::{Executor, Sqlite, SqlitePool};
 
+#[derive(Clone)]
 pub struct SqliteJobRepo {
     pool: SqlitePool,
     settings: SqliteStorageSettings,
@@ -34,23 +41,52 @@ CREATE TABLE IF NOT EXISTS {} (
         .execute(pool)
         .await?;
 
+        add_column_if_missing(
+            pool,
+            &settings.job_table_name,
+            "recovery_policy",
+            "TEXT NOT NULL DEFAULT '\"fail\"'",
+        )
+        .await?;
+
         Ok(())
     }
 }
 
+#[derive(sqlx::FromRow)]
+struct Row {
+    id: String,
+    created_at: i64,
+    r#impl: String,
+    policies: String,
+    recovery_policy: String,
+}
+
+impl Row {
+    fn into_job(self) -> storage::error::Result<Job> {
+        let id = self
+            .id
+            .parse()
+            .map_err(|_| storage::error::Error::Internal)?;
+        let created_at = chrono::DateTime::from_timestamp_millis(self.created_at)
+            .ok_or(storage::error::Error::Internal)?;
+        let r#impl =
+            serde_json::from_str(&self.r#impl).map_err(|_| storage::error::Error::Internal)?;
+        let policies =
+            serde_json::from_str(&self.policies).map_err(|_| storage::error::Error::Internal)?;
+
+        let recovery_policy = serde_json::from_str(&self.recovery_policy)
+            .map_err(|_| storage::error::Error::Internal)?;
+
+        Ok(Job::new(id, created_at, r#impl, policies).with_recovery_policy(recovery_policy))
+    }
+}
+
 #[async_trait]
 impl JobRepo for SqliteJobRepo {
     async fn get(&self, job_id: &JobId) -> storage::error::Result<Option<Job>> {
-        #[derive(sqlx::FromRow)]
-        struct Row {
-            id: String,
-            created_at: i64,
-            r#impl: String,
-            policies: String,
-        }
-
         let result = sqlx::query_as::<_, Row>(&format!(
-            "SELECT id, created_at, impl, policies FROM {} WHERE id = ?",
+            "SELECT id, created_at, impl, policies, recovery_policy FROM {} WHERE id = ?",
             self.settings.job_table_name
         ))
         .bind(job_id.to_string())
@@ -58,43 +94,11 @@ impl JobRepo for SqliteJobRepo {
         .await
         .map_err(map_sqlx_error)?;
 
-        match result {
-            Some(row) => {
-                let id = row
-                    .id
-                    .parse()
-                    .map_err(|_| storage::error::Error::Internal)?;
-                let created_at = chrono::DateTime::from_timestamp_millis(row.created_at)
-                    .ok_or(storage::error::Error::Internal)?;
-                let r#impl = serde_json::from_str(&row.r#impl)
-                    .map_err(|_| storage::error::Error::Internal)?;
-                let policies = serde_json::from_str(&row.policies)
-                    .map_err(|_| storage::error::Error::Internal)?;
-
-                Ok(Some(Job::new(id, created_at, r#impl, policies)))
-            }
-            None => Ok(None),
-        }
+        result.map(Row::into_job).transpose()
     }
 
     async fn add(&self, job: Job) -> storage::error::Result<()> {
-        if self.get(&job.id()).await?.is_some() {
-            return Err(storage::error::Error::AlreadyExists);
-        }
-
-        sqlx::query(&format!(
-            "INSERT INTO {} (id, created_at, impl, policies) VALUES (?, ?, ?, ?)",
-            self.settings.job_table_name
-        ))
-        .bind(job.id().to_string())
-        .bind(job.created_at().timestamp_millis())
-        .bind(serde_json::to_string(job.r#impl()).map_err(|_| storage::error::Error::Internal)?)
-        .bind(serde_json::to_string(job.policies()).map_err(|_| storage::error::Error::Internal)?)
-        .execute(&self.pool)
-        .await
-        .map_err(map_sqlx_error)?;
-
-        Ok(())
+        insert_job(&self.pool, &self.settings, &job).await
     }
 
     async fn delete(&self, job_id: &JobId) -> storage::error::Result<Job> {
@@ -120,21 +124,355 @@ impl JobRepo for SqliteJobRepo {
         job_id: &JobId,
         policies: Policies,
     ) -> storage::error::Result<()> {
-        let existing = self.get(job_id).await?;
-        if existing.is_none() {
-            return Err(storage::error::Error::NotFound);
-        }
+        update_job_policies(&self.pool, &self.settings, job_id, &policies).await
+    }
 
-        sqlx::query(&format!(
-            "UPDATE {} SET policies = ? WHERE id = ?",
+    async fn update_impl(
+        &self,
+        job_id: &JobId,
+        r#impl: SerializedJobImpl,
+    ) -> storage::error::Result<()> {
+        let result = sqlx::query(&format!(
+            "UPDATE {} SET impl = ? WHERE id = ?",
             self.settings.job_table_name
         ))
-        .bind(serde_json::to_string(&policies).map_err(|_| storage::error::Error::Internal)?)
+        .bind(serde_json::to_string(&r#impl).map_err(|_| storage::error::Error::Internal)?)
         .bind(job_id.to_string())
         .execute(&self.pool)
         .await
         .map_err(map_sqlx_error)?;
 
-        Ok(())
+        match result.rows_affected() {
+            0 => Err(storage::error::Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_by_impl_name(&self, name: &JobImplName) -> storage::error::Result<Vec<Job>> {
+        let rows = sqlx::query_as::<_, Row>(&format!(
+            "SELECT id, created_at, impl, policies, recovery_policy FROM {} WHERE json_extract(impl, '$.inner.name') = ?",
+            self.settings.job_table_name
+        ))
+        .bind(name.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        rows.into_iter().map(Row::into_job).collect()
+    }
+
+    async fn list(&self, filter: &Filter) -> storage::error::Result<Vec<Job>> {
+        let rows = list_query(
+            &format!(
+                "SELECT id, created_at, impl, policies, recovery_policy FROM {}",
+                self.settings.job_table_name
+            ),
+            &self.settings,
+            filter,
+            filter.state(),
+            "id",
+            "created_at",
+            "created_at, id",
+        )
+        .build_query_as::<Row>()
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        rows.into_iter().map(Row::into_job).collect()
+    }
+}
+
+/// Inserts a job, the primary key rejects duplicates.
+pub(crate) async fn insert_job<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job: &Job,
+) -> storage::error::Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(&format!(
+        "INSERT INTO {} (id, created_at, impl, policies, recovery_policy) VALUES (?, ?, ?, ?, ?)",
+        settings.job_table_name
+    ))
+    .bind(job.id().to_string())
+    .bind(job.created_at().timestamp_millis())
+    .bind(serde_json::to_string(job.r#impl()).map_err(|_| storage::error::Error::Internal)?)
+    .bind(serde_json::to_string(job.policies()).map_err(|_| storage::error::Error::Internal)?)
+    .bind(
+        serde_json::to_string(&job.recovery_policy())
+            .map_err(|_| storage::error::Error::Internal)?,
+    )
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    Ok(())
+}
+
+pub(crate) async fn update_job_policies<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job_id: &JobId,
+    policies: &Policies,
+) -> storage::error::Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let result = sqlx::query(&format!(
+        "UPDATE {} SET policies = ? WHERE id = ?",
+        settings.job_table_name
+    ))
+    .bind(serde_json::to_string(policies).map_err(|_| storage::error::Error::Internal)?)
+    .bind(job_id.to_string())
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    match result.rows_affected() {
+        0 => Err(storage::error::Error::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// Deletes a job that is neither pending nor running and whose runs all finished
+/// before `finished_before`, a job without any runs is kept.
+pub(crate) async fn delete_finished_job<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job_id: &JobId,
+    finished_before: DateTime<Utc>,
+) -> storage::error::Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let result = sqlx::query(&format!(
+        "
+DELETE FROM {0}
+WHERE id = ?1
+    AND id NOT IN (SELECT job_id FROM {1})
+    AND id NOT IN (SELECT job_id FROM {2})
+    AND (
+        EXISTS (SELECT 1 FROM {3} WHERE job_id = ?1)
+        OR EXISTS (SELECT 1 FROM {4} WHERE job_id = ?1)
+    )
+    AND NOT EXISTS (SELECT 1 FROM {3} WHERE job_id = ?1 AND finished_at >= ?2)
+    AND NOT EXISTS (SELECT 1 FROM {4} WHERE job_id = ?1 AND finished_at >= ?2)
+",
+        settings.job_table_name,
+        settings.pending_job_table_name,
+        settings.running_job_table_name,
+        settings.successful_run_table_name,
+        settings.failed_run_table_name,
+    ))
+    .bind(job_id.to_string())
+    .bind(finished_before.timestamp_millis())
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    match result.rows_affected() {
+        0 => Err(storage::error::Error::NotFound),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use jobfire_core::{
+        domain::{
+            job::{
+                error::JobError, pending::PendingJob, policy::PolicyData, recovery::RecoveryPolicy,
+                report::Report,
+            },
+            run::{failed::FailedRun, id::RunId, successful::SuccessfulRun},
+        },
+        storage::{Storage, transaction::Transaction},
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::SqliteStorage;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    fn job(name: &str) -> Job {
+        Job::new(
+            JobId::default(),
+            chrono::DateTime::from_timestamp_millis(1).unwrap(),
+            SerializedJobImpl::new(JobImplName::new(name), json!({ "value": 1 })),
+            Policies::new(Vec::new(), PolicyData::default()),
+        )
+    }
+
+    async fn repo() -> SqliteJobRepo {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        SqliteJobRepo::new(pool, SqliteStorageSettings::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_update_impl() {
+        let repo = repo().await;
+        let job = job("test");
+        repo.add(job.clone()).await.unwrap();
+
+        repo.update_impl(
+            &job.id(),
+            SerializedJobImpl::new(JobImplName::new("other"), json!({ "value": 2 })),
+        )
+        .await
+        .unwrap();
+
+        let retrieved = repo.get(&job.id()).await.unwrap().unwrap();
+        assert_eq!(retrieved.r#impl().name(), &JobImplName::new("other"));
+    }
+
+    #[tokio::test]
+    async fn test_update_impl_missing_job() {
+        let repo = repo().await;
+
+        let result = repo
+            .update_impl(
+                &JobId::default(),
+                SerializedJobImpl::new(JobImplName::new("test"), json!(null)),
+            )
+            .await;
+
+        assert!(matches!(result, Err(storage::error::Error::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_get_by_impl_name() {
+        let repo = repo().await;
+        let first = job("test");
+        let second = job("test");
+        repo.add(first.clone()).await.unwrap();
+        repo.add(second.clone()).await.unwrap();
+        repo.add(job("other")).await.unwrap();
+
+        let mut ids = repo
+            .get_by_impl_name(&JobImplName::new("test"))
+            .await
+            .unwrap()
+            .iter()
+            .map(|job| job.id().to_string())
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        let mut expected = vec![first.id().to_string(), second.id().to_string()];
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_policy() {
+        let repo = repo().await;
+        let job = job("test").with_recovery_policy(RecoveryPolicy::Requeue);
+        repo.add(job.clone()).await.unwrap();
+
+        let retrieved = repo.get(&job.id()).await.unwrap().unwrap();
+        assert_eq!(retrieved.recovery_policy(), RecoveryPolicy::Requeue);
+    }
+
+    #[tokio::test]
+    async fn test_init_adds_recovery_policy_column() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let settings = SqliteStorageSettings::default();
+        sqlx::query(&format!(
+            "CREATE TABLE {} (id TEXT PRIMARY KEY, created_at INTEGER NOT NULL, impl TEXT NOT NULL, policies TEXT NOT NULL)",
+            settings.job_table_name
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(&format!(
+            "INSERT INTO {} (id, created_at, impl, policies) VALUES (?, 1, ?, ?)",
+            settings.job_table_name
+        ))
+        .bind(JobId::default().to_string())
+        .bind(serde_json::to_string(job("test").r#impl()).unwrap())
+        .bind(serde_json::to_string(job("test").policies()).unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repo = SqliteJobRepo::new(pool, settings).await.unwrap();
+
+        let jobs = repo
+            .get_by_impl_name(&JobImplName::new("test"))
+            .await
+            .unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].recovery_policy(), RecoveryPolicy::Fail);
+    }
+
+    #[tokio::test]
+    async fn test_delete_finished_job() {
+        let storage = Storage::from(SqliteStorage::new_in_memory().await);
+        let finished = job("test");
+        let recently_finished = job("test");
+        let pending = job("test");
+        let never_run = job("test");
+        for job in [&finished, &recently_finished, &pending, &never_run] {
+            storage.job_repo().add(job.clone()).await.unwrap();
+        }
+        storage
+            .pending_job_repo()
+            .add(PendingJob::new(pending.id(), at(20)))
+            .await
+            .unwrap();
+        for job in [&finished, &pending] {
+            storage
+                .successful_run_repo()
+                .add(SuccessfulRun::new(
+                    RunId::default(),
+                    job.id(),
+                    at(1),
+                    at(5),
+                    Report::default(),
+                ))
+                .await
+                .unwrap();
+        }
+        storage
+            .failed_run_repo()
+            .add(FailedRun::new(
+                RunId::default(),
+                recently_finished.id(),
+                at(1),
+                at(10),
+                JobError::Orphaned,
+            ))
+            .await
+            .unwrap();
+
+        for job in [&recently_finished, &pending, &never_run] {
+            let result = storage
+                .commit(Transaction::default().delete_finished_job(job.id(), at(10)))
+                .await;
+            assert!(matches!(result, Err(storage::error::Error::NotFound)));
+        }
+        storage
+            .commit(Transaction::default().delete_finished_job(finished.id(), at(10)))
+            .await
+            .unwrap();
+
+        assert!(
+            storage
+                .job_repo()
+                .get(&finished.id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        for job in [&recently_finished, &pending, &never_run] {
+            assert!(storage.job_repo().get(&job.id()).await.unwrap().is_some());
+        }
     }
 }
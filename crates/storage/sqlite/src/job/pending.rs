@@ -1,13 +1,19 @@
 use chrono::{DateTime, Utc};
 use jobfire_core::{
     async_trait,
-    domain::job::{id::JobId, pending::PendingJob},
-    storage::{self, job::PendingJobRepo},
+    domain::{
+        job::{id::JobId, pending::PendingJob, running::RunningJob},
+        run::id::RunId,
+        worker::id::WorkerId,
+    },
+    storage::{self, job::PendingJobRepo, query::Filter},
 };
-use sqlx::SqlitePool;
+use sqlx::{Executor, Sqlite, SqlitePool};
 
-use crate::{SqliteStorageSettings, map_sqlx_error};
+use super::running::insert_running_job;
+use crate::{SqliteStorageSettings, map_sqlx_error, query::list_query};
 
+#[derive(Clone)]
 pub struct SqlitePendingJobRepo {
     pool: SqlitePool,
     settings: SqliteStorageSettings,
@@ -31,6 +37,24 @@ impl SqlitePendingJobRepo {
     }
 }
 
+#[derive(sqlx::FromRow)]
+struct Row {
+    job_id: String,
+    scheduled_at: i64,
+}
+
+impl Row {
+    fn into_pending_job(self) -> storage::error::Result<PendingJob> {
+        Ok(PendingJob::new(
+            self.job_id
+                .parse()
+                .map_err(|_| storage::error::Error::Internal)?,
+            DateTime::from_timestamp_millis(self.scheduled_at)
+                .ok_or(storage::error::Error::Internal)?,
+        ))
+    }
+}
+
 #[async_trait]
 impl PendingJobRepo for SqlitePendingJobRepo {
     async fn get(&self, job_id: &JobId) -> storage::error::Result<Option<PendingJob>> {
@@ -59,83 +83,152 @@ impl PendingJobRepo for SqlitePendingJobRepo {
     }
 
     async fn add(&self, job: PendingJob) -> storage::error::Result<()> {
-        let existing_job = self.get(&job.job_id()).await?;
-        if existing_job.is_some() {
-            return Err(storage::error::Error::AlreadyExists);
-        }
-
-        sqlx::query(&format!(
-            "INSERT INTO {} (job_id, scheduled_at) VALUES (?, ?)",
-            self.settings.pending_job_table_name,
-        ))
-        .bind(job.job_id().to_string())
-        .bind(job.scheduled_at().timestamp_millis())
-        .execute(&self.pool)
-        .await
-        .map_err(map_sqlx_error)?;
-
-        Ok(())
+        insert_pending_job(&self.pool, &self.settings, &job).await
     }
 
     async fn delete(&self, job_id: &JobId) -> storage::error::Result<PendingJob> {
-        let existing_job = self.get(job_id).await?;
-        if existing_job.is_none() {
-            return Err(storage::error::Error::NotFound);
-        }
-
-        sqlx::query(&format!(
-            "DELETE FROM {} WHERE job_id = ?",
-            self.settings.pending_job_table_name
-        ))
-        .bind(job_id.to_string())
-        .execute(&self.pool)
-        .await
-        .map_err(map_sqlx_error)?;
-
-        Ok(existing_job.unwrap())
+        delete_pending_job(&self.pool, &self.settings, job_id).await
     }
 
     async fn pop_scheduled(
         &self,
         now: DateTime<Utc>,
     ) -> storage::error::Result<Option<PendingJob>> {
-        #[derive(sqlx::FromRow)]
-        struct RGet {
-            job_id: String,
-            scheduled_at: i64,
-        }
+        pop_scheduled_pending_job(&self.pool, &self.settings, now).await
+    }
 
-        let timestamp = now.timestamp_millis();
+    async fn claim(
+        &self,
+        now: DateTime<Utc>,
+        worker_id: WorkerId,
+    ) -> storage::error::Result<Option<(PendingJob, RunningJob)>> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_error)?;
+
+        // dropping `tx` without committing puts the popped job back
+        let Some(pending_job) = pop_scheduled_pending_job(&mut *tx, &self.settings, now).await?
+        else {
+            return Ok(None);
+        };
+        let running_job =
+            RunningJob::new(pending_job.job_id(), RunId::default(), now, worker_id, now);
+        insert_running_job(&mut *tx, &self.settings, &running_job).await?;
 
-        let existing_job: Option<RGet> = sqlx::query_as(&format!(
-            "SELECT job_id, scheduled_at FROM {} WHERE scheduled_at < ? ORDER BY scheduled_at ASC LIMIT 1",
+        tx.commit().await.map_err(map_sqlx_error)?;
+        Ok(Some((pending_job, running_job)))
+    }
+
+    async fn next_scheduled_at(&self) -> storage::error::Result<Option<DateTime<Utc>>> {
+        let next_scheduled_at: Option<i64> = sqlx::query_scalar(&format!(
+            "SELECT MIN(scheduled_at) FROM {}",
             self.settings.pending_job_table_name
         ))
-        .bind(timestamp)
-        .fetch_optional(&self.pool)
+        .fetch_one(&self.pool)
         .await
         .map_err(map_sqlx_error)?;
 
-        let existing_job = match existing_job {
-            Some(job) => PendingJob::new(
-                job.job_id
-                    .parse()
-                    .map_err(|_| storage::error::Error::Internal)?,
-                DateTime::from_timestamp_millis(job.scheduled_at)
-                    .ok_or(storage::error::Error::Internal)?,
+        next_scheduled_at
+            .map(|timestamp| {
+                DateTime::from_timestamp_millis(timestamp).ok_or(storage::error::Error::Internal)
+            })
+            .transpose()
+    }
+
+    async fn list(&self, filter: &Filter) -> storage::error::Result<Vec<PendingJob>> {
+        let rows = list_query(
+            &format!(
+                "SELECT job_id, scheduled_at FROM {}",
+                self.settings.pending_job_table_name
             ),
-            None => return Ok(None),
-        };
+            &self.settings,
+            filter,
+            None,
+            "job_id",
+            "scheduled_at",
+            "scheduled_at, job_id",
+        )
+        .build_query_as::<Row>()
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        rows.into_iter().map(Row::into_pending_job).collect()
+    }
+}
+
+/// Removes and returns the earliest pending job scheduled before `now`.
+async fn pop_scheduled_pending_job<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    now: DateTime<Utc>,
+) -> storage::error::Result<Option<PendingJob>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    // selecting and deleting in one statement, so concurrent poppers never claim the same job
+    let popped: Option<Row> = sqlx::query_as(&format!(
+        "DELETE FROM {0} WHERE job_id = (SELECT job_id FROM {0} WHERE scheduled_at < ? ORDER BY scheduled_at ASC LIMIT 1) RETURNING job_id, scheduled_at",
+        settings.pending_job_table_name
+    ))
+    .bind(now.timestamp_millis())
+    .fetch_optional(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    popped.map(Row::into_pending_job).transpose()
+}
 
-        self.delete(&existing_job.job_id()).await?;
+/// Inserts a pending job, the primary key rejects duplicates, also when added concurrently.
+pub(crate) async fn insert_pending_job<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job: &PendingJob,
+) -> storage::error::Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(&format!(
+        "INSERT INTO {} (job_id, scheduled_at) VALUES (?, ?)",
+        settings.pending_job_table_name,
+    ))
+    .bind(job.job_id().to_string())
+    .bind(job.scheduled_at().timestamp_millis())
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    Ok(())
+}
 
-        Ok(Some(existing_job))
+pub(crate) async fn delete_pending_job<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job_id: &JobId,
+) -> storage::error::Result<PendingJob>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let deleted: Option<Row> = sqlx::query_as(&format!(
+        "DELETE FROM {} WHERE job_id = ? RETURNING job_id, scheduled_at",
+        settings.pending_job_table_name
+    ))
+    .bind(job_id.to_string())
+    .fetch_optional(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    match deleted {
+        Some(deleted) => deleted.into_pending_job(),
+        None => Err(storage::error::Error::NotFound),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
+    use crate::job::running::SqliteRunningJobRepo;
+    use jobfire_core::storage::job::RunningJobRepo;
 
     #[tokio::test]
     async fn test_init() {
@@ -243,6 +336,66 @@ mod tests {
         assert!(matches!(result, Err(storage::error::Error::NotFound)));
     }
 
+    #[tokio::test]
+    async fn test_claim() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let settings = SqliteStorageSettings::default();
+        let repo = SqlitePendingJobRepo::new(pool.clone(), settings.clone())
+            .await
+            .unwrap();
+        let running_repo = SqliteRunningJobRepo::new(pool, settings).await.unwrap();
+
+        let job = PendingJob::new(
+            JobId::default(),
+            DateTime::from_timestamp_millis(100).unwrap(),
+        );
+        repo.add(job.clone()).await.unwrap();
+        let now = DateTime::from_timestamp_millis(150).unwrap();
+        let worker_id = WorkerId::default();
+
+        let (claimed, running_job) = repo.claim(now, worker_id).await.unwrap().unwrap();
+        assert_eq!(claimed.job_id(), job.job_id());
+        assert_eq!(running_job.worker_id(), worker_id);
+        assert_eq!(running_job.started_at(), now);
+
+        assert!(repo.get(&job.job_id()).await.unwrap().is_none());
+        let running = running_repo.get(&job.job_id()).await.unwrap().unwrap();
+        assert_eq!(running.run_id(), running_job.run_id());
+        assert!(repo.claim(now, worker_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_claim_already_running() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let settings = SqliteStorageSettings::default();
+        let repo = SqlitePendingJobRepo::new(pool.clone(), settings.clone())
+            .await
+            .unwrap();
+        let running_repo = SqliteRunningJobRepo::new(pool, settings).await.unwrap();
+
+        let job = PendingJob::new(
+            JobId::default(),
+            DateTime::from_timestamp_millis(100).unwrap(),
+        );
+        repo.add(job.clone()).await.unwrap();
+        let now = DateTime::from_timestamp_millis(150).unwrap();
+        running_repo
+            .add(RunningJob::new(
+                job.job_id(),
+                RunId::default(),
+                now,
+                WorkerId::default(),
+                now,
+            ))
+            .await
+            .unwrap();
+
+        let result = repo.claim(now, WorkerId::default()).await;
+
+        assert!(matches!(result, Err(storage::error::Error::AlreadyExists)));
+        assert!(repo.get(&job.job_id()).await.unwrap().is_some());
+    }
+
     #[tokio::test]
     async fn test_pop_scheduled_no_jobs() {
         let pool = SqlitePool::connect(":memory:").await.unwrap();
@@ -327,4 +480,156 @@ mod tests {
         let popped3 = repo.pop_scheduled(after).await.unwrap();
         assert!(popped3.is_none());
     }
+
+    #[tokio::test]
+    async fn test_next_scheduled_at() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let settings = SqliteStorageSettings::default();
+        let repo = SqlitePendingJobRepo::new(pool, settings).await.unwrap();
+
+        assert!(repo.next_scheduled_at().await.unwrap().is_none());
+
+        repo.add(PendingJob::new(
+            JobId::default(),
+            DateTime::from_timestamp_millis(200).unwrap(),
+        ))
+        .await
+        .unwrap();
+        repo.add(PendingJob::new(
+            JobId::default(),
+            DateTime::from_timestamp_millis(100).unwrap(),
+        ))
+        .await
+        .unwrap();
+
+        let next_scheduled_at = repo.next_scheduled_at().await.unwrap();
+        assert_eq!(
+            next_scheduled_at,
+            Some(DateTime::from_timestamp_millis(100).unwrap())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_pop_scheduled_concurrently() {
+        const JOBS: usize = 200;
+        const POPPERS: usize = 8;
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}?mode=rwc", dir.path().join("jobs.db").display());
+        let settings = SqliteStorageSettings::default();
+
+        let repo =
+            SqlitePendingJobRepo::new(SqlitePool::connect(&url).await.unwrap(), settings.clone())
+                .await
+                .unwrap();
+        for _ in 0..JOBS {
+            repo.add(PendingJob::new(
+                JobId::default(),
+                DateTime::from_timestamp_millis(1).unwrap(),
+            ))
+            .await
+            .unwrap();
+        }
+
+        // every popper has its own pool, like separate processes sharing the file
+        let mut poppers = Vec::new();
+        for _ in 0..POPPERS {
+            let repo = SqlitePendingJobRepo::new(
+                SqlitePool::connect(&url).await.unwrap(),
+                settings.clone(),
+            )
+            .await
+            .unwrap();
+            poppers.push(tokio::spawn(async move {
+                let mut popped = Vec::new();
+                while let Some(job) = repo.pop_scheduled(Utc::now()).await.unwrap() {
+                    popped.push(job.job_id());
+                }
+                popped
+            }));
+        }
+
+        let mut popped = Vec::new();
+        for popper in poppers {
+            popped.extend(popper.await.unwrap());
+        }
+
+        assert_eq!(popped.len(), JOBS);
+        assert_eq!(popped.iter().collect::<HashSet<_>>().len(), JOBS);
+        assert!(repo.next_scheduled_at().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_claim_concurrently() {
+        const JOBS: usize = 200;
+        const CLAIMERS: usize = 8;
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}?mode=rwc", dir.path().join("jobs.db").display());
+        let settings = SqliteStorageSettings::default();
+
+        let pool = SqlitePool::connect(&url).await.unwrap();
+        let repo = SqlitePendingJobRepo::new(pool.clone(), settings.clone())
+            .await
+            .unwrap();
+        let running_repo = SqliteRunningJobRepo::new(pool, settings.clone())
+            .await
+            .unwrap();
+        for _ in 0..JOBS {
+            repo.add(PendingJob::new(
+                JobId::default(),
+                DateTime::from_timestamp_millis(1).unwrap(),
+            ))
+            .await
+            .unwrap();
+        }
+
+        // every claimer has its own pool, like separate processes sharing the file
+        let mut claimers = Vec::new();
+        for _ in 0..CLAIMERS {
+            let repo = SqlitePendingJobRepo::new(
+                SqlitePool::connect(&url).await.unwrap(),
+                settings.clone(),
+            )
+            .await
+            .unwrap();
+            let worker_id = WorkerId::default();
+            claimers.push(tokio::spawn(async move {
+                let mut claimed = Vec::new();
+                while let Some((pending_job, running_job)) =
+                    repo.claim(Utc::now(), worker_id).await.unwrap()
+                {
+                    assert_eq!(pending_job.job_id(), running_job.job_id());
+                    claimed.push(running_job);
+                }
+                claimed
+            }));
+        }
+
+        let mut claimed = Vec::new();
+        for claimer in claimers {
+            claimed.extend(claimer.await.unwrap());
+        }
+
+        assert_eq!(claimed.len(), JOBS);
+        assert_eq!(
+            claimed
+                .iter()
+                .map(|running_job| running_job.job_id())
+                .collect::<HashSet<_>>()
+                .len(),
+            JOBS
+        );
+        assert!(repo.next_scheduled_at().await.unwrap().is_none());
+        let running_jobs = running_repo.get_all().await.unwrap();
+        assert_eq!(running_jobs.len(), JOBS);
+        for running_job in running_jobs {
+            let claim = claimed
+                .iter()
+                .find(|claimed| claimed.job_id() == running_job.job_id())
+                .unwrap();
+            assert_eq!(running_job.run_id(), claim.run_id());
+            assert_eq!(running_job.worker_id(), claim.worker_id());
+        }
+    }
 }
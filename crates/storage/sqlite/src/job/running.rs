@@ -1,13 +1,18 @@
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use jobfire_core::{
     async_trait,
-    domain::job::{id::JobId, running::RunningJob},
+    domain::{
+        job::{id::JobId, running::RunningJob},
+        run::id::RunId,
+        worker::id::WorkerId,
+    },
     storage::{self, job::RunningJobRepo},
 };
-use sqlx::SqlitePool;
+use sqlx::{Executor, Sqlite, SqlitePool};
 
-use crate::{SqliteStorageSettings, map_sqlx_error};
+use crate::{SqliteStorageSettings, add_column_if_missing, map_sqlx_error};
 
+#[derive(Clone)]
 pub struct SqliteRunningJobRepo {
     pool: SqlitePool,
     settings: SqliteStorageSettings,
@@ -27,21 +32,60 @@ impl SqliteRunningJobRepo {
         .execute(pool)
         .await?;
 
+        // rows created before leases have no owner and an expired lease, so they get recovered
+        add_column_if_missing(
+            pool,
+            &settings.running_job_table_name,
+            "worker_id",
+            "TEXT NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000'",
+        )
+        .await?;
+        add_column_if_missing(
+            pool,
+            &settings.running_job_table_name,
+            "heartbeat_at",
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+
         Ok(())
     }
 }
 
+#[derive(sqlx::FromRow)]
+struct Row {
+    job_id: String,
+    run_id: String,
+    started_at: i64,
+    worker_id: String,
+    heartbeat_at: i64,
+}
+
+impl Row {
+    fn into_running_job(self) -> storage::error::Result<RunningJob> {
+        Ok(RunningJob::new(
+            self.job_id
+                .parse()
+                .map_err(|_| storage::error::Error::Internal)?,
+            self.run_id
+                .parse()
+                .map_err(|_| storage::error::Error::Internal)?,
+            DateTime::from_timestamp_millis(self.started_at)
+                .ok_or(storage::error::Error::Internal)?,
+            self.worker_id
+                .parse()
+                .map_err(|_| storage::error::Error::Internal)?,
+            DateTime::from_timestamp_millis(self.heartbeat_at)
+                .ok_or(storage::error::Error::Internal)?,
+        ))
+    }
+}
+
 #[async_trait]
 impl RunningJobRepo for SqliteRunningJobRepo {
     async fn get(&self, job_id: &JobId) -> storage::error::Result<Option<RunningJob>> {
-        #[derive(sqlx::FromRow)]
-        struct RGet {
-            run_id: String,
-            started_at: i64,
-        }
-
-        let result: Option<RGet> = sqlx::query_as(&format!(
-            "SELECT run_id, started_at FROM {} WHERE job_id = ?",
+        let result = sqlx::query_as::<_, Row>(&format!(
+            "SELECT job_id, run_id, started_at, worker_id, heartbeat_at FROM {} WHERE job_id = ?",
             self.settings.running_job_table_name,
         ))
         .bind(job_id.to_string())
@@ -49,65 +93,173 @@ impl RunningJobRepo for SqliteRunningJobRepo {
         .await
         .map_err(map_sqlx_error)?;
 
-        match result {
-            Some(result) => Ok(Some(RunningJob::new(
-                *job_id,
-                result
-                    .run_id
-                    .parse()
-                    .map_err(|_| storage::error::Error::Internal)?,
-                DateTime::from_timestamp_millis(result.started_at)
-                    .ok_or(storage::error::Error::Internal)?,
-            ))),
-            None => Ok(None),
-        }
+        result.map(Row::into_running_job).transpose()
     }
 
     async fn add(&self, job: RunningJob) -> storage::error::Result<()> {
-        let existing_job = self.get(&job.job_id()).await?;
-        if existing_job.is_some() {
-            return Err(storage::error::Error::AlreadyExists);
-        }
+        insert_running_job(&self.pool, &self.settings, &job).await
+    }
 
-        sqlx::query(&format!(
-            "INSERT INTO {} (job_id, run_id, started_at) VALUES (?, ?, ?)",
+    async fn delete(&self, job_id: &JobId) -> storage::error::Result<RunningJob> {
+        delete_running_job(&self.pool, &self.settings, job_id).await
+    }
+
+    async fn get_all(&self) -> storage::error::Result<Vec<RunningJob>> {
+        let results = sqlx::query_as::<_, Row>(&format!(
+            "SELECT job_id, run_id, started_at, worker_id, heartbeat_at FROM {}",
             self.settings.running_job_table_name,
         ))
-        .bind(job.job_id().to_string())
-        .bind(job.run_id().to_string())
-        .bind(job.started_at().timestamp_millis())
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_error)?;
 
-        Ok(())
+        results.into_iter().map(Row::into_running_job).collect()
     }
 
-    async fn delete(&self, job_id: &JobId) -> storage::error::Result<RunningJob> {
-        let existing_job = self.get(job_id).await?;
-        if existing_job.is_none() {
-            return Err(storage::error::Error::NotFound);
-        }
-
+    async fn heartbeat(
+        &self,
+        worker_id: &WorkerId,
+        now: DateTime<Utc>,
+    ) -> storage::error::Result<()> {
         sqlx::query(&format!(
-            "DELETE FROM {} WHERE job_id = ?",
+            "UPDATE {} SET heartbeat_at = ? WHERE worker_id = ?",
             self.settings.running_job_table_name
         ))
-        .bind(job_id.to_string())
+        .bind(now.timestamp_millis())
+        .bind(worker_id.to_string())
         .execute(&self.pool)
         .await
         .map_err(map_sqlx_error)?;
 
-        Ok(existing_job.unwrap())
+        Ok(())
     }
+
+    async fn delete_expired(
+        &self,
+        job_id: &JobId,
+        heartbeat_before: DateTime<Utc>,
+    ) -> storage::error::Result<Option<RunningJob>> {
+        delete_expired_running_job(&self.pool, &self.settings, job_id, heartbeat_before).await
+    }
+}
+
+pub(crate) async fn delete_running_job<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job_id: &JobId,
+) -> storage::error::Result<RunningJob>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let deleted = sqlx::query_as::<_, Row>(&format!(
+        "DELETE FROM {} WHERE job_id = ? RETURNING job_id, run_id, started_at, worker_id, heartbeat_at",
+        settings.running_job_table_name
+    ))
+    .bind(job_id.to_string())
+    .fetch_optional(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    match deleted {
+        Some(deleted) => deleted.into_running_job(),
+        None => Err(storage::error::Error::NotFound),
+    }
+}
+
+pub(crate) async fn insert_running_job<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job: &RunningJob,
+) -> storage::error::Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    // the primary key rejects duplicates, so a job is moved to running at most once
+    sqlx::query(&format!(
+        "INSERT INTO {} (job_id, run_id, started_at, worker_id, heartbeat_at) VALUES (?, ?, ?, ?, ?)",
+        settings.running_job_table_name,
+    ))
+    .bind(job.job_id().to_string())
+    .bind(job.run_id().to_string())
+    .bind(job.started_at().timestamp_millis())
+    .bind(job.worker_id().to_string())
+    .bind(job.heartbeat_at().timestamp_millis())
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    Ok(())
+}
+
+/// Deletes the running job only if it is still running `run_id`.
+pub(crate) async fn delete_running_job_of_run<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job_id: &JobId,
+    run_id: &RunId,
+) -> storage::error::Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let result = sqlx::query(&format!(
+        "DELETE FROM {} WHERE job_id = ? AND run_id = ?",
+        settings.running_job_table_name
+    ))
+    .bind(job_id.to_string())
+    .bind(run_id.to_string())
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    match result.rows_affected() {
+        0 => Err(storage::error::Error::NotFound),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) async fn delete_expired_running_job<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    job_id: &JobId,
+    heartbeat_before: DateTime<Utc>,
+) -> storage::error::Result<Option<RunningJob>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let deleted = sqlx::query_as::<_, Row>(&format!(
+        "DELETE FROM {} WHERE job_id = ? AND heartbeat_at < ? RETURNING job_id, run_id, started_at, worker_id, heartbeat_at",
+        settings.running_job_table_name
+    ))
+    .bind(job_id.to_string())
+    .bind(heartbeat_before.timestamp_millis())
+    .fetch_optional(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    deleted.map(Row::into_running_job).transpose()
 }
 
 #[cfg(test)]
 mod tests {
-    use jobfire_core::domain::run::id::RunId;
-
     use super::*;
 
+    fn running_job(worker_id: WorkerId, heartbeat_at: i64) -> RunningJob {
+        RunningJob::new(
+            JobId::default(),
+            RunId::default(),
+            DateTime::from_timestamp_millis(1).unwrap(),
+            worker_id,
+            DateTime::from_timestamp_millis(heartbeat_at).unwrap(),
+        )
+    }
+
+    async fn repo() -> SqliteRunningJobRepo {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        SqliteRunningJobRepo::new(pool, SqliteStorageSettings::default())
+            .await
+            .unwrap()
+    }
+
     #[tokio::test]
     async fn test_init() {
         let pool = SqlitePool::connect(":memory:").await.unwrap();
@@ -122,11 +274,7 @@ mod tests {
         let settings = SqliteStorageSettings::default();
         let repo = SqliteRunningJobRepo::new(pool, settings).await.unwrap();
 
-        let job = RunningJob::new(
-            JobId::default(),
-            RunId::default(),
-            DateTime::from_timestamp_millis(1).unwrap(),
-        );
+        let job = running_job(WorkerId::default(), 1);
 
         repo.add(job.clone()).await.unwrap();
 
@@ -142,11 +290,7 @@ mod tests {
         let settings = SqliteStorageSettings::default();
         let repo = SqliteRunningJobRepo::new(pool, settings).await.unwrap();
 
-        let job = RunningJob::new(
-            JobId::default(),
-            RunId::default(),
-            DateTime::from_timestamp_millis(1).unwrap(),
-        );
+        let job = running_job(WorkerId::default(), 1);
 
         repo.add(job.clone()).await.unwrap();
 
@@ -160,11 +304,7 @@ mod tests {
         let settings = SqliteStorageSettings::default();
         let repo = SqliteRunningJobRepo::new(pool, settings).await.unwrap();
 
-        let job = RunningJob::new(
-            JobId::default(),
-            RunId::default(),
-            DateTime::from_timestamp_millis(1).unwrap(),
-        );
+        let job = running_job(WorkerId::default(), 1);
 
         repo.add(job.clone()).await.unwrap();
 
@@ -192,11 +332,7 @@ mod tests {
         let settings = SqliteStorageSettings::default();
         let repo = SqliteRunningJobRepo::new(pool, settings).await.unwrap();
 
-        let job = RunningJob::new(
-            JobId::default(),
-            RunId::default(),
-            DateTime::from_timestamp_millis(1).unwrap(),
-        );
+        let job = running_job(WorkerId::default(), 1);
 
         repo.add(job.clone()).await.unwrap();
 
@@ -220,4 +356,60 @@ mod tests {
         let result = repo.delete(&job_id).await;
         assert!(matches!(result, Err(storage::error::Error::NotFound)));
     }
+
+    #[tokio::test]
+    async fn test_get_all() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let settings = SqliteStorageSettings::default();
+        let repo = SqliteRunningJobRepo::new(pool, settings).await.unwrap();
+
+        let job = running_job(WorkerId::default(), 1);
+        repo.add(job.clone()).await.unwrap();
+
+        let all = repo.get_all().await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].job_id(), job.job_id());
+        assert_eq!(all[0].run_id(), job.run_id());
+        assert_eq!(all[0].started_at(), job.started_at());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat() {
+        let repo = repo().await;
+        let worker_id = WorkerId::default();
+        let owned = running_job(worker_id, 1);
+        let other = running_job(WorkerId::default(), 1);
+        repo.add(owned.clone()).await.unwrap();
+        repo.add(other.clone()).await.unwrap();
+
+        let now = DateTime::from_timestamp_millis(100).unwrap();
+        repo.heartbeat(&worker_id, now).await.unwrap();
+
+        let owned = repo.get(&owned.job_id()).await.unwrap().unwrap();
+        let other = repo.get(&other.job_id()).await.unwrap().unwrap();
+        assert_eq!(owned.worker_id(), worker_id);
+        assert_eq!(owned.heartbeat_at(), now);
+        assert_eq!(other.heartbeat_at().timestamp_millis(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired() {
+        let repo = repo().await;
+        let job = running_job(WorkerId::default(), 10);
+        repo.add(job.clone()).await.unwrap();
+
+        let not_expired = repo
+            .delete_expired(&job.job_id(), DateTime::from_timestamp_millis(10).unwrap())
+            .await
+            .unwrap();
+        assert!(not_expired.is_none());
+
+        let expired = repo
+            .delete_expired(&job.job_id(), DateTime::from_timestamp_millis(11).unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(expired.run_id(), job.run_id());
+        assert!(repo.get(&job.job_id()).await.unwrap().is_none());
+    }
 }
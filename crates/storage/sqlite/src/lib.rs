@@ -1,11 +1,20 @@
+use chrono::{DateTime, Utc};
 use job::{SqliteJobRepo, pending::SqlitePendingJobRepo, running::SqliteRunningJobRepo};
-use jobfire_core::storage::{self, Storage};
+use jobfire_core::{
+    domain::job::{Job, id::JobId, pending::PendingJob},
+    storage::{self, Storage, transaction::Transaction},
+};
+use rate_limit::SqliteRateLimitStore;
 use run::{failed::SqliteFailedRunRepo, successful::SqliteSuccessfulRunRepo};
-use sqlx::SqlitePool;
+use sqlx::{Sqlite, SqlitePool};
 use thiserror::Error;
+use transaction::SqliteTransactionRepo;
 
 pub mod job;
+mod query;
+pub mod rate_limit;
 pub mod run;
+pub mod transaction;
 
 #[derive(Error, Debug)]
 #[error("failed to initialize storage: ")]
@@ -20,6 +29,7 @@ pub struct SqliteStorageSettings {
     pub(crate) running_job_table_name: String,
     pub(crate) successful_run_table_name: String,
     pub(crate) failed_run_table_name: String,
+    pub(crate) rate_limit_table_name: String,
 }
 
 impl Default for SqliteStorageSettings {
@@ -48,16 +58,27 @@ impl SqliteStorageSettings {
             running_job_table_name: running_job_table_name.to_owned(),
             successful_run_table_name: successful_run_table_name.to_owned(),
             failed_run_table_name: failed_run_table_name.to_owned(),
+            rate_limit_table_name: "jobfire_rate_limit".to_owned(),
         }
     }
+
+    /// Sets the table of `SqliteRateLimitStore`, defaults to `jobfire_rate_limit`.
+    pub fn with_rate_limit_table_name(mut self, rate_limit_table_name: &str) -> Self {
+        self.rate_limit_table_name = rate_limit_table_name.to_owned();
+        self
+    }
 }
 
+#[derive(Clone)]
 pub struct SqliteStorage {
+    pool: SqlitePool,
+    settings: SqliteStorageSettings,
     job_repo: SqliteJobRepo,
     pending_job_repo: SqlitePendingJobRepo,
     running_job_repo: SqliteRunningJobRepo,
     successful_run_repo: SqliteSuccessfulRunRepo,
     failed_run_repo: SqliteFailedRunRepo,
+    transaction_repo: SqliteTransactionRepo,
 }
 
 impl SqliteStorage {
@@ -69,19 +90,58 @@ impl SqliteStorage {
         let successful_run_repo =
             SqliteSuccessfulRunRepo::new(pool.clone(), settings.clone()).await?;
         let failed_run_repo = SqliteFailedRunRepo::new(pool.clone(), settings.clone()).await?;
+        let transaction_repo = SqliteTransactionRepo::new(pool.clone(), settings.clone());
 
         Ok(SqliteStorage {
+            pool,
+            settings,
             job_repo,
             pending_job_repo,
             running_job_repo,
             successful_run_repo,
             failed_run_repo,
+            transaction_repo,
         })
     }
 
     pub async fn new_in_memory() -> Self {
         Self::new(":memory:", Default::default()).await.unwrap()
     }
+
+    /// Pool the storage is connected with, e.g. to begin transactions passed to
+    /// `schedule_in_transaction`.
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Creates a store keeping rate limits in the database of the storage, to be passed
+    /// to `RateLimiter::new`.
+    pub async fn rate_limit_store(&self) -> Result<SqliteRateLimitStore> {
+        SqliteRateLimitStore::new(self.pool.clone(), self.settings.clone()).await
+    }
+
+    /// Schedules a job within a transaction of the application, so the job exists
+    /// if and only if the transaction commits.
+    ///
+    /// Workers aren't notified about the job, they pick it up within their poll rate
+    /// once the transaction commits.
+    pub async fn schedule_in_transaction(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        job: Job,
+        scheduled_at: DateTime<Utc>,
+    ) -> storage::error::Result<JobId> {
+        let job_id = job.id();
+        let transaction = Transaction::default()
+            .add_job(job)
+            .add_pending_job(PendingJob::new(job_id, scheduled_at));
+
+        for operation in transaction.operations() {
+            transaction::apply(tx, &self.settings, operation).await?;
+        }
+
+        Ok(job_id)
+    }
 }
 
 impl From<SqliteStorage> for Storage {
@@ -92,12 +152,126 @@ impl From<SqliteStorage> for Storage {
             Box::new(value.running_job_repo),
             Box::new(value.successful_run_repo),
             Box::new(value.failed_run_repo),
+            Box::new(value.transaction_repo),
         )
     }
 }
 
+/// Adds a column to an existing table, so tables created by older versions get upgraded.
+pub(crate) async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table_name: &str,
+    column_name: &str,
+    definition: &str,
+) -> Result<()> {
+    let (count,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table_name)
+            .bind(column_name)
+            .fetch_one(pool)
+            .await?;
+
+    if count == 0 {
+        sqlx::query(&format!(
+            "ALTER TABLE {table_name} ADD COLUMN {column_name} {definition}"
+        ))
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
 pub(crate) fn map_sqlx_error(error: sqlx::Error) -> storage::error::Error {
-    storage::error::Error::Custom {
-        message: error.to_string(),
+    match error {
+        sqlx::Error::Database(error) if error.is_unique_violation() => {
+            storage::error::Error::AlreadyExists
+        }
+        error => storage::error::Error::Custom {
+            message: error.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jobfire_core::domain::job::{
+        r#impl::{JobImplName, SerializedJobImpl},
+        policy::{Policies, PolicyData},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn job() -> Job {
+        Job::new(
+            JobId::default(),
+            DateTime::from_timestamp_millis(1).unwrap(),
+            SerializedJobImpl::new(JobImplName::new("test"), json!(null)),
+            Policies::new(Vec::new(), PolicyData::default()),
+        )
+    }
+
+    async fn storage() -> SqliteStorage {
+        let storage = SqliteStorage::new_in_memory().await;
+        sqlx::query("CREATE TABLE orders (id INTEGER PRIMARY KEY)")
+            .execute(storage.pool())
+            .await
+            .unwrap();
+        storage
+    }
+
+    async fn exists(storage: &SqliteStorage, job_id: &JobId) -> (bool, bool) {
+        let storage = Storage::from(storage.clone());
+        (
+            storage.job_repo().get(job_id).await.unwrap().is_some(),
+            storage
+                .pending_job_repo()
+                .get(job_id)
+                .await
+                .unwrap()
+                .is_some(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_schedule_in_committed_transaction() {
+        let storage = storage().await;
+        let mut tx = storage.pool().begin().await.unwrap();
+
+        sqlx::query("INSERT INTO orders (id) VALUES (1)")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        let job_id = storage
+            .schedule_in_transaction(&mut tx, job(), Utc::now())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        assert_eq!(exists(&storage, &job_id).await, (true, true));
+    }
+
+    #[tokio::test]
+    async fn test_schedule_in_rolled_back_transaction() {
+        let storage = storage().await;
+        let mut tx = storage.pool().begin().await.unwrap();
+
+        sqlx::query("INSERT INTO orders (id) VALUES (1)")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        let job_id = storage
+            .schedule_in_transaction(&mut tx, job(), Utc::now())
+            .await
+            .unwrap();
+        tx.rollback().await.unwrap();
+
+        assert_eq!(exists(&storage, &job_id).await, (false, false));
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM orders")
+            .fetch_one(storage.pool())
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
     }
 }
@@ -0,0 +1,220 @@
+use jobfire_core::storage::query::{Filter, JobState};
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::SqliteStorageSettings;
+
+/// Extends `select` with the conditions, order and page of a filter.
+///
+/// `job_id_column` and `time_column` are the columns matched against the job id and
+/// the time range of the filter, rows are ordered by `order_by` and paginated.
+/// `state` is only passed when listing jobs, see `Filter`.
+pub(crate) fn list_query<'a>(
+    select: &str,
+    settings: &SqliteStorageSettings,
+    filter: &'a Filter,
+    state: Option<JobState>,
+    job_id_column: &str,
+    time_column: &str,
+    order_by: &str,
+) -> QueryBuilder<'a, Sqlite> {
+    let mut builder = QueryBuilder::new(format!("{select} WHERE 1 = 1"));
+
+    if let Some(job_id) = filter.job_id() {
+        builder
+            .push(format!(" AND {job_id_column} = "))
+            .push_bind(job_id.to_string());
+    }
+    if let Some(impl_name) = filter.impl_name() {
+        builder
+            .push(format!(
+                " AND {job_id_column} IN (SELECT id FROM {} WHERE json_extract(impl, '$.inner.name') = ",
+                settings.job_table_name
+            ))
+            .push_bind(impl_name.to_string())
+            .push(")");
+    }
+    if let Some(state) = state {
+        let pending = format!(
+            "{job_id_column} IN (SELECT job_id FROM {})",
+            settings.pending_job_table_name
+        );
+        let running = format!(
+            "{job_id_column} IN (SELECT job_id FROM {})",
+            settings.running_job_table_name
+        );
+        match state {
+            JobState::Pending => builder.push(format!(" AND {pending} AND NOT {running}")),
+            JobState::Running => builder.push(format!(" AND {running}")),
+            JobState::Finished => builder.push(format!(" AND NOT {pending} AND NOT {running}")),
+        };
+    }
+    if let Some(from) = filter.from() {
+        builder
+            .push(format!(" AND {time_column} >= "))
+            .push_bind(from.timestamp_millis());
+    }
+    if let Some(to) = filter.to() {
+        builder
+            .push(format!(" AND {time_column} < "))
+            .push_bind(to.timestamp_millis());
+    }
+
+    builder
+        .push(format!(" ORDER BY {order_by} LIMIT "))
+        .push_bind(filter.limit() as i64)
+        .push(" OFFSET ")
+        .push_bind(filter.offset() as i64);
+
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use jobfire_core::{
+        domain::{
+            job::{
+                Job,
+                id::JobId,
+                r#impl::{JobImplName, SerializedJobImpl},
+                pending::PendingJob,
+                policy::{Policies, PolicyData},
+                report::Report,
+                running::RunningJob,
+            },
+            run::{id::RunId, successful::SuccessfulRun},
+            worker::id::WorkerId,
+        },
+        storage::Storage,
+    };
+    use serde_json::json;
+
+    use crate::SqliteStorage;
+
+    use super::*;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    fn job(name: &str, created_at: i64) -> Job {
+        Job::new(
+            JobId::default(),
+            at(created_at),
+            SerializedJobImpl::new(JobImplName::new(name), json!(null)),
+            Policies::new(Vec::new(), PolicyData::default()),
+        )
+    }
+
+    fn ids(jobs: &[Job]) -> Vec<JobId> {
+        jobs.iter().map(|job| job.id()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs() {
+        let storage = Storage::from(SqliteStorage::new_in_memory().await);
+        let pending = job("test", 3);
+        let running = job("test", 2);
+        let finished = job("test", 1);
+        let other = job("other", 4);
+        for job in [&pending, &running, &finished, &other] {
+            storage.job_repo().add(job.clone()).await.unwrap();
+        }
+        storage
+            .pending_job_repo()
+            .add(PendingJob::new(pending.id(), at(5)))
+            .await
+            .unwrap();
+        storage
+            .running_job_repo()
+            .add(RunningJob::new(
+                running.id(),
+                RunId::default(),
+                at(5),
+                WorkerId::default(),
+                at(5),
+            ))
+            .await
+            .unwrap();
+        let repo = storage.job_repo();
+
+        let all = repo.list(&Filter::default()).await.unwrap();
+        assert_eq!(
+            ids(&all),
+            vec![finished.id(), running.id(), pending.id(), other.id()]
+        );
+
+        let by_impl = Filter::default().with_impl_name(JobImplName::new("test"));
+        let page = repo
+            .list(&by_impl.clone().with_offset(1).with_limit(1))
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec![running.id()]);
+
+        for (state, expected) in [
+            (JobState::Pending, pending.id()),
+            (JobState::Running, running.id()),
+            (JobState::Finished, finished.id()),
+        ] {
+            let jobs = repo.list(&by_impl.clone().with_state(state)).await.unwrap();
+            assert_eq!(ids(&jobs), vec![expected]);
+        }
+
+        let in_range = repo
+            .list(&Filter::default().with_from(at(2)).with_to(at(4)))
+            .await
+            .unwrap();
+        assert_eq!(ids(&in_range), vec![running.id(), pending.id()]);
+
+        let by_id = repo
+            .list(&Filter::default().with_job_id(other.id()))
+            .await
+            .unwrap();
+        assert_eq!(ids(&by_id), vec![other.id()]);
+    }
+
+    #[tokio::test]
+    async fn test_list_successful_runs() {
+        let storage = Storage::from(SqliteStorage::new_in_memory().await);
+        let tested = job("test", 1);
+        let other = job("other", 1);
+        storage.job_repo().add(tested.clone()).await.unwrap();
+        storage.job_repo().add(other.clone()).await.unwrap();
+        let mut run_ids = Vec::new();
+        for (job_id, finished_at) in [(tested.id(), 1), (other.id(), 2), (tested.id(), 3)] {
+            let run_id = RunId::default();
+            storage
+                .successful_run_repo()
+                .add(SuccessfulRun::new(
+                    run_id,
+                    job_id,
+                    at(0),
+                    at(finished_at),
+                    Report::default(),
+                ))
+                .await
+                .unwrap();
+            run_ids.push(run_id);
+        }
+
+        let runs = storage
+            .successful_run_repo()
+            .list(&Filter::default().with_impl_name(JobImplName::new("test")))
+            .await
+            .unwrap()
+            .iter()
+            .map(|run| run.run_id())
+            .collect::<Vec<_>>();
+        assert_eq!(runs, vec![run_ids[2], run_ids[0]]);
+
+        let runs = storage
+            .successful_run_repo()
+            .list(&Filter::default().with_from(at(2)).with_limit(1))
+            .await
+            .unwrap()
+            .iter()
+            .map(|run| run.run_id())
+            .collect::<Vec<_>>();
+        assert_eq!(runs, vec![run_ids[2]]);
+    }
+}
@@ -0,0 +1,148 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use jobfire_core::{
+    services::rate_limit::{Permit, Rate, RateLimitStore},
+    storage,
+};
+use sqlx::SqlitePool;
+
+use crate::{SqliteStorageSettings, map_sqlx_error};
+
+/// Keeps token buckets in sqlite, so processes sharing the database share a budget.
+#[derive(Clone)]
+pub struct SqliteRateLimitStore {
+    pool: SqlitePool,
+    settings: SqliteStorageSettings,
+}
+
+impl SqliteRateLimitStore {
+    pub async fn new(pool: SqlitePool, settings: SqliteStorageSettings) -> crate::Result<Self> {
+        Self::init(&pool, &settings).await?;
+        Ok(Self { pool, settings })
+    }
+
+    async fn init(pool: &SqlitePool, settings: &SqliteStorageSettings) -> crate::Result<()> {
+        sqlx::query(&format!(
+            "
+CREATE TABLE IF NOT EXISTS {} (
+    key TEXT NOT NULL PRIMARY KEY,
+    tokens REAL NOT NULL,
+    updated_at INTEGER NOT NULL,
+    granted INTEGER NOT NULL
+)
+",
+            settings.rate_limit_table_name,
+        ))
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for SqliteRateLimitStore {
+    async fn try_acquire(
+        &self,
+        key: &str,
+        rate: Rate,
+        now: DateTime<Utc>,
+    ) -> storage::error::Result<Permit> {
+        // refilled and taken from in a single statement, so concurrent processes can't
+        // both take the last token
+        let refilled = "MIN(?2, tokens + MAX(0, ?3 - updated_at) * ?4)";
+        let (tokens, granted): (f64, bool) = sqlx::query_as(&format!(
+            "
+INSERT INTO {} (key, tokens, updated_at, granted)
+VALUES (?1, ?2 - 1, ?3, 1)
+ON CONFLICT (key) DO UPDATE SET
+    tokens = CASE WHEN {refilled} >= 1 THEN {refilled} - 1 ELSE {refilled} END,
+    updated_at = MAX(updated_at, ?3),
+    granted = {refilled} >= 1
+RETURNING tokens, granted
+",
+            self.settings.rate_limit_table_name,
+        ))
+        .bind(key)
+        .bind(rate.amount() as f64)
+        .bind(now.timestamp_millis())
+        .bind(rate.tokens_per_milli())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        match granted {
+            true => Ok(Permit::Granted),
+            false => Ok(Permit::Denied {
+                retry_at: rate.next_token_at(tokens, now),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jobfire_core::services::rate_limit::RateLimiter;
+
+    use super::*;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let store = SqliteRateLimitStore::new(pool, SqliteStorageSettings::default())
+            .await
+            .unwrap();
+        let rate_limiter = RateLimiter::new(store);
+        let rate = Rate::per_second(2).unwrap();
+
+        for _ in 0..2 {
+            let permit = rate_limiter.try_acquire("test", rate, at(0)).await.unwrap();
+            assert_eq!(permit, Permit::Granted);
+        }
+        let permit = rate_limiter
+            .try_acquire("test", rate, at(100))
+            .await
+            .unwrap();
+        assert_eq!(permit, Permit::Denied { retry_at: at(500) });
+
+        let permit = rate_limiter
+            .try_acquire("test", rate, at(500))
+            .await
+            .unwrap();
+        assert_eq!(permit, Permit::Granted);
+        let permit = rate_limiter
+            .try_acquire("other", rate, at(500))
+            .await
+            .unwrap();
+        assert_eq!(permit, Permit::Granted);
+    }
+
+    #[tokio::test]
+    async fn test_shared_between_stores() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}?mode=rwc", dir.path().join("jobs.db").display());
+        let rate = Rate::per_second(1).unwrap();
+
+        let first = SqliteRateLimitStore::new(
+            SqlitePool::connect(&url).await.unwrap(),
+            SqliteStorageSettings::default(),
+        )
+        .await
+        .unwrap();
+        let second = SqliteRateLimitStore::new(
+            SqlitePool::connect(&url).await.unwrap(),
+            SqliteStorageSettings::default(),
+        )
+        .await
+        .unwrap();
+
+        let permit = first.try_acquire("test", rate, at(0)).await.unwrap();
+        assert_eq!(permit, Permit::Granted);
+        let permit = second.try_acquire("test", rate, at(0)).await.unwrap();
+        assert_eq!(permit, Permit::Denied { retry_at: at(1000) });
+    }
+}
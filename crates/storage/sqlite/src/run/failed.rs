@@ -1,13 +1,17 @@
 use async_trait::async_trait;
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use jobfire_core::{
-    domain::run::{failed::FailedRun, id::RunId},
-    storage::{self, run::FailedRunRepo},
+    domain::{
+        job::id::JobId,
+        run::{failed::FailedRun, id::RunId},
+    },
+    storage::{self, query::Filter, run::FailedRunRepo},
 };
-use sqlx::SqlitePool;
+use sqlx::{Executor, Sqlite, SqlitePool};
 
-use crate::{SqliteStorageSettings, map_sqlx_error};
+use crate::{SqliteStorageSettings, add_column_if_missing, map_sqlx_error, query::list_query};
 
+#[derive(Clone)]
 pub struct SqliteFailedRunRepo {
     pool: SqlitePool,
     settings: SqliteStorageSettings,
@@ -34,26 +38,71 @@ CREATE TABLE IF NOT EXISTS {} (
         .execute(pool)
         .await?;
 
+        add_column_if_missing(
+            pool,
+            &settings.failed_run_table_name,
+            "started_at",
+            "INTEGER",
+        )
+        .await?;
+
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {0}_job_id ON {0} (job_id, finished_at)",
+            settings.failed_run_table_name,
+        ))
+        .execute(pool)
+        .await?;
+
         Ok(())
     }
 }
 
+#[derive(sqlx::FromRow)]
+struct Row {
+    run_id: String,
+    job_id: String,
+    scheduled_at: i64,
+    started_at: Option<i64>,
+    finished_at: i64,
+    error: String,
+}
+
+impl Row {
+    fn into_run(self) -> storage::error::Result<FailedRun> {
+        let run = FailedRun::new(
+            self.run_id
+                .parse()
+                .map_err(|_| storage::error::Error::Internal)?,
+            self.job_id
+                .parse()
+                .map_err(|_| storage::error::Error::Internal)?,
+            DateTime::from_timestamp_millis(self.scheduled_at)
+                .ok_or(storage::error::Error::Internal)?,
+            DateTime::from_timestamp_millis(self.finished_at)
+                .ok_or(storage::error::Error::Internal)?,
+            serde_json::from_str(&self.error).map_err(|_| storage::error::Error::Internal)?,
+        );
+
+        match self.started_at {
+            Some(started_at) => Ok(run.with_started_at(
+                DateTime::from_timestamp_millis(started_at)
+                    .ok_or(storage::error::Error::Internal)?,
+            )),
+            None => Ok(run),
+        }
+    }
+}
+
 #[async_trait]
 impl FailedRunRepo for SqliteFailedRunRepo {
     async fn get(&self, run_id: &RunId) -> storage::error::Result<Option<FailedRun>> {
-        #[derive(sqlx::FromRow)]
-        struct RGet {
-            job_id: String,
-            scheduled_at: i64,
-            finished_at: i64,
-            error: String,
-        }
-
-        let result: Option<RGet> = sqlx::query_as(&format!(
+        let result: Option<Row> = sqlx::query_as(&format!(
             "
 SELECT
+    run_id,
     job_id,
     scheduled_at,
+    started_at,
     finished_at,
     error
 FROM {}
@@ -65,51 +114,190 @@ WHERE run_id = ?",
         .await
         .map_err(map_sqlx_error)?;
 
-        match result {
-            Some(result) => Ok(Some(FailedRun::new(
-                *run_id,
-                result
-                    .job_id
-                    .parse()
-                    .map_err(|_| storage::error::Error::Internal)?,
-                DateTime::from_timestamp_millis(result.scheduled_at)
-                    .ok_or(storage::error::Error::Internal)?,
-                DateTime::from_timestamp_millis(result.finished_at)
-                    .ok_or(storage::error::Error::Internal)?,
-                serde_json::from_str(&result.error).map_err(|_| storage::error::Error::Internal)?,
-            ))),
-            None => Ok(None),
-        }
+        result.map(Row::into_run).transpose()
     }
 
     async fn add(&self, run: FailedRun) -> storage::error::Result<()> {
-        let existing_run = self.get(&run.run_id()).await?;
-        if existing_run.is_some() {
-            return Err(storage::error::Error::AlreadyExists);
-        }
+        insert_failed_run(&self.pool, &self.settings, &run).await
+    }
 
-        sqlx::query(&format!(
-            "
+    async fn list(&self, filter: &Filter) -> storage::error::Result<Vec<FailedRun>> {
+        let rows = list_query(
+            &format!(
+                "SELECT run_id, job_id, scheduled_at, started_at, finished_at, error FROM {}",
+                self.settings.failed_run_table_name
+            ),
+            &self.settings,
+            filter,
+            None,
+            "job_id",
+            "finished_at",
+            "finished_at DESC, run_id DESC",
+        )
+        .build_query_as::<Row>()
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        rows.into_iter().map(Row::into_run).collect()
+    }
+
+    async fn get_by_job_id(&self, job_id: &JobId) -> storage::error::Result<Vec<FailedRun>> {
+        let rows = sqlx::query_as::<_, Row>(&format!(
+            "SELECT run_id, job_id, scheduled_at, started_at, finished_at, error FROM {} WHERE job_id = ? ORDER BY finished_at, run_id",
+            self.settings.failed_run_table_name
+        ))
+        .bind(job_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        rows.into_iter().map(Row::into_run).collect()
+    }
+
+    async fn delete_finished_before(
+        &self,
+        finished_before: DateTime<Utc>,
+    ) -> storage::error::Result<usize> {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {} WHERE finished_at < ?",
+            self.settings.failed_run_table_name
+        ))
+        .bind(finished_before.timestamp_millis())
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+/// Inserts a failed run, the primary key rejects duplicates.
+pub(crate) async fn insert_failed_run<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    run: &FailedRun,
+) -> storage::error::Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(&format!(
+        "
 INSERT INTO {} (
     job_id,
     run_id,
     scheduled_at,
+    started_at,
     finished_at,
     error
 )
 VALUES
-(?, ?, ?, ?, ?)",
-            self.settings.failed_run_table_name,
-        ))
-        .bind(run.job_id().to_string())
-        .bind(run.run_id().to_string())
-        .bind(run.scheduled_at().timestamp_millis())
-        .bind(run.finished_at().timestamp_millis())
-        .bind(serde_json::to_string(run.error()).map_err(|_| storage::error::Error::Internal)?)
-        .execute(&self.pool)
-        .await
-        .map_err(map_sqlx_error)?;
+(?, ?, ?, ?, ?, ?)",
+        settings.failed_run_table_name,
+    ))
+    .bind(run.job_id().to_string())
+    .bind(run.run_id().to_string())
+    .bind(run.scheduled_at().timestamp_millis())
+    .bind(
+        run.started_at()
+            .map(|started_at| started_at.timestamp_millis()),
+    )
+    .bind(run.finished_at().timestamp_millis())
+    .bind(serde_json::to_string(run.error()).map_err(|_| storage::error::Error::Internal)?)
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_error)?;
 
-        Ok(())
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use jobfire_core::{domain::job::error::JobError, storage::Storage};
+
+    use super::*;
+    use crate::SqliteStorage;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_started_at_round_trip() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let repo = SqliteFailedRunRepo::new(pool, SqliteStorageSettings::default())
+            .await
+            .unwrap();
+        let started = FailedRun::new(
+            RunId::default(),
+            JobId::default(),
+            at(0),
+            at(2),
+            JobError::Orphaned,
+        )
+        .with_started_at(at(1));
+        let not_started = FailedRun::new(
+            RunId::default(),
+            JobId::default(),
+            at(0),
+            at(2),
+            JobError::JobCancelled,
+        );
+        repo.add(started.clone()).await.unwrap();
+        repo.add(not_started.clone()).await.unwrap();
+
+        let retrieved = repo.get(&started.run_id()).await.unwrap().unwrap();
+        assert_eq!(retrieved.started_at(), Some(at(1)));
+        let retrieved = repo.get(&not_started.run_id()).await.unwrap().unwrap();
+        assert_eq!(retrieved.started_at(), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_failed_runs_by_job_id() {
+        let sqlite_storage = SqliteStorage::new_in_memory().await;
+        let storage = Storage::from(sqlite_storage.clone());
+        let job_id = JobId::default();
+        let mut run_ids = Vec::new();
+        for finished_at in [2, 1, 3] {
+            let run = FailedRun::new(
+                RunId::default(),
+                job_id,
+                at(0),
+                at(finished_at),
+                JobError::Orphaned,
+            );
+            run_ids.push(run.run_id());
+            storage.failed_run_repo().add(run).await.unwrap();
+        }
+        storage
+            .failed_run_repo()
+            .add(FailedRun::new(
+                RunId::default(),
+                JobId::default(),
+                at(0),
+                at(0),
+                JobError::Orphaned,
+            ))
+            .await
+            .unwrap();
+
+        let runs = storage
+            .failed_run_repo()
+            .get_by_job_id(&job_id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|run| run.run_id())
+            .collect::<Vec<_>>();
+        assert_eq!(runs, vec![run_ids[1], run_ids[0], run_ids[2]]);
+
+        let (_, _, _, plan): (i64, i64, i64, String) = sqlx::query_as(
+            "EXPLAIN QUERY PLAN SELECT run_id FROM jobfire_failed_run WHERE job_id = ? ORDER BY finished_at",
+        )
+        .bind(job_id.to_string())
+        .fetch_one(sqlite_storage.pool())
+        .await
+        .unwrap();
+        assert!(plan.contains("jobfire_failed_run_job_id"));
     }
 }
@@ -1,13 +1,17 @@
 use async_trait::async_trait;
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use jobfire_core::{
-    domain::run::{id::RunId, successful::SuccessfulRun},
-    storage::{self, run::SuccessfulRunRepo},
+    domain::{
+        job::{id::JobId, report::Report},
+        run::{id::RunId, successful::SuccessfulRun},
+    },
+    storage::{self, query::Filter, run::SuccessfulRunRepo},
 };
-use sqlx::SqlitePool;
+use sqlx::{Executor, Sqlite, SqlitePool};
 
-use crate::{SqliteStorageSettings, map_sqlx_error};
+use crate::{SqliteStorageSettings, add_column_if_missing, map_sqlx_error, query::list_query};
 
+#[derive(Clone)]
 pub struct SqliteSuccessfulRunRepo {
     pool: SqlitePool,
     settings: SqliteStorageSettings,
@@ -35,28 +39,98 @@ CREATE TABLE IF NOT EXISTS {} (
         .execute(pool)
         .await?;
 
+        // `report` held the whole report before output and metrics got their own columns
+        add_column_if_missing(
+            pool,
+            &settings.successful_run_table_name,
+            "output",
+            "TEXT NOT NULL DEFAULT 'null'",
+        )
+        .await?;
+        add_column_if_missing(
+            pool,
+            &settings.successful_run_table_name,
+            "metrics",
+            "TEXT NOT NULL DEFAULT '{}'",
+        )
+        .await?;
+        add_column_if_missing(
+            pool,
+            &settings.successful_run_table_name,
+            "started_at",
+            "INTEGER",
+        )
+        .await?;
+
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {0}_job_id ON {0} (job_id, finished_at)",
+            settings.successful_run_table_name,
+        ))
+        .execute(pool)
+        .await?;
+
         Ok(())
     }
 }
 
+#[derive(sqlx::FromRow)]
+struct Row {
+    run_id: String,
+    job_id: String,
+    scheduled_at: i64,
+    started_at: Option<i64>,
+    finished_at: i64,
+    output: String,
+    metrics: String,
+}
+
+impl Row {
+    fn into_run(self) -> storage::error::Result<SuccessfulRun> {
+        let run = SuccessfulRun::new(
+            self.run_id
+                .parse()
+                .map_err(|_| storage::error::Error::Internal)?,
+            self.job_id
+                .parse()
+                .map_err(|_| storage::error::Error::Internal)?,
+            DateTime::from_timestamp_millis(self.scheduled_at)
+                .ok_or(storage::error::Error::Internal)?,
+            DateTime::from_timestamp_millis(self.finished_at)
+                .ok_or(storage::error::Error::Internal)?,
+            Report::new()
+                .with_raw_output(
+                    serde_json::from_str(&self.output)
+                        .map_err(|_| storage::error::Error::Internal)?,
+                )
+                .with_metrics(
+                    serde_json::from_str(&self.metrics)
+                        .map_err(|_| storage::error::Error::Internal)?,
+                ),
+        );
+
+        match self.started_at {
+            Some(started_at) => Ok(run.with_started_at(
+                DateTime::from_timestamp_millis(started_at)
+                    .ok_or(storage::error::Error::Internal)?,
+            )),
+            None => Ok(run),
+        }
+    }
+}
+
 #[async_trait]
 impl SuccessfulRunRepo for SqliteSuccessfulRunRepo {
     async fn get(&self, run_id: &RunId) -> storage::error::Result<Option<SuccessfulRun>> {
-        #[derive(sqlx::FromRow)]
-        struct RGet {
-            job_id: String,
-            scheduled_at: i64,
-            finished_at: i64,
-            report: String,
-        }
-
-        let result: Option<RGet> = sqlx::query_as(&format!(
+        let result: Option<Row> = sqlx::query_as(&format!(
             "
 SELECT
+    run_id,
     job_id,
     scheduled_at,
+    started_at,
     finished_at,
-    report
+    output,
+    metrics
 FROM {}
 WHERE run_id = ?
 ",
@@ -67,50 +141,187 @@ WHERE run_id = ?
         .await
         .map_err(map_sqlx_error)?;
 
-        match result {
-            Some(row) => Ok(Some(SuccessfulRun::new(
-                *run_id,
-                row.job_id
-                    .parse()
-                    .map_err(|_| storage::error::Error::Internal)?,
-                DateTime::from_timestamp_millis(row.scheduled_at)
-                    .ok_or(storage::error::Error::Internal)?,
-                DateTime::from_timestamp_millis(row.finished_at)
-                    .ok_or(storage::error::Error::Internal)?,
-                serde_json::from_str(&row.report).map_err(|_| storage::error::Error::Internal)?,
-            ))),
-            None => Ok(None),
-        }
+        result.map(Row::into_run).transpose()
     }
 
     async fn add(&self, run: SuccessfulRun) -> storage::error::Result<()> {
-        let existing = self.get(&run.run_id()).await?;
-        if existing.is_some() {
-            return Err(storage::error::Error::AlreadyExists);
-        }
+        insert_successful_run(&self.pool, &self.settings, &run).await
+    }
 
-        sqlx::query(&format!(
-            "
+    async fn list(&self, filter: &Filter) -> storage::error::Result<Vec<SuccessfulRun>> {
+        let rows = list_query(
+            &format!(
+                "SELECT run_id, job_id, scheduled_at, started_at, finished_at, output, metrics FROM {}",
+                self.settings.successful_run_table_name
+            ),
+            &self.settings,
+            filter,
+            None,
+            "job_id",
+            "finished_at",
+            "finished_at DESC, run_id DESC",
+        )
+        .build_query_as::<Row>()
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        rows.into_iter().map(Row::into_run).collect()
+    }
+
+    async fn get_by_job_id(&self, job_id: &JobId) -> storage::error::Result<Vec<SuccessfulRun>> {
+        let rows = sqlx::query_as::<_, Row>(&format!(
+            "SELECT run_id, job_id, scheduled_at, started_at, finished_at, output, metrics FROM {} WHERE job_id = ? ORDER BY finished_at, run_id",
+            self.settings.successful_run_table_name
+        ))
+        .bind(job_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        rows.into_iter().map(Row::into_run).collect()
+    }
+
+    async fn delete_finished_before(
+        &self,
+        finished_before: DateTime<Utc>,
+    ) -> storage::error::Result<usize> {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {} WHERE finished_at < ?",
+            self.settings.successful_run_table_name
+        ))
+        .bind(finished_before.timestamp_millis())
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+/// Inserts a successful run, the primary key rejects duplicates.
+pub(crate) async fn insert_successful_run<'e, E>(
+    executor: E,
+    settings: &SqliteStorageSettings,
+    run: &SuccessfulRun,
+) -> storage::error::Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(&format!(
+        "
 INSERT INTO {} (
     run_id,
     job_id,
     scheduled_at,
+    started_at,
     finished_at,
-    report
+    report,
+    output,
+    metrics
 )
-VALUES (?, ?, ?, ?, ?)
+VALUES (?, ?, ?, ?, ?, '{{}}', ?, ?)
 ",
-            self.settings.successful_run_table_name,
+        settings.successful_run_table_name,
+    ))
+    .bind(run.run_id().to_string())
+    .bind(run.job_id().to_string())
+    .bind(run.scheduled_at().timestamp_millis())
+    .bind(
+        run.started_at()
+            .map(|started_at| started_at.timestamp_millis()),
+    )
+    .bind(run.finished_at().timestamp_millis())
+    .bind(
+        serde_json::to_string(run.report().raw_output())
+            .map_err(|_| storage::error::Error::Internal)?,
+    )
+    .bind(
+        serde_json::to_string(run.report().metrics())
+            .map_err(|_| storage::error::Error::Internal)?,
+    )
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use serde_json::json;
+
+    use super::*;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_report_round_trip() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let repo = SqliteSuccessfulRunRepo::new(pool, SqliteStorageSettings::default())
+            .await
+            .unwrap();
+        let report = Report::new()
+            .with_output(&vec![1, 2, 3])
+            .unwrap()
+            .with_item_count(3)
+            .with_duration(Duration::seconds(2))
+            .with_metric("source", "import");
+        let run = SuccessfulRun::new(RunId::default(), JobId::default(), at(0), at(2), report)
+            .with_started_at(at(1));
+
+        repo.add(run.clone()).await.unwrap();
+
+        let retrieved = repo.get(&run.run_id()).await.unwrap().unwrap();
+        assert_eq!(retrieved.started_at(), Some(at(1)));
+        let report = retrieved.report();
+        assert_eq!(report.output::<Vec<i32>>().unwrap(), vec![1, 2, 3]);
+        assert_eq!(report.metrics().item_count(), Some(3));
+        assert_eq!(report.metrics().duration(), Some(Duration::seconds(2)));
+        assert_eq!(report.metrics().get("source"), Some(&json!("import")));
+    }
+
+    #[tokio::test]
+    async fn test_init_adds_report_columns() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        let settings = SqliteStorageSettings::default();
+        sqlx::query(&format!(
+            "CREATE TABLE {} (run_id TEXT NOT NULL PRIMARY KEY, job_id TEXT NOT NULL, scheduled_at INTEGER NOT NULL, finished_at INTEGER NOT NULL, report TEXT NOT NULL)",
+            settings.successful_run_table_name
         ))
-        .bind(run.run_id().to_string())
-        .bind(run.job_id().to_string())
-        .bind(run.scheduled_at().timestamp_millis())
-        .bind(run.finished_at().timestamp_millis())
-        .bind(serde_json::to_string(run.report()).map_err(|_| storage::error::Error::Internal)?)
-        .execute(&self.pool)
+        .execute(&pool)
         .await
-        .map_err(map_sqlx_error)?;
+        .unwrap();
+        let run_id = RunId::default();
+        sqlx::query(&format!(
+            "INSERT INTO {} (run_id, job_id, scheduled_at, finished_at, report) VALUES (?, ?, 1, 2, '{{}}')",
+            settings.successful_run_table_name
+        ))
+        .bind(run_id.to_string())
+        .bind(JobId::default().to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
 
-        Ok(())
+        let repo = SqliteSuccessfulRunRepo::new(pool, settings).await.unwrap();
+
+        let retrieved = repo.get(&run_id).await.unwrap().unwrap();
+        assert!(retrieved.report().raw_output().is_null());
+        assert_eq!(retrieved.report().metrics().item_count(), None);
+        assert_eq!(retrieved.started_at(), None);
+
+        let run = SuccessfulRun::new(
+            RunId::default(),
+            JobId::default(),
+            at(1),
+            at(2),
+            Report::new().with_raw_output(json!("done")),
+        );
+        repo.add(run.clone()).await.unwrap();
+        let retrieved = repo.get(&run.run_id()).await.unwrap().unwrap();
+        assert_eq!(retrieved.report().output::<String>().unwrap(), "done");
     }
 }
@@ -0,0 +1,198 @@
+use jobfire_core::{
+    async_trait,
+    storage::{
+        self,
+        transaction::{Operation, Transaction, TransactionRepo},
+    },
+};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    SqliteStorageSettings,
+    job::{
+        delete_finished_job, insert_job,
+        pending::{delete_pending_job, insert_pending_job},
+        running::{delete_expired_running_job, delete_running_job_of_run},
+        update_job_policies,
+    },
+    map_sqlx_error,
+    run::{failed::insert_failed_run, successful::insert_successful_run},
+};
+
+/// Applies transactions in a single SQLite transaction.
+#[derive(Clone)]
+pub struct SqliteTransactionRepo {
+    pool: SqlitePool,
+    settings: SqliteStorageSettings,
+}
+
+impl SqliteTransactionRepo {
+    pub fn new(pool: SqlitePool, settings: SqliteStorageSettings) -> Self {
+        Self { pool, settings }
+    }
+}
+
+/// Applies a single operation on a connection, usually inside of a transaction.
+pub(crate) async fn apply(
+    connection: &mut SqliteConnection,
+    settings: &SqliteStorageSettings,
+    operation: &Operation,
+) -> storage::error::Result<()> {
+    match operation {
+        Operation::AddJob(job) => insert_job(connection, settings, job).await,
+        Operation::DeleteFinishedJob {
+            job_id,
+            finished_before,
+        } => delete_finished_job(connection, settings, job_id, *finished_before).await,
+        Operation::UpdatePolicies { job_id, policies } => {
+            update_job_policies(connection, settings, job_id, policies).await
+        }
+        Operation::AddPendingJob(pending_job) => {
+            insert_pending_job(connection, settings, pending_job).await
+        }
+        Operation::DeletePendingJob(job_id) => delete_pending_job(connection, settings, job_id)
+            .await
+            .map(|_| ()),
+        Operation::DeleteRunningJob { job_id, run_id } => {
+            delete_running_job_of_run(connection, settings, job_id, run_id).await
+        }
+        Operation::DeleteExpiredRunningJob {
+            job_id,
+            heartbeat_before,
+        } => delete_expired_running_job(connection, settings, job_id, *heartbeat_before)
+            .await?
+            .map(|_| ())
+            .ok_or(storage::error::Error::NotFound),
+        Operation::AddSuccessfulRun(run) => insert_successful_run(connection, settings, run).await,
+        Operation::AddFailedRun(run) => insert_failed_run(connection, settings, run).await,
+    }
+}
+
+#[async_trait]
+impl TransactionRepo for SqliteTransactionRepo {
+    async fn commit(&self, transaction: Transaction) -> storage::error::Result<()> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_error)?;
+
+        // dropping `tx` without committing rolls back operations applied so far
+        for operation in transaction.operations() {
+            apply(&mut tx, &self.settings, operation).await?;
+        }
+
+        tx.commit().await.map_err(map_sqlx_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use jobfire_core::{
+        domain::{
+            job::{
+                Job,
+                id::JobId,
+                r#impl::{JobImplName, SerializedJobImpl},
+                pending::PendingJob,
+                policy::{Policies, PolicyData},
+            },
+            run::id::RunId,
+        },
+        storage::Storage,
+    };
+    use serde_json::json;
+
+    use crate::SqliteStorage;
+
+    use super::*;
+
+    fn job() -> Job {
+        Job::new(
+            JobId::default(),
+            DateTime::from_timestamp_millis(1).unwrap(),
+            SerializedJobImpl::new(JobImplName::new("test"), json!(null)),
+            Policies::new(Vec::new(), PolicyData::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_commit() {
+        let storage = Storage::from(SqliteStorage::new_in_memory().await);
+        let job = job();
+
+        storage
+            .commit(
+                Transaction::default()
+                    .add_job(job.clone())
+                    .add_pending_job(PendingJob::new(job.id(), job.created_at())),
+            )
+            .await
+            .unwrap();
+
+        assert!(storage.job_repo().get(&job.id()).await.unwrap().is_some());
+        assert!(
+            storage
+                .pending_job_repo()
+                .get(&job.id())
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_rolls_back_on_error() {
+        let storage = Storage::from(SqliteStorage::new_in_memory().await);
+        let job = job();
+
+        let result = storage
+            .commit(
+                Transaction::default()
+                    .add_job(job.clone())
+                    .add_pending_job(PendingJob::new(job.id(), job.created_at()))
+                    .delete_running_job(job.id(), RunId::default()),
+            )
+            .await;
+
+        assert!(matches!(result, Err(storage::error::Error::NotFound)));
+        assert!(storage.job_repo().get(&job.id()).await.unwrap().is_none());
+        assert!(
+            storage
+                .pending_job_repo()
+                .get(&job.id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_delete_pending_job() {
+        let storage = Storage::from(SqliteStorage::new_in_memory().await);
+        let job = job();
+        storage
+            .commit(
+                Transaction::default()
+                    .add_job(job.clone())
+                    .add_pending_job(PendingJob::new(job.id(), job.created_at())),
+            )
+            .await
+            .unwrap();
+
+        storage
+            .commit(Transaction::default().delete_pending_job(job.id()))
+            .await
+            .unwrap();
+        let result = storage
+            .commit(Transaction::default().delete_pending_job(job.id()))
+            .await;
+
+        assert!(matches!(result, Err(storage::error::Error::NotFound)));
+        assert!(
+            storage
+                .pending_job_repo()
+                .get(&job.id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}